@@ -0,0 +1,139 @@
+use jiff::{
+    civil::{DateTime, Time},
+    tz::TimeZone,
+    SignedDuration, Timestamp,
+};
+
+/// A busy block from a calendar, as seconds since the UNIX epoch.
+pub struct Busy {
+    pub start: u64,
+    pub end: u64,
+    pub summary: String,
+}
+
+/// Parse the busy events from an iCalendar (ICS) file.
+///
+/// All-day and transparent (free) events are skipped.
+/// UTC times (`Z`) are read as is, times with a `TZID` in that time zone, and floating times in
+/// the `local` time zone. A `TZID` that is not a known time zone is read as local, with a warning
+/// returned alongside the events.
+pub fn parse_ics(ics: &str, local: &TimeZone) -> (Vec<Busy>, Vec<String>) {
+    // unfold continuation lines
+    let ics = ics
+        .replace("\r\n", "\n")
+        .replace("\n ", "")
+        .replace("\n\t", "");
+
+    let mut events = Vec::new();
+    let mut warnings = Vec::new();
+    let mut event: Option<(Option<u64>, Option<u64>, String, bool)> = None;
+    for line in ics.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let mut params = key.split(';');
+        let name = params.next().unwrap_or_default();
+        let tzid = params
+            .find_map(|p| p.strip_prefix("TZID="))
+            .map(|x| x.trim_matches('"'));
+        let mut time = || {
+            let tz = match tzid.map(|x| (x, TimeZone::get(x))) {
+                None => local.clone(),
+                Some((_, Ok(tz))) => tz,
+                Some((x, Err(_))) => {
+                    let warning =
+                        format!("unknown time zone '{x}', so its times are read as local");
+                    if !warnings.contains(&warning) {
+                        warnings.push(warning);
+                    }
+                    local.clone()
+                }
+            };
+            parse_time(value, tz)
+        };
+        match (name, &mut event) {
+            ("BEGIN", _) if value == "VEVENT" => event = Some((None, None, String::new(), false)),
+            ("END", Some(_)) if value == "VEVENT" => {
+                if let Some((Some(start), Some(end), summary, false)) = event.take() {
+                    events.push(Busy {
+                        start,
+                        end,
+                        summary,
+                    });
+                }
+            }
+            ("DTSTART", Some(e)) => e.0 = time(),
+            ("DTEND", Some(e)) => e.1 = time(),
+            ("SUMMARY", Some(e)) => e.2 = value.replace("\\,", ",").replace("\\;", ";"),
+            ("TRANSP", Some(e)) => e.3 = value == "TRANSPARENT",
+            _ => (),
+        }
+    }
+
+    events.sort_by_key(|e| e.start);
+    (events, warnings)
+}
+
+/// Parse an ICS date-time (`20240101T093000`) in the time zone, or in UTC if it ends in `Z`.
+/// Dates without a time are not busy blocks.
+fn parse_time(s: &str, tz: TimeZone) -> Option<u64> {
+    let (s, tz) = match s.strip_suffix('Z') {
+        Some(s) => (s, TimeZone::UTC),
+        None => (s, tz),
+    };
+    let time = DateTime::strptime("%Y%m%dT%H%M%S", s).ok()?;
+    let secs = time.to_zoned(tz).ok()?.timestamp().as_second();
+    u64::try_from(secs).ok()
+}
+
+/// The `local` day around `now`, as `(midnight, next midnight)` in seconds since the UNIX epoch.
+pub fn day(now: u64, local: &TimeZone) -> Option<(u64, u64)> {
+    let date = Timestamp::from_second(now.try_into().ok()?)
+        .ok()?
+        .to_zoned(local.clone())
+        .date();
+    let midnight = |d: jiff::civil::Date| {
+        let secs = d.to_zoned(local.clone()).ok()?.timestamp().as_second();
+        u64::try_from(secs).ok()
+    };
+    Some((midnight(date)?, midnight(date.tomorrow().ok()?)?))
+}
+
+/// The time `secs` after the `local` midnight starting at `midnight`, in seconds since the UNIX
+/// epoch, so working hours keep to the clock across daylight saving changes.
+pub fn at(midnight: u64, secs: u64, local: &TimeZone) -> Option<u64> {
+    let date = Timestamp::from_second(midnight.try_into().ok()?)
+        .ok()?
+        .to_zoned(local.clone())
+        .date();
+    let time = date
+        .to_datetime(Time::midnight())
+        .checked_add(SignedDuration::from_secs(secs.try_into().ok()?))
+        .ok()?;
+    let secs = time.to_zoned(local.clone()).ok()?.timestamp().as_second();
+    u64::try_from(secs).ok()
+}
+
+/// The `HH:MM` clock time in the `local` time zone.
+pub fn clock(secs: u64, local: &TimeZone) -> String {
+    i64::try_from(secs)
+        .ok()
+        .and_then(|x| Timestamp::from_second(x).ok())
+        .map(|x| x.to_zoned(local.clone()).strftime("%H:%M").to_string())
+        .unwrap_or_default()
+}
+
+/// Seconds within `from..to` which are not covered by a busy block.
+pub fn free_time(busy: &[Busy], from: u64, to: u64) -> u64 {
+    if from >= to {
+        return 0;
+    }
+    let mut free = 0;
+    let mut at = from;
+    for b in busy {
+        let start = b.start.clamp(at, to);
+        free += start - at;
+        at = at.max(b.end.min(to));
+    }
+    free + to.saturating_sub(at)
+}
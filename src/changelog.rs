@@ -0,0 +1,46 @@
+//! One-time notices of behaviour changes after upgrading, sourced from the embedded changelog.
+use crate::io;
+use colored::Colorize;
+use miette::*;
+use std::path::Path;
+
+const CHANGELOG: &str = include_str!("../CHANGELOG.md");
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+fn parse_version(v: &str) -> Option<Vec<u64>> {
+    v.trim().split('.').map(|x| x.parse().ok()).collect()
+}
+
+/// The sections of `changelog` for versions after `seen`, up to and including `current`.
+///
+/// Sections not headed by a version, such as `Unreleased`, are never shown.
+pub fn since<'a>(changelog: &'a str, seen: &str, current: &str) -> Vec<(&'a str, &'a str)> {
+    let seen = parse_version(seen).unwrap_or_default();
+    let current = parse_version(current).unwrap_or_default();
+    changelog
+        .split("\n## ")
+        .skip(1)
+        .filter_map(|section| section.split_once('\n'))
+        .map(|(version, notes)| (version.trim(), notes.trim()))
+        .filter(|(version, _)| parse_version(version).is_some_and(|v| v > seen && v <= current))
+        .collect()
+}
+
+/// Print what changed since the version last run, once, and record this version as seen.
+///
+/// A fresh data directory has nothing to migrate, so just records the version.
+pub fn notify(dir: &Path) -> Result<()> {
+    let seen = match io::read_seen_version(dir) {
+        Some(v) if v == VERSION => return Ok(()),
+        Some(v) => v,
+        None if io::open_tasks_file(dir).exists() => "0.0.0".to_string(),
+        None => return io::write_seen_version(dir, VERSION),
+    };
+
+    for (version, notes) in since(CHANGELOG, &seen, VERSION) {
+        eprintln!("{}", format!("📣 What changed in ivly {version}").bold());
+        eprintln!("{notes}");
+        eprintln!();
+    }
+    io::write_seen_version(dir, VERSION)
+}
@@ -0,0 +1,294 @@
+use crate::{
+    stats::DAY,
+    task::{IdScheme, Task, TodoTask},
+};
+use std::time::Duration;
+
+/// User configuration, stored as `config.ron` in the ivly directory.
+///
+/// All fields are optional in the file; missing fields take their default.
+#[derive(serde::Deserialize, serde::Serialize, Default)]
+#[serde(default)]
+pub struct Config {
+    /// Ask for confirmation when `finish` is given no task number.
+    pub confirm_finish: bool,
+    /// Ring the terminal bell when a task is finished.
+    pub finish_bell: bool,
+    /// Celebrate when the last of the priority tasks is finished.
+    pub celebrate: bool,
+    /// Show the current completion streak under the priority tasks.
+    pub show_streak: bool,
+    /// How many priority tasks are shown, defaulting to six.
+    pub top: Option<usize>,
+    /// Preview this many backlog tasks under the priority tasks.
+    pub peek: usize,
+    /// How much of the list is printed after a command changes a task.
+    pub after_change: AfterChange,
+    /// Keep to six unfinished tasks, refusing to add more (also set by `IVLY_STRICT=1`).
+    pub strict: bool,
+    /// How much estimated work fits in a day (eg `6h`).
+    pub daily_capacity: Option<String>,
+    /// Open tasks older than this are flagged as stale (eg `3 weeks`), defaulting to 14 days.
+    pub stale_after: Option<String>,
+    /// The local start and end of the working day (eg `("09:00", "17:00")`), used by `agenda`.
+    pub work_hours: Option<(String, String)>,
+    /// Rules applied to new tasks.
+    pub rules: Vec<Rule>,
+    /// Warnings about the descriptions of new tasks.
+    pub hints: Hints,
+    /// Tags changed on tasks as they are swept into the done list.
+    pub sweep_tags: SweepTags,
+    /// Tags removed from open tasks when the day or week is over.
+    pub temporal_tags: TemporalTags,
+    /// What happens on the first run of a new UTC day.
+    pub rollover: Rollover,
+    /// Colours used by the interactive TUI.
+    pub theme: Theme,
+    /// How new task IDs are generated.
+    pub ids: IdScheme,
+}
+
+/// How much of the list is printed after a command changes a task.
+#[derive(serde::Deserialize, serde::Serialize, Copy, Clone, Default, PartialEq, Eq, Debug)]
+pub enum AfterChange {
+    /// Only the confirmation line.
+    Nothing,
+    /// Only the changed task.
+    Task,
+    /// This many of the tasks shown, followed by the changed task if it is further down.
+    Top(usize),
+    /// As many of the tasks shown as `top` sets, followed by the changed task if it is further down.
+    #[default]
+    Shown,
+}
+
+/// Colours used by the interactive TUI.
+///
+/// Colours are names (`dark gray`), indices (`236`), or hex (`#303030`).
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Theme {
+    /// Background of every second row.
+    /// `auto` picks a shade based on the terminal background; unset disables striping.
+    pub stripe: Option<String>,
+    /// Foreground of the selected row. If neither highlight colour is set, the row is reversed.
+    pub highlight_fg: Option<String>,
+    /// Background of the selected row.
+    pub highlight_bg: Option<String>,
+    /// Colour of text being edited.
+    pub editing: String,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            stripe: None,
+            highlight_fg: None,
+            highlight_bg: None,
+            editing: "yellow".to_string(),
+        }
+    }
+}
+
+impl Config {
+    pub fn capacity(&self) -> Option<Duration> {
+        let x = self.daily_capacity.as_deref()?;
+        humantime::parse_duration(x)
+            .map_err(|e| eprintln!("⚠️ Invalid daily_capacity '{x}': {e}"))
+            .ok()
+    }
+
+    /// How many priority tasks are shown and planned, defaulting to six.
+    pub fn top(&self) -> usize {
+        self.top.unwrap_or(6)
+    }
+
+    /// Strict mode is on in the config, or with `IVLY_STRICT=1`.
+    pub fn strict(&self) -> bool {
+        self.strict || std::env::var("IVLY_STRICT").is_ok_and(|x| x == "1")
+    }
+
+    pub fn stale_after(&self) -> Duration {
+        let default = Duration::from_secs(14 * DAY);
+        match self.stale_after.as_deref() {
+            Some(x) => humantime::parse_duration(x).unwrap_or_else(|e| {
+                eprintln!("⚠️ Invalid stale_after '{x}': {e}");
+                default
+            }),
+            None => default,
+        }
+    }
+
+    /// The workspace the first matching rule with one routes a new task to.
+    pub fn route(&self, description: &str) -> Option<&str> {
+        self.rules
+            .iter()
+            .filter(|r| r.workspace.is_some())
+            .find(|r| r.matches(description))
+            .and_then(|r| r.workspace.as_deref())
+    }
+
+    /// The working day as seconds after midnight, defaulting to 9am to 5pm.
+    pub fn work_hours(&self) -> (u64, u64) {
+        let parse = |x: &str| {
+            let (h, m) = x.split_once(':')?;
+            Some(h.parse::<u64>().ok()? * 3600 + m.parse::<u64>().ok()? * 60)
+        };
+        let default = (9 * 3600, 17 * 3600);
+        match &self.work_hours {
+            Some((start, end)) => match (parse(start), parse(end)) {
+                (Some(s), Some(e)) => (s, e),
+                _ => {
+                    eprintln!("⚠️ Invalid work_hours, expected HH:MM");
+                    default
+                }
+            },
+            None => default,
+        }
+    }
+}
+
+/// Tags changed on tasks as they are swept, so temporal tags (`today`) stay out of the history.
+#[derive(serde::Deserialize, serde::Serialize, Default)]
+#[serde(default)]
+pub struct SweepTags {
+    /// Tags added to swept tasks.
+    pub add: Vec<String>,
+    /// Tags removed from swept tasks.
+    pub remove: Vec<String>,
+}
+
+impl SweepTags {
+    pub fn apply<S>(&self, task: &mut Task<S>) {
+        for tag in &self.remove {
+            task.remove_tag(tag);
+        }
+        for tag in &self.add {
+            task.add_tag(tag.as_str());
+        }
+    }
+}
+
+/// Tags which expire, removed by the first run of a new UTC day or week.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct TemporalTags {
+    /// Removed at midnight.
+    pub day: Vec<String>,
+    /// Removed at the end of the week (Sunday midnight).
+    pub week: Vec<String>,
+}
+
+impl Default for TemporalTags {
+    fn default() -> Self {
+        Self {
+            day: vec!["today".to_string()],
+            week: vec!["this-week".to_string()],
+        }
+    }
+}
+
+/// Steps run on the first run of a new UTC day.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Rollover {
+    /// Sweep the finished tasks into the done list.
+    pub sweep: bool,
+    /// Move the tasks queued for tomorrow to the top of the open list.
+    pub promote_tomorrow: bool,
+    /// Greet the new day over the priority tasks.
+    pub banner: bool,
+}
+
+impl Default for Rollover {
+    fn default() -> Self {
+        Self {
+            sweep: true,
+            promote_tomorrow: true,
+            banner: true,
+        }
+    }
+}
+
+/// Non-blocking warnings about vague or overlong task descriptions.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Hints {
+    /// Warn when a description is longer than this many characters.
+    pub max_length: Option<usize>,
+    /// Warn when a description is just one of these words.
+    pub vague_words: Vec<String>,
+}
+
+impl Default for Hints {
+    fn default() -> Self {
+        Self {
+            max_length: Some(80),
+            vague_words: ["stuff", "things", "misc", "work", "todo", "tasks", "admin"]
+                .map(String::from)
+                .to_vec(),
+        }
+    }
+}
+
+impl Hints {
+    /// The warnings for a description, if any.
+    pub fn check(&self, description: &str) -> Vec<String> {
+        let mut warnings = Vec::new();
+        let len = description.chars().count();
+        if let Some(max) = self.max_length.filter(|max| len > *max) {
+            warnings.push(format!(
+                "The description is {len} characters, over {max}; keep the task to one clear action"
+            ));
+        }
+        let word = description.trim().trim_end_matches(['.', '!']);
+        if self
+            .vague_words
+            .iter()
+            .any(|w| w.eq_ignore_ascii_case(word))
+        {
+            warnings.push(format!(
+                "'{word}' is vague; describe what finishing the task looks like"
+            ));
+        }
+        warnings
+    }
+}
+
+/// A rule applied to a task when it is added.
+///
+/// Rules cannot set due dates, as tasks have none; tag with `today` or `this-week` instead.
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct Rule {
+    /// Regular expression matched against the task description.
+    #[serde(rename = "match")]
+    pub pattern: String,
+    /// Tags to add to matching tasks.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// The workspace matching tasks are added to, rather than the one in use.
+    #[serde(default)]
+    pub workspace: Option<String>,
+}
+
+impl Rule {
+    /// Whether the description matches the rule's pattern.
+    fn matches(&self, description: &str) -> bool {
+        match regex::Regex::new(&self.pattern) {
+            Ok(re) => re.is_match(description),
+            Err(e) => {
+                eprintln!("⚠️ Skipping rule with invalid pattern: {e}");
+                false
+            }
+        }
+    }
+
+    /// Apply the rule to the task if its description matches.
+    pub fn apply(&self, task: &mut TodoTask) {
+        if self.matches(&task.description) {
+            for tag in &self.tags {
+                task.add_tag(tag.as_str());
+            }
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! A semantic diff between two versions of the open tasks.
+use crate::task::{TodoTask, TodoTasks};
+use std::collections::HashMap;
+
+/// A change to the open tasks. Task numbers are 1-based.
+#[derive(Debug, PartialEq)]
+pub enum Change {
+    Added {
+        num: usize,
+        description: String,
+    },
+    Removed {
+        num: usize,
+        description: String,
+    },
+    Moved {
+        from: usize,
+        to: usize,
+        description: String,
+    },
+    Edited {
+        num: usize,
+        description: String,
+        fields: Vec<&'static str>,
+    },
+}
+
+/// The changes from `old` to `new`, matching tasks by ID.
+///
+/// Only tasks which moved relative to the others are reported as moved, so moving one task
+/// does not report every task it passed.
+pub fn diff(old: &TodoTasks, new: &TodoTasks) -> Vec<Change> {
+    let index = |tasks: &TodoTasks| {
+        tasks
+            .iter()
+            .enumerate()
+            .map(|(i, t)| (t.id().to_string(), i))
+            .collect::<HashMap<_, _>>()
+    };
+    let (old_at, new_at) = (index(old), index(new));
+    let mut changes = Vec::new();
+
+    for (i, t) in old.iter().enumerate() {
+        if !new_at.contains_key(t.id()) {
+            changes.push(Change::Removed {
+                num: i + 1,
+                description: t.description.clone(),
+            });
+        }
+    }
+
+    // the old index of each new task, if it was there
+    let was = new
+        .iter()
+        .map(|t| old_at.get(t.id()).copied())
+        .collect::<Vec<_>>();
+    let common = was.iter().flatten().copied().collect::<Vec<_>>();
+    let mut stayed = vec![false; common.len()];
+    for k in longest_increasing(&common) {
+        stayed[k] = true;
+    }
+
+    let mut k = 0;
+    for (j, t) in new.iter().enumerate() {
+        let Some(i) = was[j] else {
+            changes.push(Change::Added {
+                num: j + 1,
+                description: t.description.clone(),
+            });
+            continue;
+        };
+        if !stayed[k] {
+            changes.push(Change::Moved {
+                from: i + 1,
+                to: j + 1,
+                description: t.description.clone(),
+            });
+        }
+        k += 1;
+        let fields = edited_fields(&old[i], t);
+        if !fields.is_empty() {
+            changes.push(Change::Edited {
+                num: j + 1,
+                description: t.description.clone(),
+                fields,
+            });
+        }
+    }
+
+    changes
+}
+
+/// A field which differs between two versions of a task, with its values in RON.
+pub struct Edit {
+    pub field: &'static str,
+    pub old: String,
+    pub new: String,
+}
+
+/// The fields which differ between the tasks.
+pub fn edits(a: &TodoTask, b: &TodoTask) -> Vec<Edit> {
+    fn edit<T: serde::Serialize + PartialEq>(field: &'static str, a: T, b: T) -> Option<Edit> {
+        let ron = |x: &T| ron::to_string(x).unwrap_or_default();
+        (a != b).then(|| Edit {
+            field,
+            old: ron(&a),
+            new: ron(&b),
+        })
+    }
+    let waiting = |t: &TodoTask| t.waiting().map(|w| (w.on.clone(), w.until));
+    [
+        edit("description", &a.description, &b.description),
+        edit("note", &a.note, &b.note),
+        edit("tags", &a.tags, &b.tags),
+        edit("contexts", &a.contexts, &b.contexts),
+        edit("assignee", &a.assignee, &b.assignee),
+        edit("estimate", &a.estimate, &b.estimate),
+        edit("priority", &a.priority, &b.priority),
+        edit("checklist", &a.checklist, &b.checklist),
+        edit("energy", &a.energy, &b.energy),
+        edit("links", &a.links, &b.links),
+        edit("finished", a.is_finished(), b.is_finished()),
+        edit("waiting", waiting(a), waiting(b)),
+        edit("snoozed", a.snoozed_until(), b.snoozed_until()),
+        edit("pinned", a.is_pinned(), b.is_pinned()),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// The names of the fields which differ between the tasks.
+fn edited_fields(a: &TodoTask, b: &TodoTask) -> Vec<&'static str> {
+    edits(a, b).into_iter().map(|e| e.field).collect()
+}
+
+/// Positions of a longest strictly increasing subsequence, in O(n log n).
+fn longest_increasing(xs: &[usize]) -> Vec<usize> {
+    // tails[l] is the position of the smallest value ending a run of length l + 1,
+    // prev[i] the position before i in its run
+    let mut tails: Vec<usize> = Vec::new();
+    let mut prev = vec![None; xs.len()];
+    for (i, &x) in xs.iter().enumerate() {
+        let l = tails.partition_point(|&t| xs[t] < x);
+        prev[i] = l.checked_sub(1).map(|l| tails[l]);
+        match tails.get_mut(l) {
+            Some(t) => *t = i,
+            None => tails.push(i),
+        }
+    }
+
+    let mut at = tails.last().copied();
+    let mut seq = Vec::new();
+    while let Some(i) = at {
+        seq.push(i);
+        at = prev[i];
+    }
+    seq.reverse();
+    seq
+}
@@ -0,0 +1,89 @@
+//! Exporting the tasks in other formats.
+use crate::task::{DoneTasks, Tasks, TodoTasks, TrashedTasks};
+use miette::*;
+
+/// What to export.
+#[derive(clap::ValueEnum, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    /// Every task, open, done, and trashed, in one RON document that can be imported back.
+    Ron,
+    /// A small, shareable SVG card of the week's completed tasks and streak.
+    SvgSummary,
+}
+
+/// The week's progress shown on a summary card.
+pub struct Summary<'a> {
+    /// The Monday the week starts, as `YYYY-MM-DD`.
+    pub week_of: String,
+    pub completed: usize,
+    pub streak: u64,
+    /// The completed tasks' descriptions, if they are to be shown.
+    pub titles: Option<&'a [String]>,
+}
+
+/// Render the summary as an SVG card.
+pub fn svg_summary(summary: &Summary) -> String {
+    const WIDTH: usize = 400;
+    const LINE: usize = 22;
+    let titles = summary.titles.unwrap_or_default();
+    let height = 150 + titles.len() * LINE;
+
+    let mut svg = format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{WIDTH}" height="{height}" viewBox="0 0 {WIDTH} {height}" font-family="sans-serif">
+  <rect width="100%" height="100%" rx="16" fill="#1e1e2e"/>
+  <text x="24" y="40" font-size="16" fill="#a6adc8">ivly · week of {}</text>
+  <text x="24" y="92" font-size="40" font-weight="bold" fill="#a6e3a1">{} done</text>
+  <text x="24" y="126" font-size="18" fill="#fab387">🔥 {} day streak</text>
+"##,
+        summary.week_of, summary.completed, summary.streak
+    );
+    for (i, title) in titles.iter().enumerate() {
+        svg += &format!(
+            "  <text x=\"24\" y=\"{}\" font-size=\"14\" fill=\"#cdd6f4\">✔ {}</text>\n",
+            160 + i * LINE,
+            escape(title)
+        );
+    }
+    svg += "</svg>\n";
+    svg
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Every task, as exported with `--format ron`.
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct Backup {
+    pub open: TodoTasks,
+    pub done: DoneTasks,
+    #[serde(default = "Tasks::new")]
+    pub trash: TrashedTasks,
+}
+
+/// Serialise the backup.
+pub fn ron(backup: &Backup) -> Result<String> {
+    ron::ser::to_string_pretty(backup, Default::default())
+        .into_diagnostic()
+        .wrap_err("failed to serialise the tasks")
+}
+
+/// Parse a backup.
+pub fn parse(text: &str) -> Result<Backup> {
+    ron::from_str(text)
+        .into_diagnostic()
+        .wrap_err("failed to parse the tasks")
+}
+
+/// Check an exported backup reads back to exactly the same tasks.
+pub fn verify(exported: &str) -> Result<()> {
+    let backup = parse(exported)?;
+    ensure!(
+        ron(&backup)? == exported,
+        "the export does not read back to the same tasks"
+    );
+    Ok(())
+}
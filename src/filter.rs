@@ -0,0 +1,134 @@
+use crate::{
+    tags::FilterTag,
+    task::{Energy, Priority, State, Task},
+};
+use clap::ValueEnum;
+use std::{ops::Deref, str::FromStr, time::UNIX_EPOCH};
+
+/// The order tasks are listed in, rather than their saved order.
+#[derive(clap::ValueEnum, Copy, Clone, PartialEq, Eq)]
+pub enum Sort {
+    /// Most carried over first.
+    Carried,
+}
+
+/// Filters shared by commands which operate on a subset of tasks.
+#[derive(clap::Args, Clone, Default)]
+pub struct Filter {
+    /// Filter by tags and contexts.
+    /// `+` to include tag, `@` to include context.
+    /// `/` to exclude tag, `/@` to exclude context.
+    pub tags: Vec<FilterTag>,
+    /// Only tasks created at or after this time.
+    /// Accepts a duration ago (`7d`) or a UTC date (`2024-01-01`).
+    #[clap(long)]
+    pub created_since: Option<Moment>,
+    /// Only tasks created before this time.
+    #[clap(long)]
+    pub created_before: Option<Moment>,
+    /// Only tasks finished at or after this time.
+    #[clap(long)]
+    pub finished_since: Option<Moment>,
+    /// Only tasks finished before this time.
+    #[clap(long)]
+    pub finished_before: Option<Moment>,
+    /// Only tasks whose description or note matches this regular expression.
+    #[clap(long("match"), value_name = "REGEX")]
+    pub pattern: Option<regex::Regex>,
+    /// Only tasks with this priority.
+    #[clap(long, value_enum)]
+    pub priority: Option<Priority>,
+    /// Only tasks needing this energy.
+    #[clap(long, value_enum)]
+    pub energy: Option<Energy>,
+    /// Only tasks in this project.
+    #[clap(long)]
+    pub project: Option<String>,
+}
+
+impl Filter {
+    /// Check if the task passes all the filters.
+    pub fn matches<S: State>(&self, task: &Task<S>) -> bool {
+        let created = task.created();
+        let finished = task.finished_at();
+
+        self.tags.iter().all(|f| f.filter(task))
+            && self.created_since.is_none_or(|m| created >= m.0)
+            && self.created_before.is_none_or(|m| created < m.0)
+            && self
+                .finished_since
+                .is_none_or(|m| finished.is_some_and(|f| f >= m.0))
+            && self
+                .finished_before
+                .is_none_or(|m| finished.is_some_and(|f| f < m.0))
+            && self
+                .pattern
+                .as_ref()
+                .is_none_or(|re| re.is_match(&task.description) || re.is_match(&task.note))
+            && self.priority.is_none_or(|p| task.priority == p)
+            && self.energy.is_none_or(|e| task.energy == Some(e))
+            && self
+                .project
+                .as_ref()
+                .is_none_or(|p| task.project.as_ref() == Some(p))
+    }
+
+    /// A short description of each filter set, such as `+work` or `created since 2024-01-01`.
+    pub fn describe(&self) -> Vec<String> {
+        let name = |x: Option<clap::builder::PossibleValue>| {
+            x.map(|x| x.get_name().to_string()).unwrap_or_default()
+        };
+        let moments = [
+            ("created since", self.created_since),
+            ("created before", self.created_before),
+            ("finished since", self.finished_since),
+            ("finished before", self.finished_before),
+        ];
+
+        let tags = self.tags.iter().map(|t| match t {
+            FilterTag::Add(x) => format!("+{}", x.deref()),
+            FilterTag::Neg(x) => format!("/{}", x.deref()),
+            FilterTag::Context(x) => format!("@{}", x.deref()),
+            FilterTag::NegContext(x) => format!("/@{}", x.deref()),
+        });
+        let moments = moments
+            .into_iter()
+            .filter_map(|(s, m)| m.map(|m| format!("{s} {}", crate::date(m.0))));
+        tags.chain(moments)
+            .chain(self.pattern.iter().map(|re| format!("matching '{re}'")))
+            .chain(
+                self.priority
+                    .map(|p| format!("{} priority", name(p.to_possible_value()))),
+            )
+            .chain(
+                self.energy
+                    .map(|e| format!("{} energy", name(e.to_possible_value()))),
+            )
+            .chain(self.project.iter().map(|p| format!("project {p}")))
+            .collect()
+    }
+}
+
+/// A point in time, as seconds since the UNIX epoch.
+#[derive(Copy, Clone)]
+pub struct Moment(pub u64);
+
+impl FromStr for Moment {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(d) = humantime::parse_duration(s) {
+            return Ok(Moment(crate::now().saturating_sub(d.as_secs())));
+        }
+
+        let s = if s.len() == "2024-01-01".len() {
+            format!("{s}T00:00:00")
+        } else {
+            s.to_string()
+        };
+        humantime::parse_rfc3339_weak(&s)
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| Moment(d.as_secs()))
+            .ok_or_else(|| format!("'{s}' is not a duration (eg 7d) or date (eg 2024-01-01)"))
+    }
+}
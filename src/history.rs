@@ -0,0 +1,211 @@
+//! An append-only log of what happened to each task, kept in `history.jsonl`.
+//!
+//! Each line is one event in JSON, so logging only appends a line.
+use crate::diff::{self, Change};
+use crate::task::{DoneTasks, Task, Tasks, TodoTasks, TrashedTasks};
+use miette::*;
+use std::{
+    collections::{HashMap, HashSet},
+    io::Write,
+    path::Path,
+};
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct Event {
+    /// Seconds since UNIX epoch.
+    pub time: u64,
+    /// The task ID.
+    pub task: String,
+    pub description: String,
+    /// What happened, eg `added` or `edited note: "" → "call first"`.
+    pub change: String,
+    /// The command line which made the change, if known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub command: Option<String>,
+}
+
+fn history_file(dir: &Path) -> std::path::PathBuf {
+    dir.join("history.jsonl")
+}
+
+/// All the events logged, oldest first.
+pub fn read(dir: &Path) -> Vec<Event> {
+    std::fs::read_to_string(history_file(dir))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|x| serde_json::from_str(x).ok())
+        .collect()
+}
+
+/// Log the changes `command` made to the tasks in `file`, from its contents `old` to `new`.
+pub fn log_file(dir: &Path, command: &str, file: &str, old: Option<&str>, new: &str) -> Result<()> {
+    let changes = match file {
+        "open.ron" => open_changes(&parse(old), &parse(Some(new))),
+        "done.ron" => done_changes(&parse(old), &parse(Some(new))),
+        "trash.ron" => trash_changes(&parse(old), &parse(Some(new))),
+        _ => return Ok(()),
+    };
+    log(dir, command, changes)
+}
+
+/// Append the changes, as `(task ID, description, change)`, to the log.
+fn log(dir: &Path, command: &str, changes: Vec<(String, String, String)>) -> Result<()> {
+    if changes.is_empty() {
+        return Ok(());
+    }
+    let time = crate::now();
+    let command = Some(command.to_string());
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(history_file(dir))
+        .into_diagnostic()
+        .wrap_err("failed to open history.jsonl")?;
+    for (task, description, change) in changes {
+        let event = Event {
+            time,
+            task,
+            description,
+            change,
+            command: command.clone(),
+        };
+        let line = serde_json::to_string(&event)
+            .into_diagnostic()
+            .wrap_err("failed to serialise history")?;
+        writeln!(file, "{line}").into_diagnostic()?;
+    }
+    Ok(())
+}
+
+/// The changes from the open tasks `old` to `new`.
+fn open_changes(old: &TodoTasks, new: &TodoTasks) -> Vec<(String, String, String)> {
+    let was = old.iter().map(|t| (t.id(), t)).collect::<HashMap<_, _>>();
+    let reids = reids(old, new);
+    diff::diff(old, new)
+        .into_iter()
+        .filter(|c| match c {
+            Change::Removed { num, .. } => !reids.values().any(|id| *id == old[num - 1].id()),
+            _ => true,
+        })
+        .map(|c| match c {
+            Change::Added { num, description } => {
+                let id = new[num - 1].id();
+                let change = match reids.get(id) {
+                    Some(was) => format!("re-IDed from {was}"),
+                    None => "added".into(),
+                };
+                (id.to_string(), description, change)
+            }
+            Change::Removed { num, description } => (
+                old[num - 1].id().to_string(),
+                description,
+                "removed from the open list".into(),
+            ),
+            Change::Moved {
+                from,
+                to,
+                description,
+            } => (
+                new[to - 1].id().to_string(),
+                description,
+                format!("moved from {from} to {to}"),
+            ),
+            Change::Edited {
+                num,
+                description,
+                fields,
+            } => {
+                let t = &new[num - 1];
+                let change = match fields.as_slice() {
+                    ["finished"] if t.is_finished() => "finished".into(),
+                    ["finished"] => "unfinished".into(),
+                    _ => {
+                        let edits = diff::edits(was[t.id()], t)
+                            .into_iter()
+                            .map(|e| format!("{}: {} → {}", e.field, e.old, e.new))
+                            .collect::<Vec<_>>();
+                        format!("edited {}", edits.join(", "))
+                    }
+                };
+                (t.id().to_string(), description, change)
+            }
+        })
+        .collect()
+}
+
+/// The tasks added to or removed from the done list.
+fn done_changes(old: &DoneTasks, new: &DoneTasks) -> Vec<(String, String, String)> {
+    let (old_ids, new_ids) = (ids(old), ids(new));
+    let reids = reids(old, new);
+    let mut changes = Vec::new();
+    for t in new.iter().filter(|t| !old_ids.contains(t.id())) {
+        let change = match reids.get(t.id()) {
+            Some(was) => format!("re-IDed from {was}"),
+            None if t.is_cancelled() => "cancelled".into(),
+            None => "completed".into(),
+        };
+        changes.push((t.id().to_string(), t.description.clone(), change));
+    }
+    let reided = reids.into_values().collect::<HashSet<_>>();
+    for t in old
+        .iter()
+        .filter(|t| !new_ids.contains(t.id()) && !reided.contains(t.id()))
+    {
+        let change = "removed from the done list".into();
+        changes.push((t.id().to_string(), t.description.clone(), change));
+    }
+    changes
+}
+
+/// The tasks added to or removed from the trash.
+fn trash_changes(old: &TrashedTasks, new: &TrashedTasks) -> Vec<(String, String, String)> {
+    let (old_ids, new_ids) = (ids(old), ids(new));
+    let mut changes = Vec::new();
+    for t in new.iter().filter(|t| !old_ids.contains(t.id())) {
+        changes.push((t.id().to_string(), t.description.clone(), "trashed".into()));
+    }
+    for t in old.iter().filter(|t| !new_ids.contains(t.id())) {
+        let change = "taken out of the trash".into();
+        changes.push((t.id().to_string(), t.description.clone(), change));
+    }
+    changes
+}
+
+/// The tasks given a fresh ID, as `purge` does to tasks sharing one, mapped to their old ID.
+///
+/// A task is taken to be re-IDed when its ID is new, and a task with the same description
+/// had an ID now held by fewer tasks.
+fn reids<'a, S>(old: &'a Tasks<S>, new: &'a Tasks<S>) -> HashMap<&'a str, &'a str> {
+    let count = |tasks: &'a Tasks<S>| {
+        let mut n = HashMap::<&str, usize>::new();
+        for t in tasks.iter() {
+            *n.entry(t.id()).or_default() += 1;
+        }
+        n
+    };
+    let (was, now) = (count(old), count(new));
+    let mut freed = old
+        .iter()
+        .filter(|t| now.get(t.id()).copied().unwrap_or_default() < was[t.id()])
+        .collect::<Vec<_>>();
+    let mut reids = HashMap::new();
+    for t in new.iter().filter(|t| !was.contains_key(t.id())) {
+        if let Some(i) = freed.iter().position(|o| o.description == t.description) {
+            reids.insert(t.id(), freed.remove(i).id());
+        }
+    }
+    reids
+}
+
+fn ids<S>(tasks: &[Task<S>]) -> HashSet<&str> {
+    tasks.iter().map(|t| t.id()).collect()
+}
+
+fn parse<S>(x: Option<&str>) -> Tasks<S>
+where
+    Tasks<S>: serde::de::DeserializeOwned,
+{
+    x.and_then(|x| ron::from_str(x).ok())
+        .unwrap_or_else(Tasks::new)
+}
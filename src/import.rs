@@ -0,0 +1,141 @@
+//! Importing tasks from other formats.
+use crate::task::{Ids, Item, TodoTask};
+
+/// What to import from.
+#[derive(clap::ValueEnum, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    /// A RON backup, as written by `ivly export --format ron`.
+    Ron,
+    /// A markdown checklist of `- [ ]` and `- [x]` items.
+    Md,
+}
+
+/// Parse the `- [ ]`/`- [x]` items of a markdown checklist into tasks.
+///
+/// Nested items become checklist items of the task above them, and the headings an item is under
+/// become its tags. Checked tasks are returned finished. Other lines, and anything in a fenced
+/// code block, are ignored.
+///
+/// Items nested more than one level deep are flattened into the checklist, with a warning for
+/// each returned alongside the tasks.
+///
+/// An item ending in `🆔 <id>`, as other tools write, keeps that ID unless another task has it.
+/// Other items are given new IDs from `ids`.
+pub fn md(text: &str, ids: &mut Ids) -> (Vec<TodoTask>, Vec<String>) {
+    let mut tasks: Vec<TodoTask> = Vec::new();
+    let mut warnings = Vec::new();
+    let mut headings: Vec<(usize, String)> = Vec::new();
+    let mut task_indent = None;
+    let mut item_indent = None;
+    let mut fence: Option<&str> = None;
+
+    for (n, line) in text.lines().enumerate() {
+        let trimmed = line.trim_start();
+        let marker = ["```", "~~~"].into_iter().find(|m| trimmed.starts_with(m));
+        match (fence, marker) {
+            (None, Some(m)) => {
+                fence = Some(m);
+                continue;
+            }
+            (Some(f), Some(m)) if f == m => {
+                fence = None;
+                continue;
+            }
+            (Some(_), _) => continue,
+            (None, None) => (),
+        }
+        if let Some((level, heading)) = heading(trimmed) {
+            headings.retain(|(l, _)| *l < level);
+            headings.push((level, heading));
+            task_indent = None;
+            continue;
+        }
+
+        let Some((done, text)) = checkbox(trimmed) else {
+            continue;
+        };
+        let indent = line.len() - trimmed.len();
+        match (task_indent, tasks.last_mut()) {
+            (Some(parent), Some(task)) if indent > parent => {
+                let first = *item_indent.get_or_insert(indent);
+                if indent > first {
+                    warnings.push(format!(
+                        "line {}: '{text}' is nested more than one level deep, so is added to the checklist of '{}'",
+                        n + 1,
+                        task.description
+                    ));
+                }
+                task.checklist.push(Item {
+                    text: text.to_string(),
+                    done,
+                });
+            }
+            _ => {
+                let mut task = match upstream_id(text) {
+                    Some((text, id)) => {
+                        let mut task = TodoTask::new(text).with_id(id);
+                        ids.keep(&mut task);
+                        task
+                    }
+                    None => {
+                        let mut task = TodoTask::new(text);
+                        ids.assign(&mut task);
+                        task
+                    }
+                };
+                for (_, tag) in &headings {
+                    task.add_tag(tag.as_str());
+                }
+                if done {
+                    task.finish();
+                }
+                tasks.push(task);
+                task_indent = Some(indent);
+                item_indent = None;
+            }
+        }
+    }
+
+    (tasks, warnings)
+}
+
+/// A `#` heading's level and its text as a tag: lowercase, with spaces as `-`.
+fn heading(line: &str) -> Option<(usize, String)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    let text = line[level..].strip_prefix(' ')?.trim();
+    if level == 0 || text.is_empty() {
+        return None;
+    }
+    let tag = text
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_lowercase();
+    Some((level, tag))
+}
+
+/// The text before a trailing `🆔 <id>`, and the ID.
+fn upstream_id(text: &str) -> Option<(&str, &str)> {
+    let (text, id) = text.rsplit_once("🆔")?;
+    let (text, id) = (text.trim_end(), id.trim());
+    (!text.is_empty() && !id.is_empty() && !id.contains(char::is_whitespace)).then_some((text, id))
+}
+
+/// Whether a `- [ ]` line is checked, and its text.
+fn checkbox(line: &str) -> Option<(bool, &str)> {
+    let rest = line
+        .strip_prefix("- ")
+        .or_else(|| line.strip_prefix("* "))?;
+    let (done, text) = if let Some(x) = rest.strip_prefix("[ ]") {
+        (false, x)
+    } else if let Some(x) = rest
+        .strip_prefix("[x]")
+        .or_else(|| rest.strip_prefix("[X]"))
+    {
+        (true, x)
+    } else {
+        return None;
+    };
+    let text = text.trim();
+    (!text.is_empty()).then_some((done, text))
+}
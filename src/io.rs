@@ -1,66 +1,231 @@
 use crate::{
-    tags::Tags,
-    task::{DoneTasks, Tasks, TodoTasks},
+    config::Config,
+    stats::Badges,
+    tags::{Projects, Tags},
+    task::{Done, DoneTasks, Tasks, Todo, TodoTasks, TrashedTasks},
 };
 use miette::*;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+/// The file open tasks are saved to.
+pub fn open_tasks_file(dir: &Path) -> PathBuf {
+    dir.join("open.ron")
+}
 
 pub fn read_open_tasks(dir: &Path) -> TodoTasks {
-    let file = dir.join("open.ron");
-    let mut tasks = std::fs::read_to_string(file)
-        .ok()
-        .and_then(|x| ron::from_str(&x).ok());
-    if tasks.is_none() {
-        eprintln!("⚠️ Failed to read the saved tasks, opening backup tasks");
-        let file = dir.join("open.bak.ron");
-        tasks = std::fs::read_to_string(file)
+    read_tasks(dir, "open")
+}
+
+/// Read `<name>.ron`, falling back to the `<name>.bak.ron` backup.
+///
+/// A broken file is reported once per command by [`broken_task_files`], not here.
+fn read_tasks<S>(dir: &Path, name: &str) -> Tasks<S>
+where
+    Tasks<S>: serde::de::DeserializeOwned,
+{
+    let read = |file: String| {
+        std::fs::read_to_string(dir.join(file))
             .ok()
-            .and_then(|x| ron::from_str(&x).ok());
+            .and_then(|x| ron::from_str(&x).ok())
+    };
+    read(format!("{name}.ron"))
+        .or_else(|| read(format!("{name}.bak.ron")))
+        .unwrap_or_else(Tasks::new)
+}
+
+/// A task file which could not be read, so its backup is opened in its place.
+pub struct Broken {
+    /// The file name, without `.ron`.
+    pub name: &'static str,
+    /// What failed, or `None` if the file is missing.
+    pub error: Option<String>,
+    /// The backup's tasks, and the seconds since UNIX epoch it was saved.
+    pub backup: Option<(usize, Option<u64>)>,
+    /// How many tasks differ between the broken file and the backup.
+    pub differ: usize,
+}
+
+/// The open and done task files which cannot be read, where a backup was saved.
+///
+/// A broken file is kept as `<name>.broken.ron` as it is found, before any write replaces it.
+pub fn broken_task_files(dir: &Path) -> Vec<Broken> {
+    let ids = regex::Regex::new(r#"\bid:\s*"([^"]*)""#).expect("valid regex");
+    [
+        broken::<Todo>(dir, "open", &ids),
+        broken::<Done>(dir, "done", &ids),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+fn broken<S>(dir: &Path, name: &'static str, ids: &regex::Regex) -> Option<Broken>
+where
+    Tasks<S>: serde::de::DeserializeOwned,
+{
+    let file = dir.join(format!("{name}.ron"));
+    let bak = dir.join(format!("{name}.bak.ron"));
+    let text = std::fs::read_to_string(&file).ok();
+    let error = match text.as_deref().map(ron::from_str::<Tasks<S>>) {
+        Some(Ok(_)) => return None,
+        Some(Err(e)) => Some(e.to_string()),
+        // a missing file is only worth reporting if there was something to lose
+        None if !bak.exists() => return None,
+        None => None,
+    };
+    if let Some(text) = &text {
+        let kept = dir.join(format!("{name}.broken.ron"));
+        if let Err(e) = std::fs::write(&kept, text) {
+            eprintln!("⚠️ Failed to keep a copy of {name}.ron: {e}");
+        }
     }
 
-    tasks.unwrap_or_else(|| {
-        eprintln!("⚠️ No tasks saved, creating a new set");
-        Tasks::new()
+    let backup = std::fs::read_to_string(&bak)
+        .ok()
+        .and_then(|x| ron::from_str::<Tasks<S>>(&x).ok());
+    let text = text.unwrap_or_default();
+    let broken_ids = ids
+        .captures_iter(&text)
+        .map(|c| c[1].to_string())
+        .collect::<Vec<_>>();
+    let differ = backup.as_ref().map_or(broken_ids.len(), |backup| {
+        backup
+            .iter()
+            .filter(|t| !broken_ids.iter().any(|id| id == t.id()))
+            .count()
+            + broken_ids
+                .iter()
+                .filter(|id| !backup.iter().any(|t| t.id() == id.as_str()))
+                .count()
+    });
+    let saved = std::fs::metadata(&bak)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs());
+    Some(Broken {
+        name,
+        error,
+        backup: backup.map(|b| (b.len(), saved)),
+        differ,
     })
 }
 
+/// Replace a broken task file with its backup.
+pub fn restore_backup(dir: &Path, name: &str) -> Result<()> {
+    let bak = std::fs::read_to_string(dir.join(format!("{name}.bak.ron")))
+        .into_diagnostic()
+        .wrap_err("failed to read the backup")?;
+    write_file(dir, &format!("{name}.ron"), &bak)
+}
+
+/// Copy `<name>.ron` to its backup before `contents` is written over it, unless it is broken,
+/// so a broken file never replaces a good backup, or unchanged, so the backup stays a step behind.
+fn back_up<S>(dir: &Path, name: &str, contents: &str)
+where
+    Tasks<S>: serde::de::DeserializeOwned,
+{
+    let file = dir.join(format!("{name}.ron"));
+    let Ok(old) = std::fs::read_to_string(&file) else {
+        return;
+    };
+    if old != contents && ron::from_str::<Tasks<S>>(&old).is_ok() {
+        let _ = std::fs::copy(&file, dir.join(format!("{name}.bak.ron")));
+    }
+}
+
 pub fn write_open_tasks(dir: &Path, tasks: &TodoTasks) -> Result<()> {
-    let file_bak = dir.join("open.bak.ron");
-    let file = dir.join("open.ron");
-    let _ = std::fs::copy(&file, file_bak);
     let s = ron::ser::to_string_pretty(tasks, Default::default())
         .into_diagnostic()
         .wrap_err("failed to serialise open tasks")?;
-    std::fs::write(file, s.as_bytes()).into_diagnostic()
+    back_up::<Todo>(dir, "open", &s);
+    write_file(dir, "open.ron", &s)
 }
 
-pub fn read_done_tasks(dir: &Path) -> DoneTasks {
-    let file = dir.join("done.ron");
-    let mut tasks = std::fs::read_to_string(file)
-        .ok()
-        .and_then(|x| ron::from_str(&x).ok());
-    if tasks.is_none() {
-        eprintln!("⚠️ Failed to read the saved tasks, opening backup tasks");
-        let file = dir.join("done.bak.ron");
-        tasks = std::fs::read_to_string(file)
-            .ok()
-            .and_then(|x| ron::from_str(&x).ok());
+/// Write a file in the data directory, unless it already holds `contents`.
+///
+/// Every writer goes through here, so a command only touches the files it changed.
+fn write_file(dir: &Path, file: &str, contents: &str) -> Result<()> {
+    let path = dir.join(file);
+    if std::fs::read_to_string(&path).is_ok_and(|x| x == contents) {
+        return Ok(());
     }
+    std::fs::write(path, contents.as_bytes()).into_diagnostic()
+}
 
-    tasks.unwrap_or_else(|| {
-        eprintln!("⚠️ No tasks saved, creating a new set");
-        Tasks::new()
-    })
+pub fn read_done_tasks(dir: &Path) -> DoneTasks {
+    read_tasks(dir, "done")
 }
 
 pub fn write_done_tasks(dir: &Path, tasks: &DoneTasks) -> Result<()> {
-    let file_bak = dir.join("done.bak.ron");
-    let file = dir.join("done.ron");
-    let _ = std::fs::copy(&file, file_bak);
     let s = ron::ser::to_string_pretty(tasks, Default::default())
         .into_diagnostic()
         .wrap_err("failed to serialise done tasks")?;
-    std::fs::write(file, s.as_bytes()).into_diagnostic()
+    back_up::<Done>(dir, "done", &s);
+    write_file(dir, "done.ron", &s)
+}
+
+pub fn read_trash(dir: &Path) -> TrashedTasks {
+    let file = dir.join("trash.ron");
+    std::fs::read_to_string(file)
+        .ok()
+        .and_then(|x| ron::from_str(&x).ok())
+        .unwrap_or_else(Tasks::new)
+}
+
+pub fn write_trash(dir: &Path, tasks: &TrashedTasks) -> Result<()> {
+    let s = ron::ser::to_string_pretty(tasks, Default::default())
+        .into_diagnostic()
+        .wrap_err("failed to serialise trash")?;
+    write_file(dir, "trash.ron", &s)
+}
+
+/// Tasks queued for tomorrow.
+pub fn read_tomorrow(dir: &Path) -> TodoTasks {
+    let file = dir.join("tomorrow.ron");
+    std::fs::read_to_string(file)
+        .ok()
+        .and_then(|x| ron::from_str(&x).ok())
+        .unwrap_or_else(Tasks::new)
+}
+
+pub fn write_tomorrow(dir: &Path, tasks: &TodoTasks) -> Result<()> {
+    let s = ron::ser::to_string_pretty(tasks, Default::default())
+        .into_diagnostic()
+        .wrap_err("failed to serialise tomorrow's tasks")?;
+    write_file(dir, "tomorrow.ron", &s)
+}
+
+/// Quick captures waiting to be triaged.
+pub fn read_inbox(dir: &Path) -> TodoTasks {
+    let file = dir.join("inbox.ron");
+    std::fs::read_to_string(file)
+        .ok()
+        .and_then(|x| ron::from_str(&x).ok())
+        .unwrap_or_else(Tasks::new)
+}
+
+pub fn write_inbox(dir: &Path, tasks: &TodoTasks) -> Result<()> {
+    let s = ron::ser::to_string_pretty(tasks, Default::default())
+        .into_diagnostic()
+        .wrap_err("failed to serialise the inbox")?;
+    write_file(dir, "inbox.ron", &s)
+}
+
+/// The IDs of the locked plan's tasks, empty when the plan is not locked.
+pub fn read_plan_lock(dir: &Path) -> Vec<String> {
+    let file = dir.join("plan-lock.ron");
+    std::fs::read_to_string(file)
+        .ok()
+        .and_then(|x| ron::from_str(&x).ok())
+        .unwrap_or_default()
+}
+
+pub fn write_plan_lock(dir: &Path, ids: &[String]) -> Result<()> {
+    let s = ron::ser::to_string_pretty(ids, Default::default())
+        .into_diagnostic()
+        .wrap_err("failed to serialise plan lock")?;
+    write_file(dir, "plan-lock.ron", &s)
 }
 
 pub fn read_tags(dir: &Path) -> Tags {
@@ -72,11 +237,25 @@ pub fn read_tags(dir: &Path) -> Tags {
 }
 
 pub fn write_tags(dir: &Path, tags: &Tags) -> Result<()> {
-    let file = dir.join("tags.ron");
     let s = ron::ser::to_string_pretty(tags, Default::default())
         .into_diagnostic()
         .wrap_err("failed to serialise tags")?;
-    std::fs::write(file, s.as_bytes()).into_diagnostic()
+    write_file(dir, "tags.ron", &s)
+}
+
+pub fn read_projects(dir: &Path) -> Projects {
+    let file = dir.join("projects.ron");
+    std::fs::read_to_string(file)
+        .ok()
+        .and_then(|x| ron::from_str(&x).ok())
+        .unwrap_or_default()
+}
+
+pub fn write_projects(dir: &Path, projects: &Projects) -> Result<()> {
+    let s = ron::ser::to_string_pretty(projects, Default::default())
+        .into_diagnostic()
+        .wrap_err("failed to serialise projects")?;
+    write_file(dir, "projects.ron", &s)
 }
 
 pub fn read_last_tags(dir: &Path) -> Vec<String> {
@@ -88,9 +267,288 @@ pub fn read_last_tags(dir: &Path) -> Vec<String> {
 }
 
 pub fn write_last_tags(dir: &Path, tags: &[String]) -> Result<()> {
-    let file = dir.join("last-tags.ron");
     let s = ron::ser::to_string_pretty(tags, Default::default())
         .into_diagnostic()
         .wrap_err("failed to serialise tags")?;
-    std::fs::write(file, s.as_bytes()).into_diagnostic()
+    write_file(dir, "last-tags.ron", &s)
+}
+
+pub fn read_config(dir: &Path) -> Config {
+    let file = dir.join("config.ron");
+    match std::fs::read_to_string(file) {
+        Ok(x) => ron::from_str(&x).unwrap_or_else(|e| {
+            eprintln!("⚠️ Failed to read config.ron, using defaults: {e}");
+            Config::default()
+        }),
+        Err(_) => Config::default(),
+    }
+}
+
+/// The context tasks are shown for by default, if set.
+pub fn read_active_context(dir: &Path) -> Option<String> {
+    let file = dir.join("context.ron");
+    std::fs::read_to_string(file)
+        .ok()
+        .and_then(|x| ron::from_str(&x).ok())
+}
+
+pub fn write_active_context(dir: &Path, context: Option<&str>) -> Result<()> {
+    let file = dir.join("context.ron");
+    match context {
+        Some(context) => {
+            let s = ron::to_string(context)
+                .into_diagnostic()
+                .wrap_err("failed to serialise context")?;
+            write_file(dir, "context.ron", &s)
+        }
+        None if file.exists() => std::fs::remove_file(file).into_diagnostic(),
+        None => Ok(()),
+    }
+}
+
+/// The workspace used when `--workspace` is not given, if not the default.
+pub fn read_workspace(base: &Path) -> Option<String> {
+    let file = base.join("workspace.ron");
+    std::fs::read_to_string(file)
+        .ok()
+        .and_then(|x| ron::from_str(&x).ok())
+}
+
+pub fn write_workspace(base: &Path, workspace: Option<&str>) -> Result<()> {
+    let file = base.join("workspace.ron");
+    match workspace {
+        Some(workspace) => {
+            let s = ron::to_string(workspace)
+                .into_diagnostic()
+                .wrap_err("failed to serialise workspace")?;
+            write_file(base, "workspace.ron", &s)
+        }
+        None if file.exists() => std::fs::remove_file(file).into_diagnostic(),
+        None => Ok(()),
+    }
+}
+
+/// The directory a named workspace keeps its tasks in.
+///
+/// The `default` workspace is the base directory itself; others are under `workspaces`.
+pub fn workspace_dir(base: &Path, name: &str) -> Result<PathBuf> {
+    ensure!(
+        name.chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+            && !name.is_empty(),
+        help = "use letters, numbers, `-`, and `_`",
+        "invalid workspace name '{name}'"
+    );
+    Ok(match name {
+        "default" => base.to_path_buf(),
+        name => base.join("workspaces").join(name),
+    })
+}
+
+/// Move a removed workspace out of `workspaces`, into `removed-workspaces` in the data
+/// directory, returning where it went.
+///
+/// The name is stamped with the time, so a workspace removed twice keeps both copies.
+pub fn retire_workspace(base: &Path, name: &str) -> Result<PathBuf> {
+    let dir = workspace_dir(base, name)?;
+    let removed = base.join("removed-workspaces");
+    std::fs::create_dir_all(&removed).into_diagnostic()?;
+    let to = removed.join(format!("{name}-{}", crate::now()));
+    std::fs::rename(&dir, &to)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to move {}", dir.display()))?;
+    Ok(to)
+}
+
+/// The named workspaces, in alphabetical order, not including `default`.
+pub fn workspaces(base: &Path) -> Vec<String> {
+    let mut names = std::fs::read_dir(base.join("workspaces"))
+        .into_iter()
+        .flatten()
+        .filter_map(|e| {
+            let e = e.ok()?;
+            e.path()
+                .is_dir()
+                .then(|| e.file_name().to_str().map(String::from))
+                .flatten()
+        })
+        .collect::<Vec<_>>();
+    names.sort();
+    names
+}
+
+/// The UTC day (since the UNIX epoch) maintenance last ran.
+pub fn read_last_run(dir: &Path) -> Option<u64> {
+    let file = dir.join("last-run.ron");
+    std::fs::read_to_string(file)
+        .ok()
+        .and_then(|x| ron::from_str(&x).ok())
+}
+
+pub fn write_last_run(dir: &Path, day: u64) -> Result<()> {
+    write_file(dir, "last-run.ron", &day.to_string())
+}
+
+/// The ivly version whose changes were last shown.
+pub fn read_seen_version(dir: &Path) -> Option<String> {
+    let file = dir.join("version.ron");
+    std::fs::read_to_string(file)
+        .ok()
+        .and_then(|x| ron::from_str(&x).ok())
+}
+
+pub fn write_seen_version(dir: &Path, version: &str) -> Result<()> {
+    let s = ron::to_string(version)
+        .into_diagnostic()
+        .wrap_err("failed to serialise version")?;
+    write_file(dir, "version.ron", &s)
+}
+
+/// The IDs of the open tasks, in the order they were last numbered.
+pub fn read_rendered(dir: &Path) -> Option<Vec<String>> {
+    let file = dir.join("rendered.ron");
+    std::fs::read_to_string(file)
+        .ok()
+        .and_then(|x| ron::from_str(&x).ok())
+}
+
+/// Record the task numbers as they were just shown.
+///
+/// This only guards against stale task numbers, so failing to record it is ignored.
+pub fn mark_rendered(dir: &Path, tasks: &TodoTasks) {
+    let ids = tasks.iter().map(|t| t.id()).collect::<Vec<_>>();
+    if let Ok(s) = ron::to_string(&ids) {
+        let _ = write_file(dir, "rendered.ron", &s);
+    }
+}
+
+pub fn read_badges(dir: &Path) -> Badges {
+    let file = dir.join("badges.ron");
+    std::fs::read_to_string(file)
+        .ok()
+        .and_then(|x| ron::from_str(&x).ok())
+        .unwrap_or_default()
+}
+
+pub fn write_badges(dir: &Path, badges: &Badges) -> Result<()> {
+    let s = ron::ser::to_string_pretty(badges, Default::default())
+        .into_diagnostic()
+        .wrap_err("failed to serialise badges")?;
+    write_file(dir, "badges.ron", &s)
+}
+
+/// Open `text` in the user's editor (`$VISUAL`, `$EDITOR`, or `vi`), returning the edited text.
+pub fn edit_in_editor(text: &str) -> Result<String> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let mut args = editor.split_whitespace();
+    let program = args.next().ok_or_else(|| miette!("$EDITOR is empty"))?;
+
+    // a fresh file only this user can read, removed when dropped
+    let mut file = tempfile::Builder::new()
+        .prefix("ivly-note-")
+        .suffix(".md")
+        .tempfile()
+        .into_diagnostic()?;
+    std::io::Write::write_all(&mut file, text.as_bytes()).into_diagnostic()?;
+    let status = std::process::Command::new(program)
+        .args(args)
+        .arg(file.path())
+        .status()
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to run editor '{editor}'"));
+    // editors may replace the file rather than write to it, so it is read back by path
+    let edited = std::fs::read_to_string(file.path()).into_diagnostic();
+
+    ensure!(status?.success(), "editor '{editor}' exited with an error");
+    Ok(edited?.trim_end().to_string())
+}
+
+/// The directory a named snapshot is kept in.
+fn snapshot_dir(dir: &Path, name: &str) -> Result<PathBuf> {
+    ensure!(
+        !name.is_empty() && !name.starts_with('.') && !name.contains(['/', '\\']),
+        "invalid snapshot name '{name}'"
+    );
+    Ok(dir.join("snapshots").join(name))
+}
+
+/// The data files in `dir`, excluding backups, the undo and history logs, and the active workspace.
+fn data_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir).into_diagnostic()? {
+        let path = entry.into_diagnostic()?.path();
+        let name = path
+            .file_name()
+            .and_then(|x| x.to_str())
+            .unwrap_or_default();
+        let backup = name.ends_with(".bak.ron") || ["journal.ron", "workspace.ron"].contains(&name);
+        if path.is_file() && name.ends_with(".ron") && !backup {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+/// Copy the data files into the named snapshot, replacing it if it exists.
+pub fn save_snapshot(dir: &Path, name: &str) -> Result<()> {
+    let snap = snapshot_dir(dir, name)?;
+    if snap.exists() {
+        std::fs::remove_dir_all(&snap).into_diagnostic()?;
+    }
+    std::fs::create_dir_all(&snap).into_diagnostic()?;
+    for file in data_files(dir)? {
+        let to = snap.join(file.file_name().unwrap_or_default());
+        std::fs::copy(&file, to).into_diagnostic()?;
+    }
+    Ok(())
+}
+
+/// Replace the data files with those in the named snapshot.
+///
+/// The open and done tasks are backed up first, as with any other change.
+pub fn restore_snapshot(dir: &Path, name: &str) -> Result<()> {
+    let snap = snapshot_dir(dir, name)?;
+    ensure!(snap.is_dir(), "no snapshot named '{name}'");
+    for file in ["open", "done"] {
+        let _ = std::fs::copy(
+            dir.join(format!("{file}.ron")),
+            dir.join(format!("{file}.bak.ron")),
+        );
+    }
+    for file in data_files(dir)? {
+        std::fs::remove_file(file).into_diagnostic()?;
+    }
+    for file in data_files(&snap)? {
+        let to = dir.join(file.file_name().unwrap_or_default());
+        std::fs::copy(&file, to).into_diagnostic()?;
+    }
+    Ok(())
+}
+
+/// The snapshots saved, with the seconds since UNIX epoch each was taken, oldest first.
+pub fn snapshots(dir: &Path) -> Vec<(String, u64)> {
+    let mut snaps = std::fs::read_dir(dir.join("snapshots"))
+        .into_iter()
+        .flatten()
+        .filter_map(|e| {
+            let e = e.ok()?;
+            let taken = e.metadata().ok()?.modified().ok()?;
+            let taken = taken.duration_since(std::time::UNIX_EPOCH).ok()?.as_secs();
+            Some((e.file_name().to_str()?.to_string(), taken))
+        })
+        .collect::<Vec<_>>();
+    snaps.sort_by_key(|(_, taken)| *taken);
+    snaps
+}
+
+/// The open and done tasks saved in a snapshot.
+pub fn read_snapshot_tasks(dir: &Path, name: &str) -> Result<(TodoTasks, DoneTasks)> {
+    let snap = snapshot_dir(dir, name)?;
+    let read = |file: &str| std::fs::read_to_string(snap.join(file)).unwrap_or_default();
+    Ok((
+        ron::from_str(&read("open.ron")).unwrap_or_else(|_| Tasks::new()),
+        ron::from_str(&read("done.ron")).unwrap_or_else(|_| Tasks::new()),
+    ))
 }
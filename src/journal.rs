@@ -0,0 +1,169 @@
+//! A short history of the changes each command made to the task files, so it can be undone.
+//!
+//! A [`Session`] reads the task files as a command starts, and once the command is done,
+//! stores the files it changed as one entry, with their contents before and after.
+use miette::*;
+use std::path::{Path, PathBuf};
+
+/// How many commands can be undone.
+const KEEP: usize = 20;
+
+/// The files whose changes are recorded.
+const FILES: [&str; 9] = [
+    "open.ron",
+    "done.ron",
+    "trash.ron",
+    "tomorrow.ron",
+    "inbox.ron",
+    "plan-lock.ron",
+    "tags.ron",
+    "projects.ron",
+    "last-run.ron",
+];
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct Entry {
+    /// Nanoseconds since UNIX epoch the command started, identifying the entry.
+    id: u64,
+    /// The command line which made the changes.
+    pub command: String,
+    pub files: Vec<Change>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone)]
+pub struct Change {
+    pub file: String,
+    /// The contents before the command, if the file existed.
+    pub before: Option<String>,
+    pub after: String,
+}
+
+fn journal_file(dir: &Path) -> PathBuf {
+    dir.join("journal.ron")
+}
+
+fn read(dir: &Path) -> Vec<Entry> {
+    std::fs::read_to_string(journal_file(dir))
+        .ok()
+        .and_then(|x| ron::from_str(&x).ok())
+        .unwrap_or_default()
+}
+
+fn write(dir: &Path, entries: &[Entry]) -> Result<()> {
+    let s = ron::to_string(entries)
+        .into_diagnostic()
+        .wrap_err("failed to serialise journal")?;
+    std::fs::write(journal_file(dir), s).into_diagnostic()
+}
+
+/// The changes a command makes to the task files in a directory, from [`begin`](Self::begin)
+/// to [`end`](Self::end).
+pub struct Session {
+    dir: PathBuf,
+    entry: Entry,
+    /// The contents of [`FILES`] as the session began.
+    before: Vec<Option<String>>,
+}
+
+impl Session {
+    /// Start recording the changes made to the task files in `dir`, under `command`.
+    pub fn begin(dir: &Path, command: impl Into<String>) -> Self {
+        let id = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos() as u64;
+        Session {
+            dir: dir.to_path_buf(),
+            entry: Entry {
+                id,
+                command: command.into(),
+                files: Vec::new(),
+            },
+            before: FILES
+                .map(|f| std::fs::read_to_string(dir.join(f)).ok())
+                .to_vec(),
+        }
+    }
+
+    /// Store the files changed since the session began as one entry, and log their tasks'
+    /// history.
+    pub fn end(self) -> Result<()> {
+        let Session {
+            dir,
+            mut entry,
+            before,
+        } = self;
+        for (file, before) in FILES.into_iter().zip(before) {
+            // a removed file is left out, undo cannot bring it back
+            let Some(after) = std::fs::read_to_string(dir.join(file)).ok() else {
+                continue;
+            };
+            if before.as_deref() != Some(after.as_str()) {
+                log_history(&dir, &entry.command, file, before.as_deref(), &after);
+                entry.files.push(Change {
+                    file: file.to_string(),
+                    before,
+                    after,
+                });
+            }
+        }
+        if entry.files.is_empty() {
+            return Ok(());
+        }
+
+        let mut entries = read(&dir);
+        entries.push(entry);
+        let skip = entries.len().saturating_sub(KEEP);
+        write(&dir, &entries[skip..])
+    }
+}
+
+/// Log the history of the tasks in `file`, warning rather than failing, as it is only a record.
+fn log_history(dir: &Path, command: &str, file: &str, before: Option<&str>, after: &str) {
+    if let Err(e) = crate::history::log_file(dir, command, file, before, after) {
+        eprintln!("⚠️ Failed to log the history of {file}: {e}");
+    }
+}
+
+/// Revert the files changed by the most recent command, returning it.
+///
+/// Fails if a file has been changed since, unless `force` is set.
+pub fn undo(dir: &Path, force: bool) -> Result<Entry> {
+    let mut entries = read(dir);
+    let entry = entries.pop().ok_or_else(|| miette!("nothing to undo"))?;
+    for c in &entry.files {
+        let now = std::fs::read_to_string(dir.join(&c.file)).ok();
+        ensure!(
+            force || now.as_deref() == Some(c.after.as_str()),
+            "{} has changed since `{}`, use --force to undo anyway",
+            c.file,
+            entry.command
+        );
+    }
+
+    for c in &entry.files {
+        let file = dir.join(&c.file);
+        let now = std::fs::read_to_string(&file).ok();
+        log_history(
+            dir,
+            "ivly undo",
+            &c.file,
+            now.as_deref(),
+            c.before.as_deref().unwrap_or("[]"),
+        );
+        // keep the backups in step, so a removed file is not read from its backup
+        let backup = dir.join(c.file.replace(".ron", ".bak.ron"));
+        match &c.before {
+            Some(x) => {
+                let _ = std::fs::copy(&file, backup);
+                std::fs::write(file, x).into_diagnostic()?
+            }
+            None => {
+                let _ = std::fs::remove_file(backup);
+                std::fs::remove_file(file).into_diagnostic()?
+            }
+        }
+    }
+    write(dir, &entries)?;
+    Ok(entry)
+}
@@ -1,9 +1,23 @@
 #![warn(missing_docs)]
 #![doc = include_str!("../README.md")]
 
+mod agenda;
+mod changelog;
+mod config;
+mod diff;
+mod export;
+mod filter;
+mod history;
+mod import;
 mod io;
+mod journal;
+mod markdown;
 mod op;
 mod print;
+mod report;
+mod sed;
+mod similar;
+mod stats;
 mod tags;
 mod task;
 #[cfg(test)]
@@ -13,37 +27,126 @@ mod tui;
 use clap::{Parser, Subcommand};
 use colored::*;
 use miette::IntoDiagnostic;
-use std::time::Duration;
-use tags::{AddTag, FilterTag};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tags::{FilterTag, Label};
+use task::{Energy, EnergyChange, Priority, TaskRef, TodoTask};
 
 fn main() -> miette::Result<()> {
     let app = App::parse();
 
-    let dir = &if cfg!(debug_assertions) {
-        "./target/.ivly".to_string()
-    } else {
-        std::env::var("IVLY_DIR").unwrap_or_else(|_| {
-            std::env::var("HOME")
-                .map(|x| format!("{x}/.ivly"))
-                .unwrap_or_else(|_| String::from(".ivly"))
-        })
+    let base = &data_dir(app.dir.clone());
+    std::fs::create_dir_all(base).into_diagnostic()?;
+    if let Some(Cmd::Ws { ws }) = &app.cmd {
+        return match ws {
+            Ws::List => op::list_workspaces(base),
+            Ws::New { name } => op::new_workspace(base, name),
+            Ws::Use { name } => op::use_workspace(base, name),
+            Ws::Remove { name, force } => op::remove_workspace(base, name, *force),
+        };
+    }
+    let workspace = app.workspace.clone().or_else(|| io::read_workspace(base));
+    let dir = &op::open_workspace(base, workspace.as_deref())?;
+    op::check_task_files(dir)?;
+    let config = io::read_config(dir);
+    changelog::notify(dir)?;
+    // rolling over sweeps and reorders the list, so it only happens where no task numbers are given
+    let new_day = match app.cmd {
+        // best-effort, it should not stop the list being shown
+        None => maintain(dir).unwrap_or_else(|e| {
+            eprintln!("⚠️ Daily maintenance failed: {e}");
+            false
+        }),
+        Some(Cmd::Maintain) => maintain(dir)?,
+        _ => false,
     };
-    std::fs::create_dir_all(dir).into_diagnostic()?;
 
-    let dir: &std::path::Path = dir.as_ref();
+    let session = (!matches!(app.cmd, Some(Cmd::Undo { .. }))).then(|| {
+        let args = std::env::args().skip(1).collect::<Vec<_>>();
+        journal::Session::begin(dir, format!("ivly {}", args.join(" ")).trim_end())
+    });
+    let result = run(app, base, dir, &config, workspace, new_day);
+    if let Some(Err(e)) = session.map(journal::Session::end) {
+        eprintln!("⚠️ Failed to journal the changes, they cannot be undone: {e}");
+    }
+    result
+}
 
+/// Run the daily maintenance, journaled apart from the command so either can be undone.
+fn maintain(dir: &Path) -> miette::Result<bool> {
+    let session = journal::Session::begin(dir, "ivly maintain");
+    let new_day = op::maintain(dir);
+    session.end()?;
+    new_day
+}
+
+/// Run the command given on the command line.
+fn run(
+    app: App,
+    base: &Path,
+    dir: &Path,
+    config: &config::Config,
+    workspace: Option<String>,
+    new_day: bool,
+) -> miette::Result<()> {
     match app.cmd {
         None => {
             let tasks = io::read_open_tasks(dir);
             let tags = io::read_tags(dir);
-            let mut ts = tasks
+            let mut filters = app.tags.clone();
+            let explicit = filters
+                .iter()
+                .any(|f| matches!(f, FilterTag::Context(_) | FilterTag::NegContext(_)));
+            if let Some(context) = io::read_active_context(dir).filter(|_| !explicit) {
+                println!(
+                    "      {}",
+                    format!("📍 @{context}").truecolor(127, 127, 127)
+                );
+                filters.push(FilterTag::Context(tags::Context(context)));
+            }
+            if let Some(ws) = workspace.filter(|ws| ws != "default") {
+                println!("      {}", format!("🗂️ {ws}").truecolor(127, 127, 127));
+            }
+            let render = print::Render::to(&std::io::stdout()).stale_after(config.stale_after());
+            let projects = io::read_projects(dir);
+            let locked = io::read_plan_lock(dir);
+            let (plan, backlog): (Vec<_>, Vec<_>) = tasks
                 .iter()
                 .enumerate()
-                .filter(|(_, task)| app.tags.iter().all(|f| f.filter(task.tags())));
+                .filter(|(_, task)| !task.is_hidden())
+                .filter(|(_, task)| filters.iter().all(|f| f.filter(task)))
+                .partition(|(_, task)| locked.is_empty() || locked.iter().any(|x| x == task.id()));
+            // when unlocked, the six are simply the first shown
+            let (plan, mut ts) = if locked.is_empty() {
+                let mut ts = plan.into_iter();
+                (
+                    ts.by_ref().take(app.top.unwrap_or(config.top())).collect(),
+                    ts,
+                )
+            } else {
+                (plan, backlog.into_iter())
+            };
 
+            if new_day && config.rollover.banner {
+                let banner = match config.top() {
+                    6 => "☀️ Today's six".to_string(),
+                    n => format!("☀️ Today's {n}"),
+                };
+                println!("      {}", banner.bold());
+            }
+            if !locked.is_empty() {
+                println!("      {}", "🔒 plan locked".truecolor(127, 127, 127));
+            }
+            plan.iter().for_each(|(i, t)| {
+                print::todo_task(*i, t, &tags, &projects, render, std::io::stdout())
+            });
+            io::mark_rendered(dir, &tasks);
+            let peek = app.peek.unwrap_or(config.peek);
             ts.by_ref()
-                .take(6)
-                .for_each(|(i, t)| print::todo_task(i, t, &tags));
+                .take(peek)
+                .for_each(|(i, t)| print::peek(i, t, render, std::io::stdout()));
             let rem = ts.count();
             if rem > 0 {
                 println!();
@@ -54,33 +157,156 @@ fn main() -> miette::Result<()> {
                         .truecolor(127, 127, 127)
                 );
             }
+            let unfinished = plan.iter().filter(|(_, t)| !t.is_finished()).count();
+            if config.strict() && unfinished > 6 {
+                println!(
+                    "      {}",
+                    format!("⚠️ {unfinished} unfinished tasks are prioritised, strict mode keeps to six").yellow()
+                );
+            }
+            let estimates = plan
+                .iter()
+                .map(|(_, t)| t)
+                .filter(|t| !t.is_finished())
+                .filter_map(|t| t.estimate())
+                .collect::<Vec<_>>();
+            if !estimates.is_empty() {
+                print::remaining(
+                    estimates.into_iter().sum(),
+                    config.capacity(),
+                    render,
+                    std::io::stdout(),
+                );
+            }
+            if config.show_streak {
+                let days = stats::completion_days(&tasks, &io::read_done_tasks(dir));
+                let streak = stats::streaks(&days).current;
+                if streak > 0 {
+                    println!(
+                        "      {}",
+                        format!("🔥 {streak} day streak")
+                            .italic()
+                            .truecolor(127, 127, 127)
+                    );
+                }
+            }
+            let inbox = io::read_inbox(dir).len();
+            if inbox > 0 {
+                let x = format!("📥 {inbox} in the inbox, `ivly triage --inbox` to sort them");
+                println!("      {}", x.truecolor(127, 127, 127));
+            }
         }
         Some(Cmd::Add {
             description,
             note,
             tags,
+            project,
+            assignee,
+            estimate,
+            priority,
+            energy,
             tui,
+            no_parse,
+            force,
+            auto_tag,
         }) => {
+            let opts = op::AddOptions { force, auto_tag };
             if tui {
                 op::move_interactive(dir)
             } else {
                 match description {
-                    Some(desc) => op::add(dir, desc, note, tags),
-                    None => op::add_interactive(dir),
+                    Some(desc) => {
+                        let (desc, tags) = if no_parse {
+                            (desc, tags)
+                        } else {
+                            let (desc, mut inline) = tags::extract_inline(&desc);
+                            if !inline.is_empty() {
+                                let parsed = inline.iter().map(ToString::to_string);
+                                println!(
+                                    "🔎 Parsed tags: {}",
+                                    parsed.collect::<Vec<_>>().join(" ")
+                                );
+                            }
+                            inline.extend(tags);
+                            (desc, inline)
+                        };
+                        let mut task = TodoTask::new(desc);
+                        op::Changes {
+                            note,
+                            tags: tags.into_iter().map(Into::into).collect(),
+                            project,
+                            assignee,
+                            priority: Some(priority),
+                            energy: energy.map(Some),
+                            estimate: estimate.map(Into::into),
+                            ..Default::default()
+                        }
+                        .apply(&mut task);
+                        op::add_routed(base, dir, task, opts)
+                    }
+                    None => op::add_interactive(dir, opts),
                 }
             }
         }?,
-        Some(Cmd::Finish { task_num }) => {
-            if task_num.is_empty() {
-                op::finish(dir, None)?;
+        Some(Cmd::Finish {
+            task,
+            confirm,
+            force,
+            pick,
+        }) => {
+            if pick {
+                if let Some((n, _)) = op::pick(dir)? {
+                    op::finish(dir, Some(n), confirm || config.confirm_finish, force)?;
+                }
+            } else if task.is_empty() {
+                op::finish(dir, None, confirm || config.confirm_finish, force)?;
             } else {
-                for n in task_num {
-                    op::finish(dir, n.into())?;
+                let nums = op::resolve_refs(dir, &task)?.into_iter().map(|(n, _)| n);
+                for n in nums {
+                    op::finish(dir, n.into(), false, force)?;
                 }
             }
         }
-        Some(Cmd::Sweep) => op::sweep(dir)?,
-        Some(Cmd::Bump { mut task_num }) => {
+        Some(Cmd::Reopen { task }) => op::reopen(dir, &task)?,
+        Some(Cmd::Check { task, item, done }) => {
+            op::check(dir, &op::resolve_ref(dir, &task)?.1, item, done)?
+        }
+        Some(Cmd::Cancel { task, reason }) => {
+            op::cancel(dir, op::resolve_ref(dir, &task)?.0, reason)?
+        }
+        Some(Cmd::Wait {
+            task,
+            who,
+            on,
+            until,
+            clear,
+        }) => op::wait(
+            dir,
+            op::resolve_ref(dir, &task)?.0,
+            who.or(on),
+            until.map(Into::into),
+            clear,
+        )?,
+        Some(Cmd::Waiting) => op::waiting(dir),
+        Some(Cmd::Snooze { task, duration }) => {
+            op::snooze(dir, op::resolve_ref(dir, &task)?.0, duration.into())?
+        }
+        Some(Cmd::Sweep { refill, quiet }) => op::sweep(dir, refill, quiet)?,
+        Some(Cmd::Pin { task, clear }) => op::pin(dir, op::resolve_ref(dir, &task)?.0, clear)?,
+        Some(Cmd::Plan { plan }) => match plan {
+            Plan::Lock => op::lock_plan(dir)?,
+            Plan::Unlock => op::unlock_plan(dir)?,
+        },
+        Some(Cmd::Bump { pick: true, .. }) => {
+            if let Some((n, _)) = op::pick(dir)? {
+                op::bump(dir, n)?;
+            }
+        }
+        Some(Cmd::Bump { task, .. }) => {
+            let mut task_num = op::resolve_refs(dir, &task)?
+                .into_iter()
+                .map(|(n, _)| n)
+                .collect::<Vec<_>>();
             task_num.sort_unstable();
             task_num.dedup();
             for task_num in task_num.into_iter().rev() {
@@ -88,25 +314,191 @@ fn main() -> miette::Result<()> {
             }
         }
         Some(Cmd::Move {
-            task_num,
+            task,
             insert_before,
-        }) => match (task_num, insert_before) {
-            (Some(task_num), Some(insert_before)) => op::move_(dir, task_num, insert_before),
+        }) => match (task, insert_before) {
+            (Some(task), Some(insert_before)) => {
+                match &op::resolve_refs(dir, &[task, insert_before])?[..] {
+                    [(task, _), (insert_before, _)] => op::move_(dir, *task, *insert_before),
+                    _ => unreachable!("one task per reference"),
+                }
+            }
             (None, None) => op::move_interactive(dir),
             _ => Err(miette::miette!(
                 "please specify both a task number and the number to insert before"
             )),
         }?,
-        Some(Cmd::List { open, done, tags }) => op::list(dir, open, done, tags),
-        Some(Cmd::Tag { tag, fg, bg }) => op::edit_tag(dir, &tag, fg, bg)?,
-        Some(Cmd::Edit { task_id: None, .. }) => op::move_interactive(dir)?,
+        Some(Cmd::Swap { a, b }) => op::swap(dir, &a, &b)?,
+        Some(Cmd::Defer { task, n }) => op::defer(dir, op::resolve_ref(dir, &task)?.0, n)?,
+        Some(Cmd::List {
+            open,
+            done,
+            time,
+            filter,
+            sort,
+        }) => op::list(
+            dir,
+            op::ListOptions {
+                only_open: open,
+                only_done: done,
+                time,
+                sort,
+            },
+            filter,
+            print::Render::to(&std::io::stdout()).stale_after(config.stale_after()),
+            std::io::stdout(),
+        ),
+        Some(Cmd::Start { task }) => op::start(dir, op::resolve_ref(dir, &task)?.0)?,
+        Some(Cmd::Maintain) if new_day => println!("☀️ Rolled over to a new day"),
+        Some(Cmd::Maintain) => println!("Maintenance already ran today"),
+        Some(Cmd::Ws { .. }) => (), // run before opening the workspace
+        Some(Cmd::In { thought }) => op::capture(dir, thought.join(" "))?,
+        Some(Cmd::Triage { inbox: false }) => op::triage(dir)?,
+        Some(Cmd::Triage { inbox: true }) => op::triage_inbox(dir)?,
+        Some(Cmd::Stale) => op::stale(dir),
+        Some(Cmd::Diff { against }) => op::diff(dir, against)?,
+        Some(Cmd::Snapshot { snapshot }) => match snapshot {
+            Snapshot::Save { name } => op::save_snapshot(dir, &name)?,
+            Snapshot::Restore { name } => op::restore_snapshot(dir, &name)?,
+            Snapshot::List => op::list_snapshots(dir)?,
+        },
+        Some(Cmd::Context { context, clear }) => op::context(dir, context, clear)?,
+        Some(Cmd::Link { task, url }) => op::link(dir, &op::resolve_ref(dir, &task)?.1, url)?,
+        Some(Cmd::Open { task }) => op::open(dir, op::resolve_ref(dir, &task)?.0)?,
+        Some(Cmd::Stop) => op::stop(dir)?,
+        Some(Cmd::Stats {
+            fun,
+            format,
+            filter,
+        }) => op::stats(dir, fun, format, &filter)?,
+        Some(Cmd::Chart { days }) => op::chart(dir, days),
+        Some(Cmd::Export {
+            format,
+            include_titles,
+            verify,
+        }) => op::export(dir, format, include_titles, verify)?,
+        Some(Cmd::Import { format, file }) => op::import(dir, format, &file)?,
+        Some(Cmd::Agenda { ical }) => op::agenda(dir, &ical)?,
+        Some(Cmd::Report { report }) => match report {
+            Report::Aging { format } => op::report_aging(dir, format)?,
+            Report::Latency { format } => op::report_latency(dir, format)?,
+        },
+        Some(Cmd::Tag {
+            tag: Some(tag),
+            fg,
+            bg,
+            list: _,
+        }) => op::edit_tag(dir, &tag, fg, bg)?,
+        Some(Cmd::Tag { tag: None, .. }) => op::list_tags(dir),
+        Some(Cmd::Tomorrow { tomorrow }) => match tomorrow {
+            Tomorrow::Add {
+                description,
+                note,
+                tags,
+            } => {
+                let (desc, mut labels) = tags::extract_inline(&description);
+                labels.extend(tags);
+                let mut task = TodoTask::new(desc);
+                task.note = note.unwrap_or_default();
+                for label in labels {
+                    label.apply(&mut task);
+                }
+                op::add_tomorrow(dir, task)?
+            }
+            Tomorrow::List => op::list_tomorrow(dir),
+            Tomorrow::Promote => op::promote_tomorrow(dir)?,
+        },
+        Some(Cmd::Sed {
+            substitution,
+            filter,
+            yes,
+        }) => op::sed(dir, substitution, filter, yes)?,
+        Some(Cmd::Retag {
+            from,
+            to,
+            filter,
+            yes,
+        }) => op::retag(dir, from, to, filter, yes)?,
+        Some(Cmd::Project { project }) => match project {
+            Project::List => op::list_projects(dir),
+            Project::Add { name, fg, bg } => op::add_project(dir, &name, fg, bg)?,
+            Project::Rename { from, to } => op::rename_project(dir, &from, &to)?,
+        },
+        Some(Cmd::Edit {
+            task: None,
+            pick: false,
+            ..
+        }) => op::move_interactive(dir)?,
         Some(Cmd::Edit {
-            task_id: Some(task_id),
+            task,
+            pick: _,
             desc,
             note,
             tags,
-        }) => op::edit(dir, &task_id, desc, note, tags)?,
-        Some(Cmd::Remove { task_id }) => op::remove(dir, &task_id)?,
+            project,
+            assignee,
+            priority,
+            energy,
+            estimate,
+            note_editor,
+        }) => {
+            let task_id = match task {
+                Some(x) => op::resolve_ref(dir, &x)?.1,
+                None => match op::pick(dir)? {
+                    Some((_, id)) => id,
+                    None => return Ok(()),
+                },
+            };
+            op::edit(
+                dir,
+                &task_id,
+                op::Changes {
+                    description: desc,
+                    note,
+                    tags,
+                    project,
+                    assignee,
+                    priority,
+                    energy: energy.map(Into::into),
+                    estimate: estimate.map(Into::into),
+                    note_editor,
+                },
+            )?
+        }
+        Some(Cmd::Delegated) => op::delegated(dir),
+        Some(Cmd::Standup) => op::standup(dir),
+        Some(Cmd::Dedupe) => op::dedupe(dir)?,
+        Some(Cmd::Purge) => op::purge(dir)?,
+        Some(Cmd::MergeTasks { keep, merge }) => {
+            match &op::resolve_refs(dir, &[keep, merge])?[..] {
+                [(_, keep), (_, merge)] => op::merge_tasks(dir, keep, merge)?,
+                _ => unreachable!("one task per reference"),
+            }
+        }
+        Some(Cmd::Remove { task, pick: _ }) => {
+            let task_id = match task {
+                Some(x) => Some(op::resolve_ref(dir, &x)?.1),
+                None => op::pick(dir)?.map(|(_, id)| id),
+            };
+            if let Some(task_id) = task_id {
+                op::remove(dir, &task_id)?;
+            }
+        }
+        Some(Cmd::Show { task }) => op::show(dir, &task)?,
+        Some(Cmd::Undo { force }) => op::undo(dir, force)?,
+        Some(Cmd::History { task }) => {
+            // done and removed tasks are only found by their full ID
+            let task_id = task.map(|t| match op::resolve_ref(dir, &t) {
+                Ok((_, id)) => id,
+                Err(_) => t.to_string(),
+            });
+            op::history(dir, task_id.as_deref())
+        }
+        Some(Cmd::Trash { trash }) => match trash {
+            Trash::List => op::list_trash(dir),
+            Trash::Restore { task_id } => op::restore_trash(dir, &task_id)?,
+            Trash::Empty { older_than } => op::empty_trash(dir, older_than.map(Into::into))?,
+        },
     }
 
     Ok(())
@@ -133,6 +525,36 @@ pub struct App {
     /// + to include tag.
     /// / to exclude tag.
     tags: Vec<FilterTag>,
+    /// When used with `ivly`, show this many priority tasks rather than the configured `top`.
+    #[clap(long, value_name = "N")]
+    top: Option<usize>,
+    /// When used with `ivly`, preview this many backlog tasks under the six.
+    #[clap(long, value_name = "N")]
+    peek: Option<usize>,
+    /// The directory tasks are saved in, overriding the default and `IVLY_DIR`.
+    #[clap(long, global = true, value_name = "DIR")]
+    dir: Option<PathBuf>,
+    /// The workspace to use, overriding the one set by `ivly ws use`.
+    #[clap(long, global = true, value_name = "NAME")]
+    workspace: Option<String>,
+}
+
+/// The directory tasks are saved in.
+///
+/// `--dir` takes precedence over the default, which is `./target/.ivly` for debug builds.
+/// Release builds default to `IVLY_DIR`, then `$HOME/.ivly`.
+/// Debug builds ignore `IVLY_DIR`, so development and the tests never touch the real tasks.
+fn data_dir(dir: Option<PathBuf>) -> PathBuf {
+    dir.unwrap_or_else(|| {
+        if cfg!(debug_assertions) {
+            PathBuf::from("./target/.ivly")
+        } else {
+            std::env::var_os("IVLY_DIR")
+                .map(PathBuf::from)
+                .or_else(|| std::env::var_os("HOME").map(|x| PathBuf::from(x).join(".ivly")))
+                .unwrap_or_else(|| PathBuf::from(".ivly"))
+        }
+    })
 }
 
 /// Subcommand for operations.
@@ -147,38 +569,283 @@ pub enum Cmd {
         /// The task note.
         #[clap(short, long)]
         note: Option<String>,
-        /// Task tags.
-        /// Tags should be prefixed with +.
-        tags: Vec<AddTag>,
+        /// Task tags and contexts.
+        /// Tags should be prefixed with +, contexts with @.
+        tags: Vec<Label>,
+        /// The project the task belongs to.
+        #[clap(long)]
+        project: Option<String>,
+        /// Who the task is delegated to.
+        #[clap(long, visible_alias("delegated-to"))]
+        assignee: Option<String>,
+        /// Estimated effort (eg 2h, 30m).
+        #[clap(long)]
+        estimate: Option<humantime::Duration>,
+        /// The task's priority.
+        #[clap(long, value_enum, default_value_t)]
+        priority: Priority,
+        /// How much energy the task takes.
+        #[clap(long, value_enum)]
+        energy: Option<Energy>,
         /// Use an interactive adding TUI.
         #[arg(long, short('i'))]
         tui: bool,
+        /// Do not parse `+tag` words out of the description.
+        #[clap(long)]
+        no_parse: bool,
+        /// Add the task even if it would be a seventh unfinished priority task in strict mode.
+        #[clap(long)]
+        force: bool,
+        /// Add the tags suggested from similar past tasks.
+        #[clap(long)]
+        auto_tag: bool,
     },
 
     /// Finish a task.
     #[command(alias("f"))]
     Finish {
-        /// The task number. If not specified, finishes the **first** available task.
-        task_num: Vec<usize>,
+        /// The task numbers or IDs. If not specified, finishes the **first** available task.
+        task: Vec<TaskRef>,
+        /// When no task number is given, or with `--pick`, show the task and ask before finishing
+        /// it.
+        #[clap(long)]
+        confirm: bool,
+        /// Finish even if the task has unchecked checklist items.
+        #[clap(long)]
+        force: bool,
+        /// Pick the task by fuzzy searching the descriptions.
+        #[clap(long, conflicts_with = "task")]
+        pick: bool,
+    },
+
+    /// Reopen a task: clear an open task's finished mark, or move a done task back to the open list.
+    Reopen {
+        /// The task number or ID. IDs of done tasks work too.
+        task: TaskRef,
+    },
+
+    /// Add to, tick off, or show a task's checklist.
+    Check {
+        /// The task number or ID.
+        task: TaskRef,
+        /// A checklist item to add.
+        item: Option<String>,
+        /// Mark this item number as done.
+        #[clap(long)]
+        done: Option<usize>,
+    },
+
+    /// Cancel a task, moving it into the done list as won't-do.
+    Cancel {
+        /// The task number or ID.
+        task: TaskRef,
+        /// Why the task was cancelled.
+        #[clap(short, long)]
+        reason: Option<String>,
+    },
+
+    /// Put a task into a waiting state, hiding it from the priority tasks.
+    Wait {
+        /// The task number or ID.
+        task: TaskRef,
+        /// Who or what the task is waiting on.
+        #[clap(conflicts_with("on"))]
+        who: Option<String>,
+        /// What the task is waiting on.
+        #[clap(long("for"))]
+        on: Option<String>,
+        /// Resurface the task after this long (eg 3d).
+        #[clap(long)]
+        until: Option<humantime::Duration>,
+        /// Stop waiting.
+        #[clap(long, conflicts_with_all(["who", "on", "until"]))]
+        clear: bool,
+    },
+
+    /// List the waiting tasks.
+    Waiting,
+
+    /// Show tasks added, removed, moved, or edited since a previous version of the open list.
+    Diff {
+        /// A git ref (eg `HEAD~1`) or file to compare against, defaulting to the backup.
+        against: Option<String>,
+    },
+
+    /// List, create, switch, or remove workspaces, each with its own tasks and settings.
+    #[clap(alias = "workspace")]
+    Ws {
+        /// The workspace action.
+        #[clap(subcommand)]
+        ws: Ws,
+    },
+
+    /// Save, restore, or list named checkpoints of the saved tasks.
+    Snapshot {
+        /// The snapshot action.
+        #[clap(subcommand)]
+        snapshot: Snapshot,
+    },
+
+    /// List the open tasks older than the `stale_after` config, oldest first.
+    Stale,
+
+    /// Work through the backlog one task at a time:
+    /// promote, tag, snooze, delete, or skip each.
+    Triage {
+        /// Triage the inbox instead, promoting items onto the end of the open list.
+        #[clap(long)]
+        inbox: bool,
+    },
+
+    /// Quickly capture a thought into the inbox, to triage later.
+    In {
+        /// The thought.
+        #[clap(required = true)]
+        thought: Vec<String>,
+    },
+
+    /// Show or set the active context.
+    /// Only tasks in the active context are shown by `ivly`, unless a context is given.
+    Context {
+        /// The context to make active, eg @home.
+        context: Option<tags::Context>,
+        /// Show tasks in all contexts.
+        #[clap(long, conflicts_with("context"))]
+        clear: bool,
+    },
+
+    /// Roll over to a new day: remove expired temporal tags (`+today`, `+this-week`), count
+    /// yesterday's unfinished six as carried over, unlock yesterday's plan, sweep the finished
+    /// tasks, and promote the tomorrow list (see `rollover` in the config).
+    /// This runs automatically when `ivly` is first run each day.
+    Maintain,
+
+    /// Attach a URL or file path to a task.
+    Link {
+        /// The task number or ID.
+        task: TaskRef,
+        /// The URL or file path.
+        url: String,
+    },
+
+    /// Open a task's first link in the browser.
+    Open {
+        /// The task number or ID.
+        task: TaskRef,
+    },
+
+    /// Start tracking time on a task, stopping any other.
+    Start {
+        /// The task number or ID.
+        task: TaskRef,
+    },
+
+    /// Stop tracking time.
+    Stop,
+
+    /// Hide a task from the priority list for a while.
+    Snooze {
+        /// The task number or ID.
+        task: TaskRef,
+        /// How long to hide the task (eg 3d).
+        duration: humantime::Duration,
     },
 
     /// Move finished tasks into done list.
-    Sweep,
+    Sweep {
+        /// Pick which backlog tasks fill the free slots in the six.
+        #[clap(long)]
+        refill: bool,
+        /// Only print the number of tasks swept, for scripting.
+        #[clap(short, long, conflicts_with("refill"))]
+        quiet: bool,
+    },
+
+    /// Pin a task so moving or bumping other tasks cannot push it out of the top six.
+    Pin {
+        /// The task number or ID.
+        task: TaskRef,
+        /// Unpin the task.
+        #[clap(long)]
+        clear: bool,
+    },
+
+    /// Find and replace with a regex in the descriptions and notes of the matching open and
+    /// done tasks, previewing the changes first.
+    Sed {
+        /// The substitution, `s/pattern/replacement/` with optional `g` and `i` flags.
+        substitution: sed::Substitution,
+        /// Only change tasks matching these filters.
+        #[clap(flatten)]
+        filter: filter::Filter,
+        /// Apply without asking.
+        #[clap(long, short)]
+        yes: bool,
+    },
+
+    /// Replace a tag or context on the matching open and done tasks, previewing them first.
+    Retag {
+        /// The tag (`+old`) or context (`@old`) to replace.
+        #[clap(long)]
+        from: Label,
+        /// The tag or context to replace it with.
+        #[clap(long)]
+        to: Label,
+        /// Only retag tasks matching these filters.
+        #[clap(flatten)]
+        filter: filter::Filter,
+        /// Apply without asking.
+        #[clap(long, short)]
+        yes: bool,
+    },
+
+    /// Queue tasks for tomorrow without adding them to today's list.
+    Tomorrow {
+        /// The tomorrow action.
+        #[clap(subcommand)]
+        tomorrow: Tomorrow,
+    },
+
+    /// Lock or unlock today's six tasks.
+    Plan {
+        /// The plan action.
+        #[clap(subcommand)]
+        plan: Plan,
+    },
 
     /// Bump a task to the end of the open list.
     Bump {
-        /// The task number.
-        task_num: Vec<usize>,
+        /// The task numbers or IDs.
+        task: Vec<TaskRef>,
+        /// Pick the task by fuzzy searching the descriptions.
+        #[clap(long, conflicts_with = "task")]
+        pick: bool,
+    },
+
+    /// Push a task down the list, by default to just below the top six.
+    Defer {
+        /// The task number or ID.
+        task: TaskRef,
+        /// How many positions to move the task down.
+        n: Option<usize>,
     },
 
     /// Move a task.
     /// If no task numbers are specified, enters interactive move mode.
     #[command(alias("mv"))]
     Move {
-        /// The task number.
-        task_num: Option<usize>,
+        /// The task number or ID.
+        task: Option<TaskRef>,
         /// The task to insert *before*.
-        insert_before: Option<usize>,
+        insert_before: Option<TaskRef>,
+    },
+
+    /// Exchange the positions of two open tasks.
+    Swap {
+        /// The first task number or ID.
+        a: TaskRef,
+        /// The second task number or ID.
+        b: TaskRef,
     },
 
     /// List the tasks.
@@ -190,45 +857,323 @@ pub enum Cmd {
         /// Only show done tasks.
         #[clap(long)]
         done: bool,
-        /// Filter by tags.
-        /// + to include tag.
-        /// / to exclude tag.
-        tags: Vec<FilterTag>,
+        /// Show the time tracked on each task.
+        #[clap(long)]
+        time: bool,
+        /// Task filters.
+        #[clap(flatten)]
+        filter: filter::Filter,
+        /// Sort the tasks.
+        #[clap(long, value_enum)]
+        sort: Option<filter::Sort>,
+    },
+
+    /// Show completion statistics.
+    Stats {
+        /// Show streak badges.
+        #[clap(long)]
+        fun: bool,
+        /// Output format. Badges are only shown as a table.
+        #[clap(long, value_enum, default_value_t)]
+        format: report::Format,
+        /// Only count the tasks passing these filters. Badges are only awarded unfiltered.
+        #[clap(flatten)]
+        filter: filter::Filter,
+    },
+
+    /// Chart the tasks completed each day, and how many were open.
+    Chart {
+        /// How many days to chart, ending today.
+        #[clap(long, default_value_t = 30)]
+        days: u64,
+    },
+
+    /// Export the tasks, printing to stdout.
+    Export {
+        /// What to export.
+        #[clap(long, value_enum)]
+        format: export::Format,
+        /// Include the completed tasks' descriptions in the summary.
+        #[clap(long)]
+        include_titles: bool,
+        /// Check a ron export reads back to the same tasks before printing it.
+        #[clap(long)]
+        verify: bool,
+    },
+
+    /// Import tasks from a file.
+    Import {
+        /// What format the file is in.
+        #[clap(long, value_enum)]
+        format: import::Format,
+        /// The file to import.
+        file: String,
+    },
+
+    /// Reports on the task history.
+    Report {
+        /// The report to show.
+        #[clap(subcommand)]
+        report: Report,
     },
 
-    /// Set the styling of a tag.
+    /// Compare today's calendar with the planned tasks.
+    /// Flags when the estimated work exceeds the free time in the working day.
+    Agenda {
+        /// An iCalendar (ICS) file of the day's events, or `-` to read it from stdin.
+        #[clap(long)]
+        ical: String,
+    },
+
+    /// Set the styling of a tag, or list the styled tags if no tag is given.
     /// See colour names at https://docs.rs/colored/2.1.0/src/colored/color.rs.html#88-111
     Tag {
         /// The tag.
-        tag: String,
+        tag: Option<String>,
         /// The foreground colour.
-        #[clap(long)]
+        #[clap(long, requires = "tag")]
         fg: Option<colored::Color>,
         /// The background colour.
-        #[clap(long)]
+        #[clap(long, requires = "tag")]
         bg: Option<colored::Color>,
+        /// List the styled tags.
+        #[clap(long, conflicts_with = "tag")]
+        list: bool,
+    },
+
+    /// List, add, or rename projects.
+    Project {
+        /// The project action.
+        #[clap(subcommand)]
+        project: Project,
     },
 
     /// Edit a task's description, note, and/or tags.
-    /// If no id is given, drops into the TUI editor.
+    /// If no task is given, drops into the TUI editor.
     Edit {
-        /// The task ID.
-        task_id: Option<String>,
+        /// The task number or ID.
+        task: Option<TaskRef>,
         /// Set the tasks description.
         #[clap(short, long)]
         desc: Option<String>,
         /// Set the tasks note.
         #[clap(short, long)]
         note: Option<String>,
+        /// Edit the note in $EDITOR, allowing multiple lines.
+        #[clap(long)]
+        note_editor: bool,
         /// Add or remove tags.
         tags: Vec<FilterTag>,
+        /// Set the project. An empty string clears it.
+        #[clap(long)]
+        project: Option<String>,
+        /// Set who the task is delegated to. An empty string clears it.
+        #[clap(long, visible_alias("delegated-to"))]
+        assignee: Option<String>,
+        /// Set the task's priority.
+        #[clap(long, value_enum)]
+        priority: Option<Priority>,
+        /// Set how much energy the task takes. `none` clears it.
+        #[clap(long, value_enum)]
+        energy: Option<EnergyChange>,
+        /// Set the estimated effort (eg 2h). `0s` clears it.
+        #[clap(long)]
+        estimate: Option<humantime::Duration>,
+        /// Pick the task by fuzzy searching the descriptions.
+        #[clap(long, conflicts_with = "task")]
+        pick: bool,
     },
 
-    /// Remove a task, deleting it completely.
+    /// List the delegated tasks, grouped by assignee.
+    Delegated,
+
+    /// Print what was done in the last day, today's tasks, and what is waiting on others, as
+    /// plain text to paste into a standup.
+    Standup,
+
+    /// Merge one open task into another, deleting the merged task.
+    /// The kept task's description is used, notes are joined, and tags combined.
+    MergeTasks {
+        /// The number or ID of the task to keep.
+        keep: TaskRef,
+        /// The number or ID of the task to merge in and delete.
+        merge: TaskRef,
+    },
+
+    /// Walk through open tasks with similar descriptions, merging or removing duplicates.
+    Dedupe,
+
+    /// Clean up the task files: remove empty tasks, fix duplicate IDs, and normalise tags.
+    Purge,
+
+    /// Remove a task, moving it into the trash.
     Remove {
-        /// The task ID to remove.
+        /// The number or ID of the task to remove.
+        #[clap(required_unless_present = "pick")]
+        task: Option<TaskRef>,
+        /// Pick the task by fuzzy searching the descriptions.
+        #[clap(long, conflicts_with = "task")]
+        pick: bool,
+    },
+
+    /// Show what happened to a task over time, or the last 50 changes to any task.
+    History {
+        /// The task number or ID. IDs of done or removed tasks work too.
+        task: Option<TaskRef>,
+    },
+
+    /// Show every detail of a task, including its full note and history.
+    Show {
+        /// The task number or ID. IDs of done tasks work too.
+        task: TaskRef,
+    },
+
+    /// Revert the changes made by the most recent command.
+    Undo {
+        /// Undo even if the tasks have been changed since, outside of ivly.
+        #[clap(long)]
+        force: bool,
+    },
+
+    /// List, restore, or permanently delete removed tasks.
+    Trash {
+        /// The trash action.
+        #[clap(subcommand)]
+        trash: Trash,
+    },
+}
+
+/// Removed tasks, kept in `trash.ron` until emptied.
+#[derive(Subcommand)]
+pub enum Trash {
+    /// List the removed tasks, most recently removed first.
+    List,
+    /// Move a task back into the list it was removed from.
+    Restore {
+        /// The task ID.
         task_id: String,
     },
+    /// Permanently delete the removed tasks.
+    Empty {
+        /// Only delete tasks removed longer than this ago (eg 30d).
+        #[clap(long)]
+        older_than: Option<humantime::Duration>,
+    },
+}
+
+/// Projects group tasks, and are styled like tags.
+#[derive(Subcommand)]
+pub enum Project {
+    /// List the projects with their open and done task counts.
+    List,
+    /// Add a project, or change its styling.
+    Add {
+        /// The project name.
+        name: String,
+        /// The foreground colour.
+        #[clap(long)]
+        fg: Option<colored::Color>,
+        /// The background colour.
+        #[clap(long)]
+        bg: Option<colored::Color>,
+    },
+    /// Rename a project, moving its tasks across.
+    Rename {
+        /// The current name.
+        from: String,
+        /// The new name.
+        to: String,
+    },
+}
+
+/// Tasks queued for tomorrow, promoted to the top of the open list on the first run of the day.
+#[derive(Subcommand)]
+pub enum Tomorrow {
+    /// Queue a task for tomorrow.
+    Add {
+        /// The task description.
+        description: String,
+        /// The task note.
+        #[clap(short, long)]
+        note: Option<String>,
+        /// Task tags and contexts.
+        /// Tags should be prefixed with +, contexts with @.
+        tags: Vec<Label>,
+    },
+    /// List the tasks queued for tomorrow.
+    List,
+    /// Move the queued tasks to the top of the open list now.
+    Promote,
+}
+
+/// Today's commitment of six tasks.
+#[derive(Subcommand)]
+pub enum Plan {
+    /// Lock the current top six, so adding or moving tasks cannot change them.
+    Lock,
+    /// Clear the lock.
+    Unlock,
+}
+
+/// Independent sets of tasks, such as `work` and `personal`.
+///
+/// The `default` workspace is the data directory itself; others are kept under its `workspaces`.
+#[derive(Subcommand)]
+pub enum Ws {
+    /// List the workspaces with their task counts, marking the one in use.
+    List,
+    /// Create an empty workspace.
+    New {
+        /// The workspace name.
+        name: String,
+    },
+    /// Use a workspace when `--workspace` is not given.
+    Use {
+        /// The workspace name, or `default`.
+        name: String,
+    },
+    /// Remove a workspace, moving its files into `removed-workspaces`.
+    Remove {
+        /// The workspace name.
+        name: String,
+        /// Remove the workspace even if it has open or done tasks.
+        #[clap(long)]
+        force: bool,
+    },
+}
+
+/// Named checkpoints of the saved tasks, kept apart from the automatic backups.
+#[derive(Subcommand)]
+pub enum Snapshot {
+    /// Save the current tasks and settings, replacing any snapshot with the same name.
+    Save {
+        /// The snapshot name.
+        name: String,
+    },
+    /// Replace the current tasks and settings with a snapshot.
+    Restore {
+        /// The snapshot name.
+        name: String,
+    },
+    /// List the snapshots with when they were taken and their task counts.
+    List,
+}
+
+/// Reports on the task history.
+#[derive(Subcommand)]
+pub enum Report {
+    /// Bucket the open tasks by age, listing the oldest.
+    Aging {
+        /// Output format.
+        #[clap(long, value_enum, default_value_t)]
+        format: report::Format,
+    },
+    /// Time from creation to completion, grouped by tag.
+    Latency {
+        /// Output format.
+        #[clap(long, value_enum, default_value_t)]
+        format: report::Format,
+    },
 }
 
 /// Seconds since the UNIX epoch
@@ -246,8 +1191,29 @@ fn days_ago(duration: Duration) -> String {
     format!("{x} ago")
 }
 
+/// The UTC date and time of a time in seconds since the UNIX epoch, as `YYYY-MM-DD HH:MM`.
+fn datetime(secs: u64) -> String {
+    let time = std::time::UNIX_EPOCH + Duration::from_secs(secs);
+    let x = humantime::format_rfc3339_seconds(time).to_string();
+    x[.."YYYY-MM-DD HH:MM".len()].replace('T', " ")
+}
+
+/// The UTC date of a time in seconds since the UNIX epoch, as `YYYY-MM-DD`.
+fn date(secs: u64) -> String {
+    let time = std::time::UNIX_EPOCH + Duration::from_secs(secs);
+    let mut x = humantime::format_rfc3339_seconds(time).to_string();
+    x.truncate("YYYY-MM-DD".len());
+    x
+}
+
 fn tag_csv<'a>(tags: impl Iterator<Item = &'a str>) -> String {
     let mut x = tags.fold(String::new(), |s, x| s + x + ",");
     x.pop();
     x
 }
+
+/// The task's tags, followed by its `@` contexts, comma separated.
+fn label_csv<S>(task: &task::Task<S>) -> String {
+    let contexts = task.contexts().map(|c| format!("@{c}")).collect::<Vec<_>>();
+    tag_csv(task.tags().chain(contexts.iter().map(String::as_str)))
+}
@@ -0,0 +1,70 @@
+//! A small subset of markdown for notes: `**bold**`, `*italic*`, `` `code` ``, and `-` bullets.
+
+/// A run of text with one style.
+#[derive(Debug, PartialEq)]
+pub struct Span {
+    pub text: String,
+    pub style: Style,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+pub struct Style {
+    pub bold: bool,
+    pub italic: bool,
+    pub code: bool,
+}
+
+/// Parse a note into lines of styled spans. Bullets (`- ` or `* `) become `• `.
+///
+/// A marker with no closing marker later in the line is kept as literal text.
+pub fn parse(note: &str) -> Vec<Vec<Span>> {
+    note.lines().map(parse_line).collect()
+}
+
+fn parse_line(line: &str) -> Vec<Span> {
+    let indent = line.len() - line.trim_start().len();
+    let rest = &line[indent..];
+    let (prefix, rest) = match rest.strip_prefix("- ").or_else(|| rest.strip_prefix("* ")) {
+        Some(rest) => (format!("{}• ", &line[..indent]), rest),
+        None => (line[..indent].to_string(), rest),
+    };
+
+    let mut spans = Vec::new();
+    let mut style = Style::default();
+    let mut text = prefix;
+    let mut at = 0;
+    // whether the marker closes further along, so an opening marker is not left dangling
+    let closes = |from: usize, marker: &str| rest[from..].contains(marker);
+    while let Some(c) = rest[at..].chars().next() {
+        at += c.len_utf8();
+        let mut next = style;
+        match c {
+            '`' if style.code || closes(at, "`") => next.code = !style.code,
+            '*' if !style.code && rest[at..].starts_with('*') => {
+                at += 1;
+                if !style.bold && !closes(at, "**") {
+                    text.push_str("**");
+                    continue;
+                }
+                next.bold = !style.bold;
+            }
+            // `_` is left alone so snake_case words survive
+            '*' if !style.code && (style.italic || closes(at, "*")) => next.italic = !style.italic,
+            _ => {
+                text.push(c);
+                continue;
+            }
+        }
+        if !text.is_empty() {
+            spans.push(Span {
+                text: std::mem::take(&mut text),
+                style,
+            });
+        }
+        style = next;
+    }
+    if !text.is_empty() {
+        spans.push(Span { text, style });
+    }
+    spans
+}
@@ -1,11 +1,27 @@
 use crate::{
-    days_ago, io, print, tag_csv,
-    tags::{AddTag, FilterTag, Tags},
-    task::{TodoTask, TodoTasks},
+    agenda,
+    config::{AfterChange, Rollover},
+    date, datetime, days_ago, diff, export,
+    filter::{Filter, Sort},
+    history, import, io, journal, print,
+    report::{self, Format},
+    sed::Substitution,
+    similar, stats,
+    tags::{Context, FilterTag, Label, Tags},
+    task::{
+        DoneTasks, Energy, Ids, Item, Priority, Restored, Task, TaskRef, Tasks, TodoTask, TodoTasks,
+    },
     tui,
 };
+use colored::Colorize;
+use comfy_table::{Attribute, Cell};
 use miette::*;
-use std::{io::Write, ops::Not, path::Path};
+use std::{
+    borrow::Cow,
+    io::Write,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 fn ask(question: &str) -> Result<String> {
     let stdout = &mut std::io::stdout();
@@ -17,34 +33,145 @@ fn ask(question: &str) -> Result<String> {
     Ok(resp)
 }
 
-pub fn add(dir: &Path, description: String, note: Option<String>, tags: Vec<AddTag>) -> Result<()> {
-    let mut task = TodoTask::new(description);
-    if let Some(note) = note {
-        task.note = note;
+/// The IDs of the open, done, trashed, tomorrow, and inbox tasks, generating new IDs in the
+/// configured scheme.
+fn read_ids(dir: &Path) -> Ids {
+    let mut ids = Ids::new(io::read_config(dir).ids);
+    ids.extend(&io::read_open_tasks(dir));
+    ids.extend(&io::read_done_tasks(dir));
+    ids.extend(&io::read_trash(dir));
+    ids.extend(&io::read_tomorrow(dir));
+    ids.extend(&io::read_inbox(dir));
+    ids
+}
+
+/// How a task is added.
+#[derive(Default, Clone, Copy)]
+pub struct AddOptions {
+    /// Add the task even if it would be a seventh unfinished priority task in strict mode.
+    pub force: bool,
+    /// Add the tags suggested from similar past tasks.
+    pub auto_tag: bool,
+}
+
+pub fn add(dir: &Path, mut task: TodoTask, opts: AddOptions) -> Result<()> {
+    let AddOptions { force, auto_tag } = opts;
+    let config = io::read_config(dir);
+    let mut tasks = io::read_open_tasks(dir);
+    // appended tasks only join the priority tasks if there is room in them
+    let shown = tasks.iter().filter(|t| !t.is_hidden());
+    let lands_in_top = io::read_plan_lock(dir).is_empty() && shown.clone().count() < config.top();
+    let unfinished = shown
+        .take(config.top())
+        .filter(|t| !t.is_finished())
+        .count();
+    ensure!(
+        force || !config.strict() || !lands_in_top || unfinished < 6,
+        help = "finish a task first, or use --force to prioritise it anyway",
+        "strict mode: there are already {unfinished} unfinished priority tasks"
+    );
+    register_project(dir, task.project.as_deref())?;
+    let last_tags = task.tags.clone();
+    for rule in &config.rules {
+        rule.apply(&mut task);
     }
-    for tag in tags.clone() {
-        task.add_tag(tag);
+    let done = io::read_done_tasks(dir);
+    let history = tasks
+        .iter()
+        .map(|t| (t.description.as_str(), t.tags.as_slice()))
+        .chain(
+            done.iter()
+                .map(|t| (t.description.as_str(), t.tags.as_slice())),
+        );
+    let suggested = similar::suggest_tags(&task.description, history)
+        .into_iter()
+        .filter(|t| !task.tags.contains(t))
+        .take(3)
+        .collect::<Vec<_>>();
+    let hint = suggested
+        .iter()
+        .map(|t| format!("+{t}"))
+        .collect::<Vec<_>>();
+    if auto_tag {
+        for tag in &suggested {
+            task.add_tag(tag.as_str());
+        }
     }
-    let mut tasks = io::read_open_tasks(dir);
     let tags_ = io::read_tags(dir);
+    read_ids(dir).assign(&mut task);
     tasks.push(task);
 
     io::write_open_tasks(dir, &tasks)?;
-    io::write_last_tags(
-        dir,
-        tags.into_iter()
-            .map(String::from)
-            .collect::<Vec<_>>()
-            .as_slice(),
-    )?;
+    io::write_last_tags(dir, &last_tags)?;
 
     let (i, task) = tasks.iter().enumerate().last().unwrap();
     println!("✅ Added new task! ID: {}", task.id());
-    print::todo_task(i, task, &tags_);
+    print_after(dir, &tasks, &[i], &tags_);
+    for warning in config.hints.check(&task.description) {
+        println!("⚠️ {warning}");
+    }
+    if !hint.is_empty() && !auto_tag {
+        let x = format!(
+            "💡 Suggested tags: {} (--auto-tag to add them)",
+            hint.join(" ")
+        );
+        println!("      {}", x.truecolor(127, 127, 127));
+    }
+    if let Some((desc, ago)) = recently_completed(&tasks, &done, task) {
+        println!(
+            "⚠️ You completed a similar task {}: '{desc}'. Should it be a recurring task?",
+            days_ago(ago)
+        );
+    }
+    if !io::read_plan_lock(dir).is_empty() {
+        println!("🔒 Today's plan is locked, so the task is in the backlog");
+    }
     Ok(())
 }
 
-pub fn add_interactive(dir: &Path) -> Result<()> {
+/// Add the task, to the workspace a config rule routes it to if there is one.
+///
+/// A routed task gets the tags of the rules here as well as those of its workspace, and is
+/// journaled in its workspace so it can be undone there.
+pub fn add_routed(base: &Path, dir: &Path, mut task: TodoTask, opts: AddOptions) -> Result<()> {
+    let config = io::read_config(dir);
+    let Some(workspace) = config.route(&task.description) else {
+        return add(dir, task, opts);
+    };
+    let target = open_workspace(base, Some(workspace))?;
+    if target == dir {
+        return add(dir, task, opts);
+    }
+    for rule in &config.rules {
+        rule.apply(&mut task);
+    }
+    println!("🗂️ Routed to the '{workspace}' workspace");
+    let session = journal::Session::begin(&target, format!("ivly add {}", task.description));
+    let added = add(&target, task, opts);
+    session.end()?;
+    added
+}
+
+/// Completed tasks within this many days are checked for duplicates of new tasks.
+const RECENT_DAYS: u64 = 30;
+
+/// The most recently completed task like `task`, and how long ago it was completed.
+fn recently_completed<'a>(
+    open: &'a TodoTasks,
+    done: &'a DoneTasks,
+    task: &TodoTask,
+) -> Option<(&'a str, Duration)> {
+    let open = open.iter().filter(|t| t.id() != task.id());
+    let done = done.iter().filter(|t| !t.is_cancelled());
+    open.filter_map(|t| Some((t.description.as_str(), t.finished_at()?)))
+        .chain(done.filter_map(|t| Some((t.description.as_str(), t.finished_at()?))))
+        .filter(|(_, at)| crate::now().saturating_sub(*at) < RECENT_DAYS * stats::DAY)
+        .filter(|(desc, _)| similar::similarity(desc, &task.description) >= DUPLICATE_THRESHOLD)
+        .max_by_key(|(_, at)| *at)
+        .map(|(desc, at)| (desc, Duration::from_secs(crate::now().saturating_sub(at))))
+}
+
+pub fn add_interactive(dir: &Path, opts: AddOptions) -> Result<()> {
     let last_tags = io::read_last_tags(dir);
     let tags = io::read_tags(dir);
     let desc = ask("Task description:")?;
@@ -58,19 +185,221 @@ pub fn add_interactive(dir: &Path) -> Result<()> {
         let q = format!("Tags [{}] (u to use):", q.trim());
         ask(&q)
     }?;
-    let ts = if tags.trim().is_empty() {
-        Vec::new()
-    } else if tags == "u" {
-        last_tags.into_iter().map(AddTag).collect::<Vec<_>>()
-    } else {
-        let mut ts = Vec::new();
+    let mut task = TodoTask::new(desc);
+    task.note = note;
+    if tags == "u" {
+        task.tags = last_tags;
+    } else if !tags.trim().is_empty() {
         for tag in tags.split(' ') {
-            let tag = tag.parse().map_err(|e| miette!("{e}"))?;
-            ts.push(tag);
+            let label: Label = tag.parse().map_err(|e| miette!("{e}"))?;
+            label.apply(&mut task);
         }
-        ts
+    }
+    add(dir, task, opts)
+}
+
+/// Add the project to `projects.ron` if it is new.
+fn register_project(dir: &Path, project: Option<&str>) -> Result<()> {
+    let Some(project) = project else {
+        return Ok(());
     };
-    add(dir, desc, note.is_empty().not().then_some(note), ts)
+    let mut projects = io::read_projects(dir);
+    if !projects.contains(project) {
+        projects.insert(project);
+        io::write_projects(dir, &projects)?;
+    }
+    Ok(())
+}
+
+pub fn add_project(
+    dir: &Path,
+    name: &str,
+    fg: Option<colored::Color>,
+    bg: Option<colored::Color>,
+) -> Result<()> {
+    let mut projects = io::read_projects(dir);
+    projects.insert(name);
+    if let Some(fg) = fg {
+        projects.set_fg(name, fg);
+    }
+    if let Some(bg) = bg {
+        projects.set_bg(name, bg);
+    }
+    io::write_projects(dir, &projects)?;
+    println!("📁 Added project '{}'", projects.colourise(name, name));
+    Ok(())
+}
+
+/// Rename a project, and move the open and done tasks in it across.
+pub fn rename_project(dir: &Path, from: &str, to: &str) -> Result<()> {
+    let mut projects = io::read_projects(dir);
+    let mut open = io::read_open_tasks(dir);
+    let mut done = io::read_done_tasks(dir);
+    let in_from = |p: &Option<String>| p.as_deref() == Some(from);
+    let moved = open.iter().filter(|t| in_from(&t.project)).count()
+        + done.iter().filter(|t| in_from(&t.project)).count();
+    ensure!(
+        projects.rename(from, to) || moved > 0,
+        "no project named '{from}'"
+    );
+
+    for t in open.iter_mut().filter(|t| in_from(&t.project)) {
+        t.project = Some(to.to_string());
+    }
+    for t in done.iter_mut().filter(|t| in_from(&t.project)) {
+        t.project = Some(to.to_string());
+    }
+    projects.insert(to);
+    io::write_projects(dir, &projects)?;
+    if moved > 0 {
+        io::write_open_tasks(dir, &open)?;
+        io::write_done_tasks(dir, &done)?;
+    }
+    println!("✅ Renamed project '{from}' to '{to}' ({moved} tasks)");
+    Ok(())
+}
+
+/// Replace a tag or context with another on the open and done tasks matching the filter.
+///
+/// Previews the affected tasks and asks before applying, unless `yes`.
+pub fn retag(dir: &Path, from: Label, to: Label, filter: Filter, yes: bool) -> Result<()> {
+    let mut open = io::read_open_tasks(dir);
+    let mut done = io::read_done_tasks(dir);
+    let affected = open
+        .iter()
+        .filter(|t| from.is_on(*t) && filter.matches(*t))
+        .map(|t| t.description.clone())
+        .chain(
+            done.iter()
+                .filter(|t| from.is_on(*t) && filter.matches(*t))
+                .map(|t| t.description.clone()),
+        )
+        .collect::<Vec<_>>();
+    if affected.is_empty() {
+        println!("No tasks have {from}");
+        return Ok(());
+    }
+
+    println!("{} tasks will change from {from} to {to}:", affected.len());
+    for desc in affected.iter().take(5) {
+        println!("      {}", desc.truecolor(127, 127, 127));
+    }
+    if affected.len() > 5 {
+        let x = format!("… and {} more", affected.len() - 5);
+        println!("      {}", x.truecolor(127, 127, 127));
+    }
+    if !yes && !ask("Apply? [y/N]")?.eq_ignore_ascii_case("y") {
+        println!("No changes made");
+        return Ok(());
+    }
+
+    let mut open_changed = false;
+    for t in open
+        .iter_mut()
+        .filter(|t| from.is_on(*t) && filter.matches(*t))
+    {
+        from.remove_from(t);
+        to.clone().apply(t);
+        open_changed = true;
+    }
+    let mut done_changed = false;
+    for t in done
+        .iter_mut()
+        .filter(|t| from.is_on(*t) && filter.matches(*t))
+    {
+        from.remove_from(t);
+        to.clone().apply(t);
+        done_changed = true;
+    }
+    if open_changed {
+        io::write_open_tasks(dir, &open)?;
+    }
+    if done_changed {
+        io::write_done_tasks(dir, &done)?;
+    }
+    println!("✅ Retagged {} tasks", affected.len());
+    Ok(())
+}
+
+/// Substitute text in the descriptions and notes of the open and done tasks matching the filter.
+///
+/// Previews each change and asks before applying, unless `yes`.
+pub fn sed(dir: &Path, subst: Substitution, filter: Filter, yes: bool) -> Result<()> {
+    let mut open = io::read_open_tasks(dir);
+    let mut done = io::read_done_tasks(dir);
+
+    fn substitute<S>(task: &mut Task<S>, subst: &Substitution) -> Vec<(String, String)> {
+        let mut changes = Vec::new();
+        if let Cow::Owned(x) = subst.apply(&task.description) {
+            changes.push((std::mem::replace(&mut task.description, x.clone()), x));
+        }
+        if let Cow::Owned(x) = subst.apply(&task.note) {
+            changes.push((std::mem::replace(&mut task.note, x.clone()), x));
+        }
+        changes
+    }
+
+    let mut changes = Vec::new();
+    let mut changed = (false, false);
+    for t in open.iter_mut().filter(|t| filter.matches(*t)) {
+        let x = substitute(t, &subst);
+        changed.0 |= !x.is_empty();
+        changes.extend(x);
+    }
+    for t in done.iter_mut().filter(|t| filter.matches(*t)) {
+        let x = substitute(t, &subst);
+        changed.1 |= !x.is_empty();
+        changes.extend(x);
+    }
+    if changes.is_empty() {
+        println!("No matches");
+        return Ok(());
+    }
+
+    for (old, new) in &changes {
+        println!("{}", format!("- {old}").red());
+        println!("{}", format!("+ {new}").green());
+    }
+    if !yes && !ask(&format!("Apply {} changes? [y/N]", changes.len()))?.eq_ignore_ascii_case("y") {
+        println!("No changes made");
+        return Ok(());
+    }
+
+    if changed.0 {
+        io::write_open_tasks(dir, &open)?;
+    }
+    if changed.1 {
+        io::write_done_tasks(dir, &done)?;
+    }
+    println!("✅ Made {} changes", changes.len());
+    Ok(())
+}
+
+/// Print the projects with their open and done task counts.
+pub fn list_projects(dir: &Path) {
+    let mut projects = io::read_projects(dir);
+    let open = io::read_open_tasks(dir);
+    let done = io::read_done_tasks(dir);
+    for p in open.iter().filter_map(|t| t.project.as_deref()) {
+        projects.insert(p);
+    }
+    for p in done.iter().filter_map(|t| t.project.as_deref()) {
+        projects.insert(p);
+    }
+
+    let mut table = comfy_table::Table::new();
+    table
+        .load_preset(comfy_table::presets::UTF8_HORIZONTAL_ONLY)
+        .set_header(vec!["Project", "Open", "Done"]);
+    for (name, _) in projects.iter() {
+        let is = |p: &Option<String>| p.as_deref() == Some(name);
+        table.add_row(vec![
+            projects.colourise(name, name).to_string(),
+            open.iter().filter(|t| is(&t.project)).count().to_string(),
+            done.iter().filter(|t| is(&t.project)).count().to_string(),
+        ]);
+    }
+    println!("{table}");
 }
 
 fn read_tasks_tags(dir: &Path) -> (TodoTasks, Tags) {
@@ -86,7 +415,119 @@ fn translate_task_num(tasks: &TodoTasks, num: usize) -> Result<usize> {
     Ok(num - 1)
 }
 
-pub fn finish(dir: &Path, task_num: Option<usize>) -> Result<()> {
+/// The index of the open task a reference is to.
+///
+/// An exact ID match wins over a task number, which wins over an ID prefix.
+fn resolve(tasks: &TodoTasks, task: &TaskRef) -> Result<usize> {
+    if let Some(i) = tasks.iter().position(|t| t.id() == task.as_str()) {
+        return Ok(i);
+    }
+    if let Some(num) = task.num() {
+        return translate_task_num(tasks, num);
+    }
+    let matches = tasks
+        .iter()
+        .enumerate()
+        .filter(|(_, t)| t.id().starts_with(task.as_str()))
+        .collect::<Vec<_>>();
+    match matches[..] {
+        [(i, _)] => Ok(i),
+        [] => Err(miette!("no open task with a number or ID of '{task}'")),
+        _ => Err(miette!(
+            help = format!(
+                "it could be any of {}",
+                matches
+                    .iter()
+                    .map(|(_, t)| t.id())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            "'{task}' matches {} open tasks",
+            matches.len()
+        )),
+    }
+}
+
+/// Resolve a reference to an open task, returning its task number and ID.
+///
+/// Task numbers are checked against the numbers last shown, see [`confirm_task_nums`].
+pub fn resolve_ref(dir: &Path, task: &TaskRef) -> Result<(usize, String)> {
+    let mut found = resolve_refs(dir, std::slice::from_ref(task))?;
+    Ok(found.remove(0))
+}
+
+/// Resolve references to open tasks, returning their task numbers and IDs.
+///
+/// The task numbers are confirmed together, so a command warns at most once.
+pub fn resolve_refs(dir: &Path, refs: &[TaskRef]) -> Result<Vec<(usize, String)>> {
+    let tasks = io::read_open_tasks(dir);
+    let found = refs
+        .iter()
+        .map(|r| resolve(&tasks, r))
+        .collect::<Result<Vec<_>>>()?;
+    let nums = refs
+        .iter()
+        .zip(&found)
+        .filter(|(r, _)| r.num().is_some() && !tasks.iter().any(|t| t.id() == r.as_str()))
+        .map(|(_, &i)| i)
+        .collect::<Vec<_>>();
+    confirm_task_nums(dir, &tasks, &nums)?;
+    Ok(found
+        .into_iter()
+        .map(|i| (i + 1, tasks[i].id().to_string()))
+        .collect())
+}
+
+/// Warn that task numbers have shifted if any of the `indices` now refer to a different task
+/// than when the numbers were last shown, showing the tasks they now refer to and, in a
+/// terminal, asking to go ahead.
+fn confirm_task_nums(dir: &Path, tasks: &TodoTasks, indices: &[usize]) -> Result<()> {
+    let Some(rendered) = io::read_rendered(dir) else {
+        return Ok(());
+    };
+    let shifted = indices
+        .iter()
+        .copied()
+        .filter(|&i| rendered.get(i).map(String::as_str) != Some(tasks[i].id()))
+        .collect::<Vec<_>>();
+    if shifted.is_empty() {
+        return Ok(());
+    }
+
+    eprintln!("⚠️ The list has changed since it was last shown, so task numbers have shifted");
+    let tags = io::read_tags(dir);
+    for i in shifted {
+        print::todo_task(
+            i,
+            &tasks[i],
+            &tags,
+            &io::read_projects(dir),
+            print::Render::to(&std::io::stderr()).stale_after(io::read_config(dir).stale_after()),
+            std::io::stderr(),
+        );
+    }
+    if std::io::IsTerminal::is_terminal(&std::io::stdin())
+        && !ask("Are these the tasks? [y/N]")?.eq_ignore_ascii_case("y")
+    {
+        bail!(
+            help = "run `ivly` to see the current numbers",
+            "no changes made"
+        );
+    }
+    Ok(())
+}
+
+/// Pick an open task by fuzzy search, returning its task number and ID.
+pub fn pick(dir: &Path) -> Result<Option<(usize, String)>> {
+    let tasks = io::read_open_tasks(dir);
+    let picked = tui::Picker::new(&tasks).run()?;
+    if picked.is_none() {
+        println!("No task picked");
+    }
+    Ok(picked.map(|i| (i + 1, tasks[i].id().to_string())))
+}
+
+pub fn finish(dir: &Path, task_num: Option<usize>, confirm: bool, force: bool) -> Result<()> {
     let (mut tasks, tags) = read_tasks_tags(dir);
     let task_num = task_num.unwrap_or_else(|| {
         tasks
@@ -96,154 +537,1857 @@ pub fn finish(dir: &Path, task_num: Option<usize>) -> Result<()> {
             + 1
     });
     let index = translate_task_num(&tasks, task_num)?;
+    if confirm {
+        print::todo_task(
+            index,
+            &tasks[index],
+            &tags,
+            &io::read_projects(dir),
+            render(dir),
+            std::io::stdout(),
+        );
+        if !ask("Finish this task? [y/N]")?.eq_ignore_ascii_case("y") {
+            println!("No changes made");
+            return Ok(());
+        }
+    }
     let task = tasks.get_mut(index).unwrap();
-    task.finish();
-    let task = task.clone();
-    io::write_open_tasks(dir, &tasks)?;
-    println!("✅ Finished '{}'!", task.description);
-    tasks
+    match task.progress() {
+        Some((done, total)) if done < total && !force => {
+            return Err(miette!(
+                help = "use --force to finish anyway",
+                "'{}' has {} unchecked items",
+                task.description,
+                total - done
+            ))
+        }
+        _ => (),
+    }
+    // finishing a finished task changes nothing, so there is nothing to ring or celebrate
+    let finished = !task.is_finished();
+    if finished {
+        task.finish();
+        io::write_open_tasks(dir, &tasks)?;
+    }
+    let config = io::read_config(dir);
+    if finished && config.finish_bell {
+        print!("\x07");
+    }
+    println!("✅ Finished '{}'!", tasks[index].description);
+    let top = tasks
         .iter()
         .enumerate()
-        .take(6)
-        .for_each(|(i, t)| print::todo_task(i, t, &tags));
+        .filter(|(_, t)| !t.is_hidden())
+        .take(config.top())
+        .collect::<Vec<_>>();
+    let in_top = top.iter().any(|(i, _)| *i == index);
+    if finished && config.celebrate && in_top && top.iter().all(|(_, t)| t.is_finished()) {
+        print::celebrate(render(dir), std::io::stdout());
+    }
+    print_after(dir, &tasks, &[index], &tags);
     Ok(())
 }
 
-pub fn sweep(dir: &Path) -> Result<()> {
-    let (mut open, tags) = read_tasks_tags(dir);
+pub fn check(dir: &Path, id: &str, item: Option<String>, done: Option<usize>) -> Result<()> {
+    let mut tasks = io::read_open_tasks(dir);
+    let task = tasks
+        .iter_mut()
+        .find(|t| t.id() == id)
+        .ok_or_else(|| miette!("no open task with id {id}"))?;
+
+    let changed = item.is_some() || done.is_some();
+    if let Some(text) = item {
+        task.checklist.push(Item { text, done: false });
+    }
+    if let Some(n) = done {
+        let len = task.checklist.len();
+        let item = n
+            .checked_sub(1)
+            .and_then(|i| task.checklist.get_mut(i))
+            .ok_or_else(|| miette!("item number must be between 1 and {len}"))?;
+        item.done = true;
+    }
+
+    println!("{}", task.description.bold());
+    for (i, item) in task.checklist.iter().enumerate() {
+        let mark = if item.done { "☑" } else { "☐" };
+        println!(" {:>4} {mark} {}", format!("{}.", i + 1), item.text);
+    }
+    if changed {
+        io::write_open_tasks(dir, &tasks)?;
+    }
+    Ok(())
+}
+
+/// Move the finished tasks into the done list.
+///
+/// `quiet` prints only the number of tasks swept.
+pub fn sweep(dir: &Path, refill: bool, quiet: bool) -> Result<()> {
+    let tags = io::read_tags(dir);
+    let (open, done_len, swept) = sweep_finished(dir, refill)?;
+
+    if quiet {
+        println!("{}", swept.len());
+        return Ok(());
+    }
+
+    println!(
+        "✅ Swept {} finished task{} into the done list ({} open, {} done)",
+        swept.len(),
+        if swept.len() == 1 { "" } else { "s" },
+        open.len(),
+        done_len
+    );
+    for desc in &swept {
+        println!("      {}", format!("✔ {desc}").truecolor(127, 127, 127));
+    }
+    print_top(dir, &open, &tags, std::io::stdout());
+    Ok(())
+}
+
+/// How to render what is printed to stdout.
+fn render(dir: &Path) -> print::Render {
+    print::Render::to(&std::io::stdout()).stale_after(io::read_config(dir).stale_after())
+}
+
+/// Print the list after a command changes it, as much as the `after_change` config asks for.
+///
+/// `changed` are the indices of the changed tasks still in the list.
+fn print_after(dir: &Path, tasks: &TodoTasks, changed: &[usize], tags: &Tags) {
+    let mut wtr = std::io::stdout();
+    let config = io::read_config(dir);
+    let r = print::Render::to(&wtr).stale_after(config.stale_after());
+    let projects = io::read_projects(dir);
+    let n = match config.after_change {
+        AfterChange::Nothing => return,
+        AfterChange::Task => 0,
+        AfterChange::Top(n) => n,
+        AfterChange::Shown => config.top(),
+    };
+    let top = tasks
+        .iter()
+        .enumerate()
+        .filter(|(_, t)| !t.is_hidden())
+        .take(n)
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    for &i in &top {
+        print::todo_task(i, &tasks[i], tags, &projects, r, &mut wtr);
+    }
+    let mut rest = changed
+        .iter()
+        .copied()
+        .filter(|i| *i < tasks.len() && !top.contains(i))
+        .collect::<Vec<_>>();
+    rest.sort_unstable();
+    rest.dedup();
+    if !top.is_empty() && !rest.is_empty() {
+        println!("      {}", "…".truecolor(127, 127, 127));
+    }
+    for &i in &rest {
+        print::todo_task(i, &tasks[i], tags, &projects, r, &mut wtr);
+    }
+    if !top.is_empty() || !rest.is_empty() {
+        io::mark_rendered(dir, tasks);
+    }
+}
+
+/// Print as many of the tasks shown as the `top` config sets.
+fn print_top(
+    dir: &Path,
+    tasks: &TodoTasks,
+    tags: &Tags,
+    mut wtr: impl Write + std::io::IsTerminal,
+) {
+    let config = io::read_config(dir);
+    let r = print::Render::to(&wtr).stale_after(config.stale_after());
+    let projects = io::read_projects(dir);
+    tasks
+        .iter()
+        .enumerate()
+        .filter(|(_, t)| !t.is_hidden())
+        .take(config.top())
+        .for_each(|(i, t)| print::todo_task(i, t, tags, &projects, r, &mut wtr));
+    io::mark_rendered(dir, tasks);
+}
+
+/// Move the finished tasks into the done list.
+///
+/// Returns the open tasks, the number of done tasks, and the descriptions of those swept.
+fn sweep_finished(dir: &Path, refill: bool) -> Result<(TodoTasks, usize, Vec<String>)> {
+    let mut open = io::read_open_tasks(dir);
     let mut done = io::read_done_tasks(dir);
+    let open_len = open.len();
+    let config = io::read_config(dir);
+    let top = config.top();
+    let kept = open
+        .iter()
+        .filter(|t| !t.is_hidden())
+        .take(top)
+        .filter(|t| !t.is_finished())
+        .map(|t| t.id().to_string())
+        .collect::<Vec<_>>();
 
+    let sweep_tags = config.sweep_tags;
+    let mut swept = Vec::new();
     let mut i = 0;
     while i < open.len() {
         if open[i].is_finished() {
-            let val = open.remove(i);
+            let mut val = open.remove(i);
+            sweep_tags.apply(&mut val);
+            swept.push(val.description.clone());
             done.push(val.complete());
         } else {
             i += 1;
         }
     }
 
-    done.sort();
+    let refilled = refill && kept.len() < top && refill_from_backlog(dir, &mut open, &kept, top)?;
 
-    io::write_done_tasks(dir, &done)?;
-    io::write_open_tasks(dir, &open)?;
+    // only touch the files (and their backups) if something was swept
+    if open.len() != open_len {
+        done.sort();
+        io::write_done_tasks(dir, &done)?;
+    }
+    if open.len() != open_len || refilled {
+        io::write_open_tasks(dir, &open)?;
+    }
+
+    Ok((open, done.len(), swept))
+}
 
-    println!("✅ Swept finished tasks into done list");
-    open.iter()
+/// Ask which backlog tasks fill the free slots in the `top`, moving them up behind the `kept` tasks.
+///
+/// Returns if the order changed.
+fn refill_from_backlog(
+    dir: &Path,
+    open: &mut TodoTasks,
+    kept: &[String],
+    top: usize,
+) -> Result<bool> {
+    let slots = top.saturating_sub(kept.len());
+    let backlog = open
+        .iter()
         .enumerate()
-        .take(6)
-        .for_each(|(i, t)| print::todo_task(i, t, &tags));
-    Ok(())
+        .filter(|(_, t)| !t.is_hidden() && !kept.iter().any(|k| k == t.id()))
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    if backlog.is_empty() {
+        return Ok(false);
+    }
+
+    println!("📋 {slots} free slots, pick from the backlog:");
+    let (tags, projects) = (io::read_tags(dir), io::read_projects(dir));
+    for &i in &backlog {
+        print::todo_task(
+            i,
+            &open[i],
+            &tags,
+            &projects,
+            render(dir),
+            std::io::stdout(),
+        );
+    }
+    let answer = ask(&format!(
+        "Task numbers (up to {slots}, empty keeps the order):"
+    ))?;
+    let mut chosen = Vec::new();
+    for x in answer.split_whitespace() {
+        let i = x
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| n.checked_sub(1))
+            .filter(|i| backlog.contains(i))
+            .ok_or_else(|| miette!("'{x}' is not a backlog task number"))?;
+        if !chosen.contains(&i) {
+            chosen.push(i);
+        }
+    }
+    ensure!(chosen.len() <= slots, "only {slots} tasks can be picked");
+    if chosen.is_empty() {
+        return Ok(false);
+    }
+
+    let mut tasks = std::mem::take(&mut open.0)
+        .into_iter()
+        .map(Some)
+        .collect::<Vec<_>>();
+    let kept_idx = kept
+        .iter()
+        .filter_map(|k| {
+            tasks
+                .iter()
+                .position(|t| t.as_ref().is_some_and(|t| t.id() == k))
+        })
+        .collect::<Vec<_>>();
+    for i in kept_idx.into_iter().chain(chosen) {
+        open.extend(tasks[i].take());
+    }
+    open.extend(tasks.into_iter().flatten());
+    Ok(true)
 }
 
 pub fn bump(dir: &Path, task_num: usize) -> Result<()> {
     let (mut tasks, tags) = read_tasks_tags(dir);
     let index = translate_task_num(&tasks, task_num)?;
-    let task = tasks.remove(index);
-    tasks.push(task);
+    let last = tasks.len() - 1;
+    let top = io::read_config(dir).top();
+    ensure!(
+        tasks.reorder(index, last, top, &io::read_plan_lock(dir)),
+        "📌 bumping task {task_num} would push a pinned task out of the top {top}, or change the locked plan"
+    );
     io::write_open_tasks(dir, &tasks)?;
     let task = tasks.last().unwrap();
     println!("✅ Bumped '{}'!", task.description);
-    tasks
-        .iter()
-        .enumerate()
-        .last()
-        .into_iter()
-        .for_each(|(i, t)| print::todo_task(i, t, &tags));
+    print_after(dir, &tasks, &[last], &tags);
     Ok(())
 }
 
-pub fn move_(dir: &Path, task_num: usize, insert_before: usize) -> Result<()> {
-    let mut tasks = io::read_open_tasks(dir);
-    let task = translate_task_num(&tasks, task_num)?;
-    let mut before = translate_task_num(&tasks, insert_before)?;
-    if task < before {
-        before = before.saturating_sub(1);
-    }
-    let task = tasks.remove(task);
-    tasks.insert(before, task);
-    io::write_open_tasks(dir, &tasks)?;
-    let (a, b) = (&tasks[before], &tasks[before + 1]);
-    println!(
-        "✅ Moved '{}' in front of '{}'!",
-        a.description, b.description
+/// Move a task `by` positions down the list, or by default to just below the tasks shown.
+pub fn defer(dir: &Path, task_num: usize, by: Option<usize>) -> Result<()> {
+    let (mut tasks, tags) = read_tasks_tags(dir);
+    let index = translate_task_num(&tasks, task_num)?;
+    let last = tasks.len() - 1;
+    let top = io::read_config(dir).top();
+    let to = match by {
+        Some(by) => index.saturating_add(by).min(last),
+        None => {
+            // the last shown of the other tasks, which the task goes after
+            let bottom = tasks
+                .iter()
+                .enumerate()
+                .filter(|(i, t)| *i != index && !t.is_hidden())
+                .nth(top.saturating_sub(1))
+                .map(|(i, _)| i);
+            match bottom {
+                Some(i) if i > index => i,
+                Some(_) => bail!(
+                    "'{}' is already below the top {top}",
+                    tasks[index].description
+                ),
+                None => last,
+            }
+        }
+    };
+    ensure!(
+        to > index,
+        "'{}' is already at the end of the list",
+        tasks[index].description
+    );
+    ensure!(
+        tasks.reorder(index, to, top, &io::read_plan_lock(dir)),
+        "📌 deferring task {task_num} would push a pinned task out of the top {top}, or change the locked plan"
     );
+    io::write_open_tasks(dir, &tasks)?;
+    println!("⏬ Deferred '{}' to task {}", tasks[to].description, to + 1);
+    print_after(dir, &tasks, &[to], &tags);
     Ok(())
 }
 
-pub fn move_interactive(dir: &Path) -> Result<()> {
-    let mut tasks = io::read_open_tasks(dir);
-    let save = tui::Move::new(&mut tasks).run()?;
-
-    if save {
-        io::write_open_tasks(dir, &tasks)?;
-        println!("✅ Saved changes");
+pub fn pin(dir: &Path, task_num: usize, clear: bool) -> Result<()> {
+    let (mut tasks, tags) = read_tasks_tags(dir);
+    let index = translate_task_num(&tasks, task_num)?;
+    let task = &mut tasks[index];
+    if clear {
+        task.unpin();
+        println!("✅ Unpinned '{}'", task.description);
     } else {
-        println!("No changes made");
+        task.pin();
+        println!("📌 Pinned '{}'", task.description);
     }
+    io::write_open_tasks(dir, &tasks)?;
+    print_after(dir, &tasks, &[index], &tags);
     Ok(())
 }
 
-pub fn list(dir: &Path, only_open: bool, only_done: bool, tags: Vec<FilterTag>) {
-    let fopen = only_open || !(only_open ^ only_done);
-    let fdone = only_done || !(only_open ^ only_done);
-
-    let open = io::read_open_tasks(dir)
-        .into_iter()
-        .filter(|_| fopen)
-        .filter(|t| tags.iter().all(|f| f.filter(t.tags())));
-    let done = io::read_done_tasks(dir)
-        .into_iter()
-        .filter(|_| fdone)
-        .filter(|t| tags.iter().all(|f| f.filter(t.tags())));
+/// Clear the finished mark of an open task, or move a done task back onto the end of the open list.
+pub fn reopen(dir: &Path, task: &TaskRef) -> Result<()> {
+    let (mut open, tags) = read_tasks_tags(dir);
+    let index = match resolve(&open, task) {
+        Ok(i) => {
+            let t = &mut open[i];
+            ensure!(t.is_finished(), "'{}' is not finished", t.description);
+            t.unfinish();
+            io::write_open_tasks(dir, &open)?;
+            i
+        }
+        Err(e) => {
+            let mut done = io::read_done_tasks(dir);
+            let i = done.iter().position(|t| t.id() == task.as_str()).ok_or(e)?;
+            open.push(done.remove(i).reopen());
+            io::write_done_tasks(dir, &done)?;
+            io::write_open_tasks(dir, &open)?;
+            open.len() - 1
+        }
+    };
+    println!("↩️ Reopened '{}'", open[index].description);
+    print_after(dir, &open, &[index], &tags);
+    Ok(())
+}
 
-    let mut table = comfy_table::Table::new();
+/// Lock the first tasks shown, as many as the `top` config sets, as today's plan.
+pub fn lock_plan(dir: &Path) -> Result<()> {
+    let (tasks, tags) = read_tasks_tags(dir);
+    let projects = io::read_projects(dir);
+    let plan = tasks
+        .iter()
+        .enumerate()
+        .filter(|(_, t)| !t.is_hidden() && !t.is_finished())
+        .take(io::read_config(dir).top())
+        .collect::<Vec<_>>();
+    ensure!(!plan.is_empty(), "there are no open tasks to lock");
+    let ids = plan
+        .iter()
+        .map(|(_, t)| t.id().to_string())
+        .collect::<Vec<_>>();
+    io::write_plan_lock(dir, &ids)?;
+    println!("🔒 Locked today's plan");
+    for (i, t) in plan {
+        print::todo_task(i, t, &tags, &projects, render(dir), std::io::stdout());
+    }
+    Ok(())
+}
+
+pub fn unlock_plan(dir: &Path) -> Result<()> {
+    if io::read_plan_lock(dir).is_empty() {
+        println!("Today's plan is not locked");
+        return Ok(());
+    }
+    io::write_plan_lock(dir, &[])?;
+    println!("🔓 Unlocked today's plan");
+    Ok(())
+}
+
+pub fn cancel(dir: &Path, task_num: usize, reason: Option<String>) -> Result<()> {
+    let (mut open, tags) = read_tasks_tags(dir);
+    let mut done = io::read_done_tasks(dir);
+    let index = translate_task_num(&open, task_num)?;
+    let task = open.remove(index).cancel(reason);
+    println!("🚫 Cancelled '{}'", task.description);
+    done.push(task);
+    done.sort();
+
+    io::write_done_tasks(dir, &done)?;
+    io::write_open_tasks(dir, &open)?;
+    print_after(dir, &open, &[], &tags);
+    Ok(())
+}
+
+pub fn wait(
+    dir: &Path,
+    task_num: usize,
+    on: Option<String>,
+    until: Option<Duration>,
+    clear: bool,
+) -> Result<()> {
+    let (mut tasks, tags) = read_tasks_tags(dir);
+    let index = translate_task_num(&tasks, task_num)?;
+    let task = &mut tasks[index];
+    match on {
+        _ if clear => {
+            task.stop_waiting();
+            println!("✅ '{}' is no longer waiting", task.description);
+        }
+        Some(on) => {
+            task.wait(on, until);
+            println!("⏳ '{}' is waiting", task.description);
+        }
+        None => bail!("please specify who the task is waiting on, or --clear"),
+    }
+    io::write_open_tasks(dir, &tasks)?;
+    print_after(dir, &tasks, &[index], &tags);
+    Ok(())
+}
+
+pub fn snooze(dir: &Path, task_num: usize, duration: Duration) -> Result<()> {
+    let (mut tasks, tags) = read_tasks_tags(dir);
+    let index = translate_task_num(&tasks, task_num)?;
+    let task = &mut tasks[index];
+    task.snooze(duration);
+    println!(
+        "💤 Snoozed '{}' for {}",
+        task.description,
+        humantime::format_duration(duration)
+    );
+    io::write_open_tasks(dir, &tasks)?;
+    print_after(dir, &tasks, &[index], &tags);
+    Ok(())
+}
+
+pub fn start(dir: &Path, task_num: usize) -> Result<()> {
+    let (mut tasks, tags) = read_tasks_tags(dir);
+    let index = translate_task_num(&tasks, task_num)?;
+    for task in tasks.iter_mut() {
+        task.stop_tracking();
+    }
+    let task = &mut tasks[index];
+    task.start_tracking();
+    println!("⏲ Started '{}'", task.description);
+    io::write_open_tasks(dir, &tasks)?;
+    print_after(dir, &tasks, &[index], &tags);
+    Ok(())
+}
+
+pub fn stop(dir: &Path) -> Result<()> {
+    let mut tasks = io::read_open_tasks(dir);
+    let Some(task) = tasks.iter_mut().find(|t| t.is_tracking()) else {
+        println!("No task is being tracked");
+        return Ok(());
+    };
+    task.stop_tracking();
+    println!(
+        "⏹ Stopped '{}', {} tracked in total",
+        task.description,
+        humantime::format_duration(task.time_tracked())
+    );
+    io::write_open_tasks(dir, &tasks)
+}
+
+pub fn link(dir: &Path, id: &str, url: String) -> Result<()> {
+    let mut tasks = io::read_open_tasks(dir);
+    let task = tasks
+        .iter_mut()
+        .find(|t| t.id() == id)
+        .ok_or_else(|| miette!("no open task with id {id}"))?;
+    println!("🔗 Linked '{}' to {url}", task.description);
+    if !task.links.contains(&url) {
+        task.links.push(url);
+        io::write_open_tasks(dir, &tasks)?;
+    }
+    Ok(())
+}
+
+pub fn open(dir: &Path, task_num: usize) -> Result<()> {
+    let tasks = io::read_open_tasks(dir);
+    let index = translate_task_num(&tasks, task_num)?;
+    let task = &tasks[index];
+    let link = task
+        .links
+        .first()
+        .ok_or_else(|| miette!("'{}' has no links", task.description))?;
+
+    // `explorer` takes the link as one argument, where `cmd /C start` would split it on `&`
+    let mut cmd = if cfg!(target_os = "macos") {
+        std::process::Command::new("open")
+    } else if cfg!(windows) {
+        std::process::Command::new("explorer")
+    } else {
+        std::process::Command::new("xdg-open")
+    };
+    cmd.arg(link)
+        .spawn()
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to open {link}"))?;
+    println!("🌐 Opened {link}");
+    Ok(())
+}
+
+pub fn context(dir: &Path, context: Option<Context>, clear: bool) -> Result<()> {
+    if clear {
+        io::write_active_context(dir, None)?;
+        println!("🌐 Showing tasks in all contexts");
+    } else if let Some(context) = context {
+        io::write_active_context(dir, Some(&context))?;
+        println!("📍 Active context is now @{}", &*context);
+    } else {
+        match io::read_active_context(dir) {
+            Some(c) => println!("📍 @{c}"),
+            None => println!("No active context"),
+        }
+    }
+    Ok(())
+}
+
+/// Remove expired temporal tags and roll over to the new day, once per UTC day.
+///
+/// Returns if it rolled over to a new day.
+pub fn maintain(dir: &Path) -> Result<bool> {
+    let today = crate::now() / stats::DAY;
+    let last = match io::read_last_run(dir) {
+        Some(last) if last >= today => return Ok(false),
+        Some(last) => last,
+        // nothing to expire on the very first run
+        None => return io::write_last_run(dir, today).map(|_| false),
+    };
+
+    // days since epoch start on a Thursday, weeks on a Monday
+    let week = |day: u64| (day + 3) / 7;
+    let config = io::read_config(dir);
+    let top = config.top();
+    let temporal = config.temporal_tags;
+    let mut expired = temporal.day;
+    if week(last) < week(today) {
+        expired.extend(temporal.week);
+    }
+
+    let mut tasks = io::read_open_tasks(dir);
+    let mut count = 0;
+    for task in tasks.iter_mut() {
+        let before = task.tags.len();
+        task.tags.retain(|t| !expired.contains(t));
+        count += usize::from(task.tags.len() != before);
+    }
+    if count > 0 {
+        io::write_open_tasks(dir, &tasks)?;
+        eprintln!("🧹 Removed expired tags from {count} tasks");
+    }
+    rollover(dir, &config.rollover, top)?;
+    io::write_last_run(dir, today)?;
+    Ok(true)
+}
+
+/// Start a new day: count yesterday's unfinished six as carried over, unlock yesterday's plan,
+/// sweep the finished tasks, and promote tomorrow's.
+fn rollover(dir: &Path, rollover: &Rollover, top: usize) -> Result<()> {
+    let mut tasks = io::read_open_tasks(dir);
+    let mut carried = 0;
+    for task in tasks
+        .iter_mut()
+        .filter(|t| !t.is_hidden())
+        .take(top)
+        .filter(|t| !t.is_finished())
+    {
+        task.carried += 1;
+        carried += 1;
+    }
+    if carried > 0 {
+        io::write_open_tasks(dir, &tasks)?;
+    }
+    if !io::read_plan_lock(dir).is_empty() {
+        io::write_plan_lock(dir, &[])?;
+        eprintln!("🔓 Unlocked yesterday's plan");
+    }
+    if rollover.sweep {
+        let (_, _, swept) = sweep_finished(dir, false)?;
+        if !swept.is_empty() {
+            eprintln!("🧹 Swept {} finished tasks from yesterday", swept.len());
+        }
+    }
+    if rollover.promote_tomorrow {
+        let n = promote_queued(dir)?;
+        if n > 0 {
+            eprintln!("🌅 Promoted {n} tasks from tomorrow's list");
+        }
+    }
+    Ok(())
+}
+
+/// Queue a task for tomorrow, keeping it out of today's six.
+pub fn add_tomorrow(dir: &Path, mut task: TodoTask) -> Result<()> {
+    register_project(dir, task.project.as_deref())?;
+    for rule in &io::read_config(dir).rules {
+        rule.apply(&mut task);
+    }
+    let mut tasks = io::read_tomorrow(dir);
+    read_ids(dir).assign(&mut task);
+    println!("🌅 Queued '{}' for tomorrow", task.description);
+    tasks.push(task);
+    io::write_tomorrow(dir, &tasks)
+}
+
+pub fn list_tomorrow(dir: &Path) {
+    let tasks = io::read_tomorrow(dir);
+    if tasks.is_empty() {
+        println!("Nothing is queued for tomorrow");
+    }
+    let tags = io::read_tags(dir);
+    let projects = io::read_projects(dir);
+    for (i, t) in tasks.iter().enumerate() {
+        print::todo_task(i, t, &tags, &projects, render(dir), std::io::stdout());
+    }
+}
+
+/// Move the tasks queued for tomorrow to the top of the open list, keeping their order.
+pub fn promote_tomorrow(dir: &Path) -> Result<()> {
+    match promote_queued(dir)? {
+        0 => println!("No tasks are queued for tomorrow"),
+        n => println!("🌅 Promoted {n} tasks from tomorrow's list"),
+    }
+    Ok(())
+}
+
+/// Move the queued tasks to the top of the open list, returning how many moved.
+fn promote_queued(dir: &Path) -> Result<usize> {
+    let queued = io::read_tomorrow(dir);
+    if queued.is_empty() {
+        return Ok(0);
+    }
+    let n = queued.len();
+    let mut tasks = io::read_open_tasks(dir);
+    tasks.splice(0..0, queued.0);
+    io::write_open_tasks(dir, &tasks)?;
+    io::write_tomorrow(dir, &Tasks::new())?;
+    Ok(n)
+}
+
+pub fn waiting(dir: &Path) {
+    let (tasks, tags) = read_tasks_tags(dir);
+    let projects = io::read_projects(dir);
+    tasks
+        .iter()
+        .enumerate()
+        .filter(|(_, t)| t.waiting().is_some())
+        .for_each(|(i, t)| {
+            print::todo_task(i, t, &tags, &projects, render(dir), std::io::stdout())
+        });
+}
+
+/// Print the open tasks older than `stale_after`, oldest first.
+pub fn stale(dir: &Path) {
+    let (tasks, tags) = read_tasks_tags(dir);
+    let projects = io::read_projects(dir);
+    let after = io::read_config(dir).stale_after();
+    let mut stale = tasks
+        .iter()
+        .enumerate()
+        .filter(|(_, t)| t.is_stale(after))
+        .collect::<Vec<_>>();
+    stale.sort_by_key(|(_, t)| t.created());
+
+    if stale.is_empty() {
+        println!("No tasks older than {}", humantime::format_duration(after));
+    }
+    for (i, task) in stale {
+        print::todo_task(i, task, &tags, &projects, render(dir), std::io::stdout());
+    }
+}
+
+pub fn delegated(dir: &Path) {
+    let (tasks, tags) = read_tasks_tags(dir);
+    let projects = io::read_projects(dir);
+    let mut delegated = tasks
+        .iter()
+        .enumerate()
+        .filter_map(|(i, t)| t.assignee.as_deref().map(|a| (a, i, t)))
+        .collect::<Vec<_>>();
+    delegated.sort_by_key(|(a, i, _)| (*a, *i));
+
+    let mut prev = None;
+    for (assignee, i, task) in delegated {
+        if prev != Some(assignee) {
+            println!("👤 {}", assignee.bold());
+            prev = Some(assignee);
+        }
+        print::todo_task(i, task, &tags, &projects, render(dir), std::io::stdout());
+    }
+}
+
+/// Print plain text for a standup: what was finished in the last day, the priority tasks left,
+/// and what is waiting on others, either delegated or waiting.
+pub fn standup(dir: &Path) {
+    let open = io::read_open_tasks(dir);
+    let done = io::read_done_tasks(dir);
+    let since = crate::now().saturating_sub(stats::DAY);
+    let top = io::read_config(dir).top();
+
+    let finished = open
+        .iter()
+        .filter_map(|t| Some((t.finished_at()?, &t.description)))
+        .chain(
+            done.iter()
+                .filter(|t| !t.is_cancelled())
+                .filter_map(|t| Some((t.finished_at()?, &t.description))),
+        )
+        .filter(|(at, _)| *at >= since)
+        .map(|(_, d)| d.as_str())
+        .collect::<Vec<_>>();
+    let today = open
+        .iter()
+        .filter(|t| !t.is_hidden())
+        .take(top)
+        .filter(|t| !t.is_finished())
+        .map(|t| t.description.as_str())
+        .collect::<Vec<_>>();
+    let waiting = open
+        .iter()
+        .filter(|t| !t.is_finished())
+        .filter_map(|t| {
+            let on = t
+                .assignee
+                .as_deref()
+                .or(t.waiting().map(|w| w.on.as_str()))?;
+            Some(format!("{on}: {}", t.description))
+        })
+        .collect::<Vec<_>>();
+
+    for (heading, items) in [
+        ("Done", finished),
+        ("Today", today),
+        ("Waiting on", waiting.iter().map(String::as_str).collect()),
+    ] {
+        if items.is_empty() {
+            continue;
+        }
+        println!("{heading}:");
+        for item in items {
+            println!("- {item}");
+        }
+    }
+}
+
+pub fn merge_tasks(dir: &Path, a: &str, b: &str) -> Result<()> {
+    ensure!(a != b, "cannot merge task `{a}` into itself");
+    let (mut tasks, tags) = read_tasks_tags(dir);
+    let find = |tasks: &TodoTasks, id: &str| {
+        tasks
+            .iter()
+            .position(|t| t.id() == id)
+            .ok_or_else(|| miette!("No open task found with ID '{id}'"))
+    };
+
+    let i = find(&tasks, b)?;
+    let task_b = tasks.remove(i);
+    let i = find(&tasks, a)?;
+    tasks[i].merge(task_b);
+
+    io::write_open_tasks(dir, &tasks)?;
+    println!("✅ Merged task `{b}` into `{a}`");
+    print_after(dir, &tasks, &[i], &tags);
+    Ok(())
+}
+
+/// Descriptions at least this similar are considered duplicates.
+const DUPLICATE_THRESHOLD: f64 = 0.75;
+
+/// Walk through near-duplicate open tasks in a TUI, merging them or deleting one.
+pub fn dedupe(dir: &Path) -> Result<()> {
+    let mut tasks = io::read_open_tasks(dir);
+    let mut tui = tui::Dedupe::new(&mut tasks, DUPLICATE_THRESHOLD);
+    let save = tui.run()?;
+    let trashed = std::mem::replace(&mut tui.trashed, Tasks::new());
+    if save {
+        io::write_open_tasks(dir, &tasks)?;
+        if !trashed.is_empty() {
+            let mut trash = io::read_trash(dir);
+            trash.extend(trashed.0);
+            io::write_trash(dir, &trash)?;
+        }
+        println!("✅ Saved changes");
+    } else {
+        println!("No changes made");
+    }
+    Ok(())
+}
+
+/// Counts of what [`purge`] cleaned up.
+#[derive(Default)]
+struct Purged {
+    empty: usize,
+    /// The tasks given fresh IDs, as `(old ID, new ID, description)`.
+    ids: Vec<(String, String, String)>,
+    tags: usize,
+}
+
+impl Purged {
+    fn is_empty(&self) -> bool {
+        self.empty + self.ids.len() + self.tags == 0
+    }
+}
+
+/// Trim, lowercase, and deduplicate tags or contexts, returning if any changed.
+fn normalise_labels(labels: &mut Vec<String>) -> bool {
+    let mut normal = Vec::with_capacity(labels.len());
+    for label in labels.iter() {
+        let label = label.trim().to_lowercase();
+        if !label.is_empty() && !normal.contains(&label) {
+            normal.push(label);
+        }
+    }
+    let changed = normal != *labels;
+    *labels = normal;
+    changed
+}
+
+/// Drop tasks with empty descriptions, re-ID clashing tasks, and normalise tags and contexts.
+///
+/// `ids` holds the IDs already in use, so clashes across files are caught too.
+fn purge_tasks<S>(tasks: &mut Tasks<S>, ids: &mut Ids, purged: &mut Purged) {
+    let len = tasks.len();
+    tasks.retain(|t| !t.description.trim().is_empty());
+    purged.empty += len - tasks.len();
+
+    for t in tasks.iter_mut() {
+        let id = t.id().to_string();
+        ids.keep(t);
+        if t.id() != id {
+            purged
+                .ids
+                .push((id, t.id().to_string(), t.description.clone()));
+        }
+
+        let tags = normalise_labels(&mut t.tags);
+        let contexts = normalise_labels(&mut t.contexts);
+        if tags || contexts {
+            purged.tags += 1;
+        }
+    }
+}
+
+/// Rename the tag styles to match the normalised tags, returning how many changed.
+///
+/// A style whose normalised tag is already styled is dropped.
+fn purge_tag_styles(tags: &mut Tags) -> usize {
+    let names = tags.iter().map(|(t, _)| t.to_string()).collect::<Vec<_>>();
+    let mut changed = 0;
+    for name in names {
+        let normal = name.trim().to_lowercase();
+        if normal == name {
+            continue;
+        }
+        if normal.is_empty() || tags.contains(&normal) {
+            tags.remove(&name);
+        } else {
+            tags.rename(&name, &normal);
+        }
+        changed += 1;
+    }
+    changed
+}
+
+/// Clean up the open and done tasks, reporting what was changed.
+///
+/// Removes tasks with empty descriptions, gives duplicate IDs fresh ones (open tasks keep
+/// theirs), and trims, lowercases, and deduplicates tags and contexts, renaming the tag
+/// styles to match.
+pub fn purge(dir: &Path) -> Result<()> {
+    let mut open = io::read_open_tasks(dir);
+    let mut done = io::read_done_tasks(dir);
+    let mut tags = io::read_tags(dir);
+    let mut ids = Ids::new(io::read_config(dir).ids);
+
+    let mut purged_open = Purged::default();
+    purge_tasks(&mut open, &mut ids, &mut purged_open);
+    let mut purged_done = Purged::default();
+    purge_tasks(&mut done, &mut ids, &mut purged_done);
+    let styles = purge_tag_styles(&mut tags);
+
+    if purged_open.is_empty() && purged_done.is_empty() && styles == 0 {
+        println!("Nothing to clean up");
+        return Ok(());
+    }
+    if !purged_open.is_empty() {
+        io::write_open_tasks(dir, &open)?;
+    }
+    if !purged_done.is_empty() {
+        io::write_done_tasks(dir, &done)?;
+    }
+    if styles > 0 {
+        io::write_tags(dir, &tags)?;
+    }
+
+    let empty = purged_open.empty + purged_done.empty;
+    let ids = purged_open
+        .ids
+        .into_iter()
+        .chain(purged_done.ids)
+        .collect::<Vec<_>>();
+    let tasks = purged_open.tags + purged_done.tags;
+    println!("✅ Cleaned up tasks:");
+    println!("      {empty} empty tasks removed");
+    println!("      {} duplicate IDs replaced", ids.len());
+    for (old, new, description) in ids {
+        println!("        {old} → {new} '{description}'");
+    }
+    println!("      {tasks} tasks with tags or contexts normalised");
+    println!("      {styles} tag styles renamed");
+    Ok(())
+}
+
+pub fn move_(dir: &Path, task_num: usize, insert_before: usize) -> Result<()> {
+    let (mut tasks, tags) = read_tasks_tags(dir);
+    let task = translate_task_num(&tasks, task_num)?;
+    let mut before = translate_task_num(&tasks, insert_before)?;
+    if task < before {
+        before = before.saturating_sub(1);
+    }
+    let top = io::read_config(dir).top();
+    ensure!(
+        tasks.reorder(task, before, top, &io::read_plan_lock(dir)),
+        "📌 moving task {task_num} would push a pinned task out of the top {top}, or change the locked plan"
+    );
+    io::write_open_tasks(dir, &tasks)?;
+    let (a, b) = (&tasks[before], &tasks[before + 1]);
+    println!(
+        "✅ Moved '{}' in front of '{}'!",
+        a.description, b.description
+    );
+    print_after(dir, &tasks, &[before], &tags);
+    Ok(())
+}
+
+/// Exchange the positions of two open tasks.
+pub fn swap(dir: &Path, a: &TaskRef, b: &TaskRef) -> Result<()> {
+    let (mut tasks, tags) = read_tasks_tags(dir);
+    let (i, j) = (resolve(&tasks, a)?, resolve(&tasks, b)?);
+    ensure!(i != j, "cannot swap task {} with itself", i + 1);
+    let top = io::read_config(dir).top();
+    ensure!(
+        tasks.exchange(i, j, top, &io::read_plan_lock(dir)),
+        "📌 swapping tasks {} and {} would push a pinned task out of the top {top}, or change the locked plan",
+        i + 1,
+        j + 1
+    );
+    io::write_open_tasks(dir, &tasks)?;
+    println!(
+        "✅ Swapped '{}' and '{}'!",
+        tasks[j].description, tasks[i].description
+    );
+    print_after(dir, &tasks, &[i, j], &tags);
+    Ok(())
+}
+
+pub fn move_interactive(dir: &Path) -> Result<()> {
+    let mut tasks = io::read_open_tasks(dir);
+    let mut done = io::read_done_tasks(dir);
+    let done_len = done.len();
+    let mut tui = tui::Move::new(&mut tasks, &mut done)
+        .ids(read_ids(dir))
+        .watch(dir)
+        .theme(&io::read_config(dir).theme)
+        .top(io::read_config(dir).top())
+        .projects(io::read_projects(dir))
+        .lock(io::read_plan_lock(dir));
+    let save = tui.run()?;
+    let trashed = std::mem::replace(&mut tui.trashed, Tasks::new());
+
+    if save {
+        io::write_open_tasks(dir, &tasks)?;
+        if done.len() != done_len {
+            io::write_done_tasks(dir, &done)?;
+        }
+        if !trashed.is_empty() {
+            let mut trash = io::read_trash(dir);
+            trash.extend(trashed.0);
+            io::write_trash(dir, &trash)?;
+        }
+        println!("✅ Saved changes");
+    } else {
+        println!("No changes made");
+    }
+    Ok(())
+}
+
+pub fn triage(dir: &Path) -> Result<()> {
+    let mut tasks = io::read_open_tasks(dir);
+    let top = io::read_config(dir).top();
+    let mut tui = tui::Triage::new(&mut tasks, top).lock(io::read_plan_lock(dir));
+    let save = tui.run()?;
+    let trashed = std::mem::replace(&mut tui.trashed, Tasks::new());
+    if save {
+        io::write_open_tasks(dir, &tasks)?;
+        if !trashed.is_empty() {
+            let mut trash = io::read_trash(dir);
+            trash.extend(trashed.0);
+            io::write_trash(dir, &trash)?;
+        }
+        println!("✅ Saved changes");
+    } else {
+        println!("No changes made");
+    }
+    Ok(())
+}
+
+/// Capture a thought into the inbox, out of the way of the open list.
+pub fn capture(dir: &Path, thought: String) -> Result<()> {
+    let mut inbox = io::read_inbox(dir);
+    let mut task = TodoTask::new(thought);
+    read_ids(dir).assign(&mut task);
+    inbox.push(task);
+    io::write_inbox(dir, &inbox)?;
+    println!("📥 Captured, {} in the inbox", inbox.len());
+    Ok(())
+}
+
+/// Work through the inbox, promoting items onto the end of the open list.
+pub fn triage_inbox(dir: &Path) -> Result<()> {
+    let mut inbox = io::read_inbox(dir);
+    let mut tui = tui::Triage::inbox(&mut inbox);
+    let save = tui.run()?;
+    if !save {
+        println!("No changes made");
+        return Ok(());
+    }
+    save_inbox_triage(dir, tui)
+}
+
+/// Write out a finished inbox triage: promoted items join the open list, deleted items go to
+/// the trash, and the rest stay in the inbox.
+pub fn save_inbox_triage(dir: &Path, mut triage: tui::Triage) -> Result<()> {
+    let trashed = std::mem::replace(&mut triage.trashed, Tasks::new());
+    let promoted = triage.take_promoted();
+    let inbox = triage.into_tasks();
+
+    if !promoted.is_empty() {
+        let mut open = io::read_open_tasks(dir);
+        // the promoted tasks keep their IDs, unless a task elsewhere has taken them
+        let mut ids = Ids::new(io::read_config(dir).ids);
+        ids.extend(&open);
+        ids.extend(&io::read_done_tasks(dir));
+        ids.extend(&io::read_trash(dir));
+        ids.extend(&io::read_tomorrow(dir));
+        ids.extend(inbox);
+        for mut task in promoted.into_iter() {
+            ids.keep(&mut task);
+            open.push(task);
+        }
+        io::write_open_tasks(dir, &open)?;
+    }
+    if !trashed.is_empty() {
+        let mut trash = io::read_trash(dir);
+        trash.extend(trashed.0);
+        io::write_trash(dir, &trash)?;
+    }
+    io::write_inbox(dir, inbox)?;
+    println!("✅ Saved changes");
+    Ok(())
+}
+
+/// Report the task files which cannot be read, and in a terminal, offer to replace each with
+/// its backup.
+pub fn check_task_files(dir: &Path) -> Result<()> {
+    for broken in io::broken_task_files(dir) {
+        let name = broken.name;
+        match &broken.error {
+            Some(e) => eprintln!("⚠️ Failed to read {name}.ron: {e}"),
+            None => eprintln!("⚠️ {name}.ron is missing"),
+        }
+        let Some((count, saved)) = broken.backup else {
+            eprintln!("⚠️ There is no readable backup, creating a new set");
+            continue;
+        };
+        let saved = saved
+            .map(|x| format!(" saved {} UTC", crate::datetime(x)))
+            .unwrap_or_default();
+        eprintln!(
+            "⚠️ Opening the backup{saved}, with {count} tasks ({} differ from {name}.ron)",
+            broken.differ
+        );
+        if broken.error.is_some() {
+            eprintln!("      the broken file is kept as {name}.broken.ron");
+        }
+
+        let terminal = std::io::IsTerminal::is_terminal(&std::io::stdin())
+            && std::io::IsTerminal::is_terminal(&std::io::stderr());
+        if terminal
+            && ask(&format!("Replace {name}.ron with the backup? [y/N]"))?.eq_ignore_ascii_case("y")
+        {
+            io::restore_backup(dir, name)?;
+            eprintln!("✅ Restored {name}.ron from the backup");
+        }
+    }
+    Ok(())
+}
+
+/// Print what changed in the open tasks since a previous version.
+///
+/// The previous version is the backup by default, or `against` as a file path or git ref.
+pub fn diff(dir: &Path, against: Option<String>) -> Result<()> {
+    let (old, source) = match against {
+        None => {
+            let file = dir.join("open.bak.ron");
+            let s = std::fs::read_to_string(&file)
+                .into_diagnostic()
+                .wrap_err("failed to read the backup tasks")?;
+            (s, "the backup".to_string())
+        }
+        Some(file) if Path::new(&file).is_file() => {
+            let s = std::fs::read_to_string(&file)
+                .into_diagnostic()
+                .wrap_err_with(|| format!("failed to read '{file}'"))?;
+            (s, format!("'{file}'"))
+        }
+        Some(rev) => {
+            let output = std::process::Command::new("git")
+                .arg("-C")
+                .arg(dir)
+                .args(["show", &format!("{rev}:./open.ron")])
+                .output()
+                .into_diagnostic()
+                .wrap_err("failed to run git")?;
+            ensure!(
+                output.status.success(),
+                "'{rev}' is not a file or a git ref with open.ron: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            (
+                String::from_utf8_lossy(&output.stdout).into_owned(),
+                format!("'{rev}'"),
+            )
+        }
+    };
+    let old: TodoTasks = ron::from_str(&old)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to parse the tasks in {source}"))?;
+    let new = io::read_open_tasks(dir);
+
+    let changes = diff::diff(&old, &new);
+    if changes.is_empty() {
+        println!("No changes since {source}");
+    }
+    for change in changes {
+        match change {
+            diff::Change::Added { num, description } => {
+                println!("{}", format!("+ {num:>3}. {description}").green())
+            }
+            diff::Change::Removed { num, description } => {
+                println!("{}", format!("- {num:>3}. {description}").red())
+            }
+            diff::Change::Moved {
+                from,
+                to,
+                description,
+            } => println!(
+                "{}",
+                format!("↕ {to:>3}. {description} (was {from})").yellow()
+            ),
+            diff::Change::Edited {
+                num,
+                description,
+                fields,
+            } => println!(
+                "{}",
+                format!("✎ {num:>3}. {description} ({})", fields.join(", ")).blue()
+            ),
+        }
+    }
+    Ok(())
+}
+
+/// The directory of the workspace to use, which must exist unless it is the default.
+pub fn open_workspace(base: &Path, workspace: Option<&str>) -> Result<PathBuf> {
+    let Some(name) = workspace else {
+        return Ok(base.to_path_buf());
+    };
+    let dir = io::workspace_dir(base, name)?;
+    ensure!(
+        dir.is_dir(),
+        help =
+            format!("create it with `ivly ws new {name}`, or go back with `ivly ws use default`"),
+        "no workspace named '{name}'"
+    );
+    Ok(dir)
+}
+
+pub fn list_workspaces(base: &Path) -> Result<()> {
+    let active = io::read_workspace(base).unwrap_or_else(|| "default".to_string());
+    let mut table = comfy_table::Table::new();
+    table
+        .load_preset(comfy_table::presets::UTF8_HORIZONTAL_ONLY)
+        .set_header(vec!["Workspace", "Open", "Done"]);
+    for name in std::iter::once("default".to_string()).chain(io::workspaces(base)) {
+        let dir = io::workspace_dir(base, &name)?;
+        let name = match name == active {
+            true => format!("▶ {name}").bold().to_string(),
+            false => format!("  {name}"),
+        };
+        let (open, done) = workspace_counts(&dir);
+        table.add_row(vec![name, open.to_string(), done.to_string()]);
+    }
+    println!("{table}");
+    Ok(())
+}
+
+/// The number of open and done tasks in a workspace, without warning about a new one.
+fn workspace_counts(dir: &Path) -> (usize, usize) {
+    let saved =
+        |file: &str| dir.join(file).exists() || dir.join(file.replace(".", ".bak.")).exists();
+    let open = if saved("open.ron") {
+        io::read_open_tasks(dir).len()
+    } else {
+        0
+    };
+    let done = if saved("done.ron") {
+        io::read_done_tasks(dir).len()
+    } else {
+        0
+    };
+    (open, done)
+}
+
+pub fn new_workspace(base: &Path, name: &str) -> Result<()> {
+    let dir = io::workspace_dir(base, name)?;
+    ensure!(!dir.exists(), "workspace '{name}' already exists");
+    std::fs::create_dir_all(&dir).into_diagnostic()?;
+    println!("🗂️ Created workspace '{name}', switch to it with `ivly ws use {name}`");
+    Ok(())
+}
+
+pub fn use_workspace(base: &Path, name: &str) -> Result<()> {
+    open_workspace(base, Some(name))?;
+    io::write_workspace(base, Some(name).filter(|x| *x != "default"))?;
+    println!("🗂️ Now using workspace '{name}'");
+    Ok(())
+}
+
+pub fn remove_workspace(base: &Path, name: &str, force: bool) -> Result<()> {
+    ensure!(name != "default", "the default workspace cannot be removed");
+    let dir = open_workspace(base, Some(name))?;
+    ensure!(
+        io::read_workspace(base).as_deref() != Some(name),
+        help = "switch to another with `ivly ws use`",
+        "workspace '{name}' is in use"
+    );
+    let (open, done) = workspace_counts(&dir);
+    ensure!(
+        open + done == 0 || force,
+        help = "use --force to remove it anyway",
+        "workspace '{name}' has {open} open and {done} done tasks"
+    );
+    let to = io::retire_workspace(base, name)?;
+    println!(
+        "🗑️ Removed workspace '{name}', its files are kept in {}",
+        to.display()
+    );
+    Ok(())
+}
+
+pub fn save_snapshot(dir: &Path, name: &str) -> Result<()> {
+    io::save_snapshot(dir, name)?;
+    println!("📸 Saved snapshot '{name}'");
+    Ok(())
+}
+
+pub fn restore_snapshot(dir: &Path, name: &str) -> Result<()> {
+    io::restore_snapshot(dir, name)?;
+    println!("✅ Restored snapshot '{name}'");
+    Ok(())
+}
+
+pub fn list_snapshots(dir: &Path) -> Result<()> {
+    let snaps = io::snapshots(dir);
+    if snaps.is_empty() {
+        println!("No snapshots saved");
+        return Ok(());
+    }
+    let mut table = comfy_table::Table::new();
     table
         .load_preset(comfy_table::presets::UTF8_HORIZONTAL_ONLY)
-        .set_header([
-            "ID",
-            "Task#",
-            "Description",
-            "Note",
-            "Status",
-            "Created",
-            "Finished",
-            "Tags",
+        .set_header(vec!["Name", "Taken", "Open", "Done"]);
+    for (name, taken) in snaps {
+        let (open, done) = io::read_snapshot_tasks(dir, &name)?;
+        table.add_row(vec![
+            name,
+            date(taken),
+            open.len().to_string(),
+            done.len().to_string(),
         ]);
+    }
+    println!("{table}");
+    Ok(())
+}
 
-    table.add_rows(open.enumerate().map(|(i, t)| {
-        [
-            t.id().to_string(),
-            format!("{}", i + 1),
-            t.description.clone(),
-            t.note.clone(),
-            if t.is_finished() {
-                "marked".to_string()
+/// Which tasks `list` shows, and how.
+#[derive(Default, Clone, Copy)]
+pub struct ListOptions {
+    /// Only the open tasks.
+    pub only_open: bool,
+    /// Only the done tasks.
+    pub only_done: bool,
+    /// Add a column of the time tracked.
+    pub time: bool,
+    /// The order to list in, rather than the saved order.
+    pub sort: Option<Sort>,
+}
+
+pub fn list(
+    dir: &Path,
+    opts: ListOptions,
+    filter: Filter,
+    r: print::Render,
+    mut wtr: impl std::io::Write,
+) {
+    let ListOptions {
+        only_open,
+        only_done,
+        time,
+        sort,
+    } = opts;
+    let fopen = only_open || !(only_open ^ only_done);
+    let fdone = only_done || !(only_open ^ only_done);
+
+    let mut open = io::read_open_tasks(dir)
+        .into_iter()
+        .filter(|_| fopen)
+        .filter(|t| filter.matches(t))
+        .enumerate()
+        .collect::<Vec<_>>();
+    let mut done = io::read_done_tasks(dir)
+        .into_iter()
+        .filter(|_| fdone)
+        .filter(|t| filter.matches(t))
+        .collect::<Vec<_>>();
+    let footer = {
+        let marked = open.iter().filter(|(_, t)| t.is_finished()).count();
+        let shown = format!(
+            "{} open, {marked} marked, {} done shown",
+            open.len(),
+            done.len()
+        );
+        match filter.describe() {
+            filters if filters.is_empty() => shown,
+            filters => format!("{shown} · filtered by {}", filters.join(", ")),
+        }
+    };
+    if let Some(Sort::Carried) = sort {
+        open.sort_by_key(|(_, t)| std::cmp::Reverse(t.carried));
+        done.sort_by_key(|t| std::cmp::Reverse(t.carried));
+    }
+    let carried = |desc: &str, n: u32| match n {
+        0 => desc.to_string(),
+        n => format!("{desc} (×{n})"),
+    };
+
+    // tracked time is shown to the minute
+    let tracked = |d: Duration| match d.as_secs() / 60 * 60 {
+        0 => String::new(),
+        x => humantime::format_duration(Duration::from_secs(x)).to_string(),
+    };
+    let with_time = |mut row: Vec<Cell>, d: Duration| {
+        if time {
+            row.push(Cell::new(tracked(d)));
+        }
+        row
+    };
+
+    // each label takes its own style, and the table measures the cells without the colour codes
+    let tags = io::read_tags(dir);
+    let labels = |task_tags: &[String], contexts: &[String]| {
+        let tags = task_tags.iter().map(|t| r.paint(tags.colourise(t, t)));
+        let contexts = contexts.iter().map(|c| r.paint(format!("@{c}").cyan()));
+        Cell::new(tags.chain(contexts).collect::<Vec<_>>().join(","))
+    };
+    let projects = io::read_projects(dir);
+    let project = |p: Option<&str>| {
+        let p = p.unwrap_or_default();
+        Cell::new(r.paint(projects.colourise(p, p)))
+    };
+    let grey = comfy_table::Color::DarkGrey;
+
+    let mut header = vec![
+        "ID",
+        "Task#",
+        "Description",
+        "Note",
+        "Status",
+        "Created",
+        "Finished",
+        "Project",
+        "Tags",
+    ];
+    if time {
+        header.push("Time");
+    }
+    let mut table = comfy_table::Table::new();
+    table
+        .load_preset(comfy_table::presets::UTF8_HORIZONTAL_ONLY)
+        .set_header(header);
+    match r.ansi {
+        true => table.enforce_styling(),
+        false => table.force_no_tty(),
+    };
+
+    table.add_rows(open.into_iter().map(|(i, t)| {
+        let description = Cell::new(carried(&t.description, t.carried));
+        let (description, status) = if t.is_finished() {
+            let x = description.add_attribute(Attribute::CrossedOut);
+            (x, Cell::new("marked").fg(comfy_table::Color::DarkGreen))
+        } else if t.is_waiting() {
+            let x = description.add_attribute(Attribute::Dim);
+            (x, Cell::new("waiting").fg(comfy_table::Color::DarkYellow))
+        } else if let Some(until) = t.snoozed_until() {
+            let x = format!("snoozed until\n{}", datetime(until));
+            (description, Cell::new(x).fg(comfy_table::Color::DarkBlue))
+        } else {
+            (
+                description.add_attribute(Attribute::Bold),
+                Cell::new("todo"),
+            )
+        };
+        let created = days_ago(t.duration_since_creation());
+        let row = vec![
+            Cell::new(t.id()).fg(grey),
+            Cell::new(format!(
+                "{}{}{}",
+                if t.is_pinned() { "📌" } else { "" },
+                t.priority.marker(),
+                i + 1
+            )),
+            description,
+            Cell::new(with_links(&t.note, &t.links)).add_attribute(Attribute::Italic),
+            status,
+            if t.is_stale(r.stale_after) {
+                Cell::new(format!("⚠ {created}")).fg(comfy_table::Color::DarkYellow)
             } else {
-                "todo".to_string()
+                Cell::new(created)
             },
-            days_ago(t.duration_since_creation()),
-            t.duration_since_finished()
-                .map(days_ago)
-                .unwrap_or_default(),
-            tag_csv(t.tags()),
-        ]
+            Cell::new(
+                t.duration_since_finished()
+                    .map(days_ago)
+                    .unwrap_or_default(),
+            ),
+            project(t.project.as_deref()),
+            labels(&t.tags, &t.contexts),
+        ];
+        with_time(row, t.time_tracked())
     }));
 
-    table.add_rows(done.map(|t| {
-        [
-            t.id().to_string(),
-            String::new(),
-            t.description.clone(),
-            t.note.clone(),
-            "done".to_string(),
-            days_ago(t.duration_since_creation()),
-            days_ago(t.duration_since_completed()),
-            tag_csv(t.tags()),
-        ]
+    table.add_rows(done.into_iter().map(|t| {
+        let row = vec![
+            Cell::new(t.id()).fg(grey),
+            Cell::new(t.priority.marker()),
+            Cell::new(carried(&t.description, t.carried)).add_attribute(Attribute::CrossedOut),
+            Cell::new(with_links(
+                &match t.cancel_reason() {
+                    Some(reason) if t.note.is_empty() => reason.to_string(),
+                    Some(reason) => format!("{}\n{reason}", t.note),
+                    None => t.note.clone(),
+                },
+                &t.links,
+            ))
+            .add_attribute(Attribute::Italic),
+            if t.is_cancelled() {
+                Cell::new("cancelled").fg(comfy_table::Color::DarkRed)
+            } else {
+                Cell::new("done").fg(comfy_table::Color::DarkGreen)
+            },
+            Cell::new(days_ago(t.duration_since_creation())),
+            Cell::new(days_ago(t.duration_since_completed())),
+            project(t.project.as_deref()),
+            labels(&t.tags, &t.contexts),
+        ];
+        with_time(row, t.time_tracked())
     }));
 
-    println!("{table}");
+    writeln!(wtr, "{table}").unwrap();
+    writeln!(wtr, "{footer}").unwrap();
+}
+
+/// A note followed by a line per link.
+fn with_links(note: &str, links: &[String]) -> String {
+    let links = links.iter().map(|l| format!("🔗 {l}"));
+    let lines = std::iter::once(note.to_string())
+        .filter(|n| !n.is_empty())
+        .chain(links);
+    lines.collect::<Vec<_>>().join("\n")
+}
+
+/// Compare today's busy blocks in a calendar with the planned tasks, in local time.
+///
+/// `ical` is an ICS file, or `-` to read one from stdin (eg piped from `curl`).
+pub fn agenda(dir: &Path, ical: &str) -> Result<()> {
+    if ical.starts_with("http://") || ical.starts_with("https://") {
+        return Err(miette!(
+            help = "pipe the feed in instead, eg `curl -s <url> | ivly agenda --ical -`",
+            "ivly does not fetch calendars over the network"
+        ));
+    }
+    let ics = if ical == "-" {
+        std::io::read_to_string(std::io::stdin())
+            .into_diagnostic()
+            .wrap_err("failed to read the calendar from stdin")?
+    } else {
+        std::fs::read_to_string(ical)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to read calendar '{ical}'"))?
+    };
+    let local = jiff::tz::TimeZone::system();
+    let (busy, warnings) = agenda::parse_ics(&ics, &local);
+    for warning in warnings {
+        eprintln!("⚠️ {warning}");
+    }
+
+    let config = io::read_config(dir);
+    let (start, end) = config.work_hours();
+    let (midnight, next) = agenda::day(crate::now(), &local)
+        .ok_or_else(|| miette!("failed to work out today's date"))?;
+    let hour = |x| agenda::at(midnight, x, &local).unwrap_or(midnight + x);
+    let (start, end) = (hour(start), hour(end));
+    let today = busy
+        .into_iter()
+        .filter(|b| b.end > midnight && b.start < next)
+        .collect::<Vec<_>>();
+
+    let time = |x: u64| agenda::clock(x, &local);
+    println!("📅 {}", "Calendar".bold());
+    if today.is_empty() {
+        println!("   Nothing booked today");
+    }
+    for b in &today {
+        println!("   {}–{} {}", time(b.start), time(b.end), b.summary);
+    }
+
+    let tasks = io::read_open_tasks(dir);
+    let planned = tasks
+        .iter()
+        .filter(|t| !t.is_hidden())
+        .take(io::read_config(dir).top())
+        .filter(|t| !t.is_finished())
+        .collect::<Vec<_>>();
+    println!("\n📝 {}", "Planned".bold());
+    for t in &planned {
+        match t.estimate() {
+            Some(e) => println!("   {} ({})", t.description, humantime::format_duration(e)),
+            None => println!("   {}", t.description),
+        }
+    }
+
+    let planned = planned.iter().filter_map(|t| t.estimate).sum::<u64>();
+    let free = agenda::free_time(&today, start.max(crate::now()), end);
+    let fmt = |x: u64| humantime::format_duration(Duration::from_secs(x / 60 * 60));
+    println!(
+        "\n⏱ {} planned, {} free in working hours",
+        fmt(planned),
+        fmt(free)
+    );
+    if planned > free {
+        println!(
+            "{}",
+            format!(
+                "⚠️ Planned work exceeds free time by {}",
+                fmt(planned - free)
+            )
+            .yellow()
+        );
+    }
+
+    Ok(())
+}
+
+pub fn report_aging(dir: &Path, format: Format) -> Result<()> {
+    let aging = report::aging(&io::read_open_tasks(dir), 5);
+    match format {
+        Format::Json => println!("{}", report::json(&aging)?),
+        // the buckets, then the oldest tasks, as two tables apart by a blank line
+        Format::Csv => {
+            println!("{}", report::render(&aging.buckets, format)?);
+            println!("\n{}", report::render(&aging.oldest, format)?);
+        }
+        Format::Table => {
+            println!("{}", report::render(&aging.buckets, format)?);
+            if !aging.oldest.is_empty() {
+                println!("\n🐢 {}", "Oldest tasks".bold());
+                for a in &aging.oldest {
+                    println!("   {:>4}d {} {}", a.age_days, a.id.dimmed(), a.description);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn report_latency(dir: &Path, format: Format) -> Result<()> {
+    let latency = report::latency(&io::read_open_tasks(dir), &io::read_done_tasks(dir));
+    println!("{}", report::render(&latency, format)?);
+    Ok(())
+}
+
+pub fn export(
+    dir: &Path,
+    format: export::Format,
+    include_titles: bool,
+    verify: bool,
+) -> Result<()> {
+    ensure!(
+        !verify || format == export::Format::Ron,
+        "only ron exports can be verified"
+    );
+    match format {
+        export::Format::Ron => {
+            let backup = export::Backup {
+                open: io::read_open_tasks(dir),
+                done: io::read_done_tasks(dir),
+                trash: io::read_trash(dir),
+            };
+            let s = export::ron(&backup)?;
+            if verify {
+                export::verify(&s)?;
+                eprintln!("✅ Verified the export reads back losslessly");
+            }
+            println!("{s}");
+        }
+        export::Format::SvgSummary => {
+            let open = io::read_open_tasks(dir);
+            let done = io::read_done_tasks(dir);
+            let this_week = stats::week(crate::now());
+            let open_ = open
+                .iter()
+                .filter_map(|t| Some((t.description.clone(), t.finished_at()?)));
+            let done_ = done
+                .iter()
+                .filter(|t| !t.is_cancelled())
+                .filter_map(|t| Some((t.description.clone(), t.finished_at()?)));
+            let titles = open_
+                .chain(done_)
+                .filter(|(_, at)| stats::week(*at) == this_week)
+                .map(|(desc, _)| desc)
+                .collect::<Vec<_>>();
+            // weeks start on a Monday, 4 days after the epoch's Thursday
+            let monday = (this_week * 7).saturating_sub(3) * stats::DAY;
+            let summary = export::Summary {
+                week_of: date(monday),
+                completed: titles.len(),
+                streak: stats::streaks(&stats::completion_days(&open, &done)).current,
+                titles: include_titles.then_some(titles.as_slice()),
+            };
+            print!("{}", export::svg_summary(&summary));
+        }
+    }
+    Ok(())
+}
+
+/// Import tasks from a file, appending the open ones to the backlog and the checked ones to done.
+pub fn import(dir: &Path, format: import::Format, file: &str) -> Result<()> {
+    let text = std::fs::read_to_string(file)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to read '{file}'"))?;
+    let mut ids = read_ids(dir);
+    let imported = match format {
+        import::Format::Md => {
+            let (tasks, warnings) = import::md(&text, &mut ids);
+            for warning in warnings {
+                eprintln!("⚠️ {warning}");
+            }
+            tasks
+        }
+        import::Format::Ron => return import_backup(dir, export::parse(&text)?),
+    };
+    if imported.is_empty() {
+        println!("No tasks found in '{file}'");
+        return Ok(());
+    }
+
+    let mut open = io::read_open_tasks(dir);
+    let mut done = io::read_done_tasks(dir);
+    let (mut opened, mut completed) = (0, 0);
+    for task in imported {
+        if task.is_finished() {
+            done.push(task.complete());
+            completed += 1;
+        } else {
+            open.push(task);
+            opened += 1;
+        }
+    }
+    if opened > 0 {
+        io::write_open_tasks(dir, &open)?;
+    }
+    if completed > 0 {
+        io::write_done_tasks(dir, &done)?;
+    }
+    println!("✅ Imported {opened} open and {completed} done tasks");
+    Ok(())
+}
+
+/// Restore the tasks of a backup, keeping their IDs and skipping tasks already present.
+fn import_backup(dir: &Path, backup: export::Backup) -> Result<()> {
+    let mut open = io::read_open_tasks(dir);
+    let mut done = io::read_done_tasks(dir);
+    let mut trash = io::read_trash(dir);
+    let mut ids = read_ids(dir);
+
+    let (o, d, t) = (open.len(), done.len(), trash.len());
+    open.extend(backup.open.into_iter().filter(|x| ids.insert(x.id())));
+    done.extend(backup.done.into_iter().filter(|x| ids.insert(x.id())));
+    trash.extend(backup.trash.into_iter().filter(|x| ids.insert(x.id())));
+    let (o, d, t) = (open.len() - o, done.len() - d, trash.len() - t);
+
+    if o > 0 {
+        io::write_open_tasks(dir, &open)?;
+    }
+    if d > 0 {
+        io::write_done_tasks(dir, &done)?;
+    }
+    if t > 0 {
+        io::write_trash(dir, &trash)?;
+    }
+    println!("✅ Imported {o} open, {d} done, and {t} trashed tasks");
+    Ok(())
+}
+
+pub fn chart(dir: &Path, days: u64) {
+    let open = io::read_open_tasks(dir);
+    let done = io::read_done_tasks(dir);
+    print::chart(
+        &stats::daily(&open, &done, days),
+        render(dir),
+        std::io::stdout(),
+    );
+}
+
+pub fn stats(dir: &Path, fun: bool, format: Format, filter: &Filter) -> Result<()> {
+    let mut open = io::read_open_tasks(dir);
+    let mut done = io::read_done_tasks(dir);
+    open.retain(|t| filter.matches(t));
+    done.retain(|t| filter.matches(t));
+    let filters = filter.describe();
+    let completed = stats::completed(&open, &done);
+    let streaks = stats::streaks(&stats::completion_days(&open, &done));
+    let times = stats::completion_times(&open, &done);
+    let (this_week, this_month) = stats::this_week_and_month(&times);
+    let busiest = stats::busiest_weekday(&times);
+
+    if format != Format::Table {
+        let summary = report::Summary {
+            completed,
+            current_streak: streaks.current,
+            longest_streak: streaks.longest,
+            this_week,
+            this_month,
+            busiest_weekday: busiest.map(|(d, _)| d.to_string()).unwrap_or_default(),
+        };
+        println!("{}", report::render(&[summary], format)?);
+        return Ok(());
+    }
+
+    if !filters.is_empty() {
+        println!(
+            "{}",
+            format!("Filtered: {}", filters.join(", ")).truecolor(127, 127, 127)
+        );
+    }
+    println!("Completed tasks: {completed}");
+    println!("  this week:     {this_week}");
+    println!("  this month:    {this_month}");
+    println!("Current streak:  {} days", streaks.current);
+    println!("Longest streak:  {} days", streaks.longest);
+    if let Some((day, n)) = busiest {
+        println!("Busiest weekday: {day} ({n} tasks)");
+    }
+
+    if fun {
+        let mut badges = io::read_badges(dir);
+        // badges are for all the tasks, not a filtered few
+        let new = match filters.is_empty() {
+            true => badges.award(completed, &streaks),
+            false => Vec::new(),
+        };
+        if !new.is_empty() {
+            io::write_badges(dir, &badges)?;
+        }
+
+        println!();
+        if badges.0.is_empty() {
+            println!("No badges yet, keep finishing tasks!");
+        }
+        for (name, earned) in &badges.0 {
+            let earned = format!("earned {}", date(*earned)).truecolor(127, 127, 127);
+            if new.contains(&name.as_str()) {
+                println!("🏅 {} {earned} {}", name.bold(), "✨ new!".bright_yellow());
+            } else {
+                println!("🏅 {} {earned}", name.bold());
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn list_tags(dir: &Path) {
+    let tags = io::read_tags(dir);
+    if tags.iter().next().is_none() {
+        println!("No tags are styled, set one with `ivly tag <tag> --fg <colour>`");
+        return;
+    }
+    print::tags(&tags, render(dir), std::io::stdout());
 }
 
 pub fn edit_tag(
@@ -261,50 +2405,82 @@ pub fn edit_tag(
     }
 
     io::write_tags(dir, &tags)?;
-    print::tags(&tags, std::io::stdout());
+    print::tags(&tags, render(dir), std::io::stdout());
     Ok(())
 }
 
-pub fn edit(
-    dir: &Path,
-    id: &str,
-    description: Option<String>,
-    note: Option<String>,
-    tags: Vec<FilterTag>,
-) -> Result<()> {
-    let mut tasks = io::read_open_tasks(dir);
-    let task = tasks.iter_mut().find(|t| t.id() == id);
-    if let Some(task) = task {
-        if let Some(d) = description {
+/// Changes made to a task by `edit`, and to a new task by `add`.
+#[derive(Default)]
+pub struct Changes {
+    pub description: Option<String>,
+    pub note: Option<String>,
+    pub tags: Vec<FilterTag>,
+    /// An empty string clears the project.
+    pub project: Option<String>,
+    /// An empty string clears the assignee.
+    pub assignee: Option<String>,
+    pub priority: Option<Priority>,
+    /// `Some(None)` clears the energy.
+    pub energy: Option<Option<Energy>>,
+    pub estimate: Option<Duration>,
+    /// Edit the note in the user's editor.
+    pub note_editor: bool,
+}
+
+impl Changes {
+    /// Open the editor on the note, if asked to.
+    fn open_editor(&mut self, note: &str) -> Result<()> {
+        if self.note_editor {
+            let note = self.note.as_deref().unwrap_or(note);
+            self.note = Some(io::edit_in_editor(note)?);
+        }
+        Ok(())
+    }
+
+    pub fn apply<S>(self, task: &mut Task<S>) {
+        if let Some(d) = self.description {
             task.description = d;
         }
-        if let Some(n) = note {
+        if let Some(n) = self.note {
             task.note = n;
         }
-        for t in tags {
-            if t.is_neg() {
-                task.remove_tag(&t);
-            } else {
-                task.add_tag(t);
-            }
+        if let Some(p) = self.project {
+            task.project = (!p.is_empty()).then_some(p);
+        }
+        if let Some(a) = self.assignee {
+            task.assignee = (!a.is_empty()).then_some(a);
+        }
+        if let Some(p) = self.priority {
+            task.priority = p;
+        }
+        if let Some(e) = self.energy {
+            task.energy = e;
+        }
+        if let Some(e) = self.estimate {
+            task.estimate = (!e.is_zero()).then_some(e.as_secs());
+        }
+        for t in self.tags {
+            t.apply(task);
         }
+    }
+}
+
+pub fn edit(dir: &Path, id: &str, mut changes: Changes) -> Result<()> {
+    register_project(dir, changes.project.as_deref().filter(|p| !p.is_empty()))?;
+    let (mut tasks, tags) = read_tasks_tags(dir);
+    if let Some(i) = tasks.iter().position(|t| t.id() == id) {
+        changes.open_editor(&tasks[i].note)?;
+        changes.apply(&mut tasks[i]);
         io::write_open_tasks(dir, &tasks)?;
         println!("✅ Edited task {id}");
+        print_after(dir, &tasks, &[i], &tags);
         return Ok(());
     }
 
     let mut tasks = io::read_done_tasks(dir);
-    let task = tasks.iter_mut().find(|t| t.id() == id);
-    if let Some(task) = task {
-        if let Some(d) = description {
-            task.description = d;
-        }
-        if let Some(n) = note {
-            task.note = n;
-        }
-        for t in tags {
-            task.add_tag(t);
-        }
+    if let Some(task) = tasks.iter_mut().find(|t| t.id() == id) {
+        changes.open_editor(&task.note)?;
+        changes.apply(task);
         io::write_done_tasks(dir, &tasks)?;
         println!("✅ Edited task {id}");
         return Ok(());
@@ -313,25 +2489,157 @@ pub fn edit(
     Err(miette!("No task found with ID '{id}'"))
 }
 
+/// Print what happened to a task, or the most recent events across all tasks.
+pub fn history(dir: &Path, task_id: Option<&str>) {
+    let mut events = history::read(dir);
+    match task_id {
+        Some(id) => events.retain(|e| e.task == id),
+        None => {
+            let skip = events.len().saturating_sub(50);
+            events.drain(..skip);
+        }
+    }
+    if events.is_empty() {
+        println!("No history found");
+    }
+    for e in events {
+        print!(
+            "{} {} {} {}",
+            datetime(e.time).truecolor(127, 127, 127),
+            e.task.bold(),
+            e.description,
+            format!("— {}", e.change).yellow()
+        );
+        match e.command {
+            Some(c) => println!(" {}", format!("({c})").truecolor(127, 127, 127)),
+            None => println!(),
+        }
+    }
+}
+
+/// Print every detail of an open task, or of a done task given by its full ID.
+pub fn show(dir: &Path, task: &TaskRef) -> Result<()> {
+    let open = io::read_open_tasks(dir);
+    let tags = io::read_tags(dir);
+    let mut events = history::read(dir);
+    match resolve(&open, task) {
+        Ok(i) => {
+            let t = &open[i];
+            events.retain(|e| e.task == t.id());
+            let status = if t.is_finished() {
+                format!("finished, task {}", i + 1)
+            } else if let Some(w) = t.waiting() {
+                format!("waiting on {}, task {}", w.on, i + 1)
+            } else if let Some(until) = t.snoozed_until() {
+                format!("snoozed until {} UTC, task {}", datetime(until), i + 1)
+            } else {
+                format!("open, task {}", i + 1)
+            };
+            print::card(t, &status, &tags, &events, std::io::stdout());
+        }
+        Err(e) => {
+            let done = io::read_done_tasks(dir);
+            let t = done.iter().find(|t| t.id() == task.as_str()).ok_or(e)?;
+            events.retain(|e| e.task == t.id());
+            let status = match t.cancel_reason() {
+                Some(reason) => format!("cancelled: {reason}"),
+                None if t.is_cancelled() => "cancelled".to_string(),
+                None => "done".to_string(),
+            };
+            print::card(t, &status, &tags, &events, std::io::stdout());
+        }
+    }
+    Ok(())
+}
+
+pub fn undo(dir: &Path, force: bool) -> Result<()> {
+    let entry = journal::undo(dir, force)?;
+    println!("↩️ Undid `{}`", entry.command);
+    let (tasks, tags) = read_tasks_tags(dir);
+    print_top(dir, &tasks, &tags, std::io::stdout());
+    Ok(())
+}
+
+/// Move a task into the trash.
 pub fn remove(dir: &Path, id: &str) -> Result<()> {
-    let mut tasks = io::read_open_tasks(dir);
-    let ol1 = tasks.len();
-    tasks.retain(|t| t.id() != id);
-    let nl1 = tasks.len();
-    io::write_open_tasks(dir, &tasks)?;
+    let mut trash = io::read_trash(dir);
+    let (mut tasks, tags) = read_tasks_tags(dir);
+    if let Some(i) = tasks.iter().position(|t| t.id() == id) {
+        trash.push(tasks.remove(i).trash());
+        io::write_trash(dir, &trash)?;
+        io::write_open_tasks(dir, &tasks)?;
+        println!("🗑 Moved task `{id}` from the todo list to the trash");
+        print_after(dir, &tasks, &[], &tags);
+        return Ok(());
+    }
 
     let mut tasks = io::read_done_tasks(dir);
-    let ol2 = tasks.len();
-    tasks.retain(|t| t.id() != id);
-    let nl2 = tasks.len();
-    io::write_done_tasks(dir, &tasks)?;
-
-    if ol1 != nl1 {
-        println!("✅ Removed task `{id}` from todo task list");
-    } else if ol2 != nl2 {
-        println!("✅ Removed task `{id}` from done task list");
-    } else {
-        return Err(miette!("task `{id}` not found in todo or done task lists"));
+    if let Some(i) = tasks.iter().position(|t| t.id() == id) {
+        trash.push(tasks.remove(i).trash());
+        io::write_trash(dir, &trash)?;
+        io::write_done_tasks(dir, &tasks)?;
+        println!("🗑 Moved task `{id}` from the done list to the trash");
+        return Ok(());
+    }
+
+    Err(miette!("task `{id}` not found in todo or done task lists"))
+}
+
+pub fn list_trash(dir: &Path) {
+    let trash = io::read_trash(dir);
+    if trash.is_empty() {
+        println!("The trash is empty");
+        return;
     }
+    let mut table = comfy_table::Table::new();
+    table
+        .load_preset(comfy_table::presets::UTF8_HORIZONTAL_ONLY)
+        .set_header(vec!["ID", "Description", "From", "Deleted"]);
+    for t in trash.iter().rev() {
+        table.add_row(vec![
+            t.id().to_string(),
+            t.description.clone(),
+            if t.was_open() { "todo" } else { "done" }.to_string(),
+            days_ago(Duration::from_secs(
+                crate::now().saturating_sub(t.deleted()),
+            )),
+        ]);
+    }
+    println!("{table}");
+}
+
+/// Move a task out of the trash, back into the list it was removed from.
+pub fn restore_trash(dir: &Path, id: &str) -> Result<()> {
+    let mut trash = io::read_trash(dir);
+    let i = trash
+        .iter()
+        .position(|t| t.id() == id)
+        .ok_or_else(|| miette!("task `{id}` not found in the trash"))?;
+    match trash.remove(i).restore() {
+        Restored::Todo(task) => {
+            let mut tasks = io::read_open_tasks(dir);
+            println!("✅ Restored '{}' to the todo list", task.description);
+            tasks.push(task);
+            io::write_open_tasks(dir, &tasks)?;
+        }
+        Restored::Done(task) => {
+            let mut tasks = io::read_done_tasks(dir);
+            println!("✅ Restored '{}' to the done list", task.description);
+            tasks.push(task);
+            tasks.sort();
+            io::write_done_tasks(dir, &tasks)?;
+        }
+    }
+    io::write_trash(dir, &trash)
+}
+
+/// Permanently delete the trashed tasks, or only those deleted longer than `older_than` ago.
+pub fn empty_trash(dir: &Path, older_than: Option<Duration>) -> Result<()> {
+    let mut trash = io::read_trash(dir);
+    let len = trash.len();
+    let cutoff = older_than.map(|d| crate::now().saturating_sub(d.as_secs()));
+    trash.retain(|t| cutoff.is_some_and(|c| t.deleted() >= c));
+    io::write_trash(dir, &trash)?;
+    println!("🗑 Permanently deleted {} tasks", len - trash.len());
     Ok(())
 }
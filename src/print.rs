@@ -1,50 +1,225 @@
-use crate::{days_ago, tags::Tags, task::TodoTask};
+use crate::{
+    datetime, days_ago,
+    history::Event,
+    markdown,
+    tags::{Projects, Tags},
+    task::{Energy, Priority, State, Task, TodoTask},
+};
 use colored::*;
+use std::{fmt::Write as _, io, time::Duration};
 
-pub fn todo_task(index: usize, task: &TodoTask, tags: &Tags) {
+/// How styled text is rendered.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Render {
+    /// ANSI escape codes, or the bare text for snapshots, docs, and other tools.
+    pub ansi: bool,
+    /// The age tasks are flagged as stale after, defaulting to 14 days.
+    pub stale_after: Duration,
+}
+
+impl Render {
+    /// Render with ANSI escape codes.
+    pub fn ansi() -> Self {
+        Render {
+            ansi: true,
+            stale_after: Duration::from_secs(14 * crate::stats::DAY),
+        }
+    }
+
+    /// Render the bare text.
+    pub fn plain() -> Self {
+        Render {
+            ansi: false,
+            ..Self::ansi()
+        }
+    }
+
+    /// How to render for the writer: ANSI for a terminal (or when `CLICOLOR_FORCE` is set), plain
+    /// otherwise.
+    pub fn to(wtr: &impl io::IsTerminal) -> Self {
+        let forced = std::env::var_os("CLICOLOR_FORCE").is_some_and(|x| x != "0");
+        if wtr.is_terminal() || forced {
+            Self::ansi()
+        } else {
+            Self::plain()
+        }
+    }
+
+    /// Flag tasks older than this as stale.
+    pub fn stale_after(mut self, after: Duration) -> Self {
+        self.stale_after = after;
+        self
+    }
+
+    /// Render the styled text, with `colored` still honouring `NO_COLOR`.
+    pub fn paint(self, s: ColoredString) -> String {
+        match self.ansi {
+            true => s.to_string(),
+            false => (*s).to_string(),
+        }
+    }
+}
+
+pub fn todo_task(
+    index: usize,
+    task: &TodoTask,
+    tags: &Tags,
+    projects: &Projects,
+    r: Render,
+    mut wtr: impl io::Write,
+) {
+    let x = render_todo_task(index, task, tags, projects, r);
+    wtr.write_all(x.as_bytes()).unwrap();
+}
+
+/// Render a task over multiple lines, as printed by [`todo_task`].
+pub fn render_todo_task(
+    index: usize,
+    task: &TodoTask,
+    tags: &Tags,
+    projects: &Projects,
+    r: Render,
+) -> String {
+    let mut out = String::new();
     let done = task.is_finished();
-    print!(
-        " {:>4} {}",
-        format!("{}.", index + 1).truecolor(127, 127, 127).bold(),
-        if done {
+    let energy = match task.energy {
+        Some(Energy::Low) => "● ".green(),
+        Some(Energy::Medium) => "● ".yellow(),
+        Some(Energy::High) => "● ".red(),
+        None => "".into(),
+    };
+    let _ = write!(
+        out,
+        " {:>4} {}{}",
+        r.paint(format!("{}.", index + 1).truecolor(127, 127, 127).bold()),
+        r.paint(energy),
+        r.paint(if done {
             task.description.bold().strikethrough()
+        } else if task.is_waiting() {
+            task.description.dimmed()
         } else {
             task.description.bold()
-        }
+        })
     );
 
+    if task.carried > 0 {
+        let x = format!("(×{})", task.carried);
+        out += &format!(" {}", r.paint(x.truecolor(127, 127, 127)));
+    }
+
+    if task.is_pinned() {
+        out += " 📌";
+    }
+
+    match task.priority {
+        Priority::High => out += &format!(" {}", r.paint("▲ high".red().bold())),
+        Priority::Low => out += &format!(" {}", r.paint("▼ low".blue())),
+        Priority::Normal => (),
+    }
+
+    if task.is_stale(r.stale_after) {
+        let x = format!("⚠ {}", days_ago(task.duration_since_creation()));
+        out += &format!(" {}", r.paint(x.yellow()));
+    }
+
     if let Some(finished) = task.duration_since_finished() {
-        print!(
-            " ➡ {}",
-            format!("Completed {}", days_ago(finished))
-                .green()
-                .underline()
-        )
+        let x = format!("Completed {}", days_ago(finished));
+        out += &format!(" ➡ {}", r.paint(x.green().underline()));
     }
-    println!();
+    out.push('\n');
 
-    if !task.note.is_empty() {
-        println!("       {}", task.note.italic());
+    if let Some(w) = task.waiting() {
+        let mut line = format!("⏳ waiting on {}", w.on);
+        if let Some(until) = w.until {
+            let now = crate::now();
+            if until > now {
+                let d = humantime::format_duration(Duration::from_secs(until - now)).to_string();
+                line += &format!(
+                    " (resurfaces in {})",
+                    d.split(' ').next().unwrap_or_default()
+                );
+            } else {
+                line += " (resurfaced)";
+            }
+        }
+        if task.is_waiting() {
+            let _ = writeln!(out, "       {}", r.paint(line.dimmed()));
+        } else {
+            let _ = writeln!(out, "       {}", r.paint(line.yellow()));
+        }
+    }
+
+    for line in markdown::parse(&task.note) {
+        out += "       ";
+        for span in line {
+            let mut x = span.text.normal();
+            if span.style.italic {
+                x = x.italic();
+            }
+            if span.style.bold {
+                x = x.bold();
+            }
+            if span.style.code {
+                x = x.cyan();
+            }
+            out += &r.paint(x);
+        }
+        out.push('\n');
+    }
+
+    for link in &task.links {
+        let _ = writeln!(out, "       🔗 {}", r.paint(link.blue().underline()));
     }
 
-    print!(
+    let created = days_ago(task.duration_since_creation());
+    let _ = write!(
+        out,
         "       {} ",
-        days_ago(task.duration_since_creation())
-            .truecolor(165, 165, 165)
-            .underline()
+        r.paint(created.truecolor(165, 165, 165).underline())
     );
 
     for tag in task.tags() {
-        print!("{} ", tags.colourise(tag, tag));
+        let _ = write!(out, "{} ", r.paint(tags.colourise(tag, tag)));
     }
 
-    println!();
+    for context in task.contexts() {
+        let _ = write!(out, "{} ", r.paint(format!("@{context}").cyan()));
+    }
+
+    if let Some(project) = &task.project {
+        let x = projects.colourise(project, &format!("📁 {project}"));
+        let _ = write!(out, "{} ", r.paint(x));
+    }
+
+    if let Some((done, total)) = task.progress() {
+        let x = format!("☑ {done}/{total}");
+        if done == total {
+            let _ = write!(out, "{} ", r.paint(x.green()));
+        } else {
+            let _ = write!(out, "{x} ");
+        }
+    }
+
+    if task.is_tracking() {
+        let _ = write!(out, "{} ", r.paint("⏲ tracking".red()));
+    }
+
+    if let Some(estimate) = task.estimate() {
+        let _ = write!(out, "⏱ {} ", humantime::format_duration(estimate));
+    }
+
+    if let Some(assignee) = &task.assignee {
+        let _ = write!(out, "👤 {assignee}");
+    }
+
+    out.push('\n');
+    out
 }
 
-pub fn tags(tags: &Tags, mut wtr: impl std::io::Write) {
+pub fn tags(tags: &Tags, r: Render, mut wtr: impl io::Write) {
     let ts = tags
         .iter()
-        .map(|(tag, _)| (tag.chars().count(), tags.colourise(tag, tag)))
+        .map(|(tag, _)| (tag.chars().count(), r.paint(tags.colourise(tag, tag))))
         .collect::<Vec<_>>();
     let fgs = tags
         .iter()
@@ -70,7 +245,7 @@ pub fn tags(tags: &Tags, mut wtr: impl std::io::Write) {
         bgs.iter().map(|x| x.0).max().unwrap_or_default().max(2),
     ];
 
-    fn pad(mut w: impl std::io::Write, n: usize) {
+    fn pad(mut w: impl io::Write, n: usize) {
         w.write_all(&vec![b' '; n]).unwrap();
     }
 
@@ -85,10 +260,174 @@ pub fn tags(tags: &Tags, mut wtr: impl std::io::Write) {
     for (((tl, tag), (fl, fg)), (bl, bg)) in ts.into_iter().zip(fgs).zip(bgs) {
         pad(&mut wtr, lens[0] - tl);
         write!(&mut wtr, "{}", tag).unwrap();
-        write!(&mut wtr, " {}", fg.color(fg)).unwrap();
+        write!(&mut wtr, " {}", r.paint(fg.color(fg))).unwrap();
         pad(&mut wtr, lens[1] - fl);
-        write!(&mut wtr, " {}", bg.on_color(bg)).unwrap();
+        write!(&mut wtr, " {}", r.paint(bg.on_color(bg))).unwrap();
         pad(&mut wtr, lens[2] - bl);
         writeln!(&mut wtr).unwrap();
     }
 }
+
+/// Print a backlog task on a single, dimmed line.
+pub fn peek(index: usize, task: &TodoTask, r: Render, mut wtr: impl io::Write) {
+    writeln!(
+        wtr,
+        " {:>4} {}",
+        r.paint(format!("{}.", index + 1).truecolor(90, 90, 90)),
+        r.paint(task.description.truecolor(127, 127, 127))
+    )
+    .unwrap();
+}
+
+/// Print the estimated time remaining for the priority tasks.
+pub fn remaining(
+    remaining: Duration,
+    capacity: Option<Duration>,
+    r: Render,
+    mut wtr: impl io::Write,
+) {
+    let x = format!(
+        "≈ {} remaining today",
+        humantime::format_duration(remaining)
+    );
+    writeln!(
+        wtr,
+        "      {}",
+        r.paint(x.italic().truecolor(127, 127, 127))
+    )
+    .unwrap();
+    if let Some(capacity) = capacity.filter(|c| remaining > *c) {
+        let x = format!(
+            "⚠️ Today's plan exceeds your {} capacity",
+            humantime::format_duration(capacity)
+        );
+        writeln!(wtr, "      {}", r.paint(x.yellow())).unwrap();
+    }
+}
+
+/// Print a bar chart of the tasks completed and open each day.
+pub fn chart(days: &[crate::stats::Day], r: Render, mut wtr: impl io::Write) {
+    const WIDTH: usize = 30;
+    let bar = |n: usize, max: usize| "█".repeat((n * WIDTH).div_ceil(max.max(1)));
+    let max_done = days.iter().map(|d| d.completed).max().unwrap_or_default();
+    let max_open = days.iter().map(|d| d.open).max().unwrap_or_default();
+
+    writeln!(
+        wtr,
+        "{:10}  {:>4} {:WIDTH$}  {:>4}",
+        "Date", "Done", "", "Open"
+    )
+    .unwrap();
+    for d in days {
+        let done = bar(d.completed, max_done);
+        let open = bar(d.open, max_open);
+        writeln!(
+            wtr,
+            "{}  {:>4} {}{}  {:>4} {}",
+            crate::date(d.day * crate::stats::DAY),
+            d.completed,
+            r.paint(done.as_str().green()),
+            " ".repeat(WIDTH - done.chars().count()),
+            d.open,
+            r.paint(open.as_str().truecolor(127, 127, 127)),
+        )
+        .unwrap();
+    }
+}
+
+pub fn celebrate(r: Render, mut wtr: impl io::Write) {
+    let art = r"
+        \o/   All of today's tasks are done!
+         |    Time to plan tomorrow's six.
+        / \
+";
+    writeln!(wtr, "{}", r.paint(art.bright_yellow().bold())).unwrap();
+}
+
+/// Print every detail of a task as a card, including its full note and history.
+pub fn card<S: State>(
+    task: &Task<S>,
+    status: &str,
+    tags: &Tags,
+    history: &[Event],
+    mut wtr: impl io::Write,
+) {
+    let field =
+        |name: &str, value: &str| format!("  {:<10} {value}\n", name.truecolor(127, 127, 127));
+    let mut out = format!("{}\n", task.description.bold());
+    out += &field("id", task.id());
+    out += &field("status", status);
+    let created = task.created();
+    out += &field(
+        "created",
+        &format!(
+            "{} UTC ({})",
+            datetime(created),
+            days_ago(task.duration_since_creation())
+        ),
+    );
+    if let Some(at) = task.finished_at() {
+        out += &field("finished", &format!("{} UTC", datetime(at)));
+    }
+    let labels = task
+        .tags()
+        .map(|t| tags.colourise(t, &format!("+{t}")).to_string())
+        .chain(task.contexts().map(|c| format!("@{c}").cyan().to_string()))
+        .collect::<Vec<_>>();
+    if !labels.is_empty() {
+        out += &field("tags", &labels.join(" "));
+    }
+    if let Some(project) = &task.project {
+        out += &field("project", project);
+    }
+    if let Some(assignee) = &task.assignee {
+        out += &field("assignee", assignee);
+    }
+    match task.priority {
+        Priority::High => out += &field("priority", "high"),
+        Priority::Low => out += &field("priority", "low"),
+        Priority::Normal => (),
+    }
+    match task.energy {
+        Some(Energy::Low) => out += &field("energy", "low"),
+        Some(Energy::Medium) => out += &field("energy", "medium"),
+        Some(Energy::High) => out += &field("energy", "high"),
+        None => (),
+    }
+    if let Some(estimate) = task.estimate() {
+        out += &field(
+            "estimate",
+            &humantime::format_duration(estimate).to_string(),
+        );
+    }
+    let tracked = task.time_tracked();
+    if !tracked.is_zero() {
+        out += &field("tracked", &humantime::format_duration(tracked).to_string());
+    }
+    if task.carried > 0 {
+        out += &field("carried", &format!("{} days", task.carried));
+    }
+    for (i, link) in task.links.iter().enumerate() {
+        out += &field(if i == 0 { "links" } else { "" }, link);
+    }
+    for (i, item) in task.checklist.iter().enumerate() {
+        let x = format!("[{}] {}", if item.done { "x" } else { " " }, item.text);
+        out += &field(if i == 0 { "checklist" } else { "" }, &x);
+    }
+
+    if !task.note.is_empty() {
+        out.push('\n');
+        for line in task.note.lines() {
+            out += &format!("  {line}\n");
+        }
+    }
+
+    if !history.is_empty() {
+        out.push('\n');
+        for e in history {
+            let time = format!("{} UTC", datetime(e.time));
+            out += &format!("  {} {}\n", time.truecolor(127, 127, 127), e.change);
+        }
+    }
+    wtr.write_all(out.as_bytes()).unwrap();
+}
@@ -0,0 +1,258 @@
+use crate::{
+    stats::DAY,
+    task::{DoneTasks, TodoTasks},
+};
+use miette::{IntoDiagnostic, Result};
+use std::{collections::BTreeMap, time::Duration};
+
+/// How a report is printed.
+#[derive(clap::ValueEnum, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Format {
+    /// A table for reading.
+    #[default]
+    Table,
+    /// Comma separated values, with raw numbers (durations are seconds).
+    Csv,
+    /// JSON, with raw numbers (durations are seconds).
+    Json,
+}
+
+/// A row of a report, printable as a table or CSV.
+pub trait Row: serde::Serialize {
+    const HEADER: &'static [&'static str];
+
+    /// The raw values of the row.
+    fn cells(&self) -> Vec<String>;
+
+    /// The values of the row for reading, defaulting to the raw values.
+    fn pretty(&self) -> Vec<String> {
+        self.cells()
+    }
+}
+
+/// Render the rows in the given format.
+pub fn render<R: Row>(rows: &[R], format: Format) -> Result<String> {
+    match format {
+        Format::Table => {
+            let mut table = comfy_table::Table::new();
+            table
+                .load_preset(comfy_table::presets::UTF8_HORIZONTAL_ONLY)
+                .set_header(R::HEADER.to_vec())
+                .add_rows(rows.iter().map(Row::pretty));
+            Ok(table.to_string())
+        }
+        Format::Csv => {
+            let lines = std::iter::once(R::HEADER.iter().map(|x| csv_field(x)).collect())
+                .chain(
+                    rows.iter()
+                        .map(|r| r.cells().iter().map(|x| csv_field(x)).collect()),
+                )
+                .map(|fields: Vec<_>| fields.join(","));
+            Ok(lines.collect::<Vec<_>>().join("\n"))
+        }
+        Format::Json => json(rows),
+    }
+}
+
+/// Render any report as pretty JSON.
+pub fn json<T: serde::Serialize + ?Sized>(report: &T) -> Result<String> {
+    serde_json::to_string_pretty(report).into_diagnostic()
+}
+
+/// Quote a CSV field if it needs it.
+fn csv_field(x: &str) -> String {
+    if x.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", x.replace('"', "\"\""))
+    } else {
+        x.to_string()
+    }
+}
+
+/// A duration to the minute, using the two largest units (eg `3days 4h`).
+fn short_duration(secs: u64) -> String {
+    let x = humantime::format_duration(Duration::from_secs(secs / 60 * 60)).to_string();
+    x.split(' ').take(2).collect::<Vec<_>>().join(" ")
+}
+
+/// Age buckets, as the label and the exclusive upper bound in days.
+const AGING_BUCKETS: &[(&str, u64)] = &[("0-2d", 3), ("3-7d", 8), ("1-4w", 30), (">1mo", u64::MAX)];
+
+#[derive(serde::Serialize)]
+pub struct Aging {
+    pub buckets: Vec<Bucket>,
+    /// The oldest open tasks, oldest first.
+    pub oldest: Vec<Aged>,
+}
+
+#[derive(serde::Serialize)]
+pub struct Bucket {
+    pub bucket: &'static str,
+    pub count: usize,
+}
+
+impl Row for Bucket {
+    const HEADER: &'static [&'static str] = &["Age", "Tasks"];
+    fn cells(&self) -> Vec<String> {
+        vec![self.bucket.to_string(), self.count.to_string()]
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct Aged {
+    pub id: String,
+    pub description: String,
+    pub age_days: u64,
+}
+
+impl Row for Aged {
+    const HEADER: &'static [&'static str] = &["ID", "Description", "Age (days)"];
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.id.clone(),
+            self.description.clone(),
+            self.age_days.to_string(),
+        ]
+    }
+}
+
+/// Bucket the unfinished open tasks by how long ago they were created.
+pub fn aging(tasks: &TodoTasks, oldest: usize) -> Aging {
+    let now = crate::now();
+    let mut aged = tasks
+        .iter()
+        .filter(|t| !t.is_finished())
+        .map(|t| Aged {
+            id: t.id().to_string(),
+            description: t.description.clone(),
+            age_days: now.saturating_sub(t.created()) / DAY,
+        })
+        .collect::<Vec<_>>();
+    aged.sort_by_key(|a| std::cmp::Reverse(a.age_days));
+
+    let mut buckets = AGING_BUCKETS
+        .iter()
+        .map(|(bucket, _)| Bucket { bucket, count: 0 })
+        .collect::<Vec<_>>();
+    for a in &aged {
+        let i = AGING_BUCKETS
+            .iter()
+            .position(|(_, max)| a.age_days < *max)
+            .unwrap_or_default();
+        buckets[i].count += 1;
+    }
+
+    aged.truncate(oldest);
+    Aging {
+        buckets,
+        oldest: aged,
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct Latency {
+    pub tag: String,
+    pub count: usize,
+    /// Seconds from creation to completion.
+    pub median: u64,
+    pub p90: u64,
+}
+
+impl Row for Latency {
+    const HEADER: &'static [&'static str] = &["Tag", "Completed", "Median", "90th percentile"];
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.tag.clone(),
+            self.count.to_string(),
+            self.median.to_string(),
+            self.p90.to_string(),
+        ]
+    }
+    fn pretty(&self) -> Vec<String> {
+        vec![
+            self.tag.clone(),
+            self.count.to_string(),
+            short_duration(self.median),
+            short_duration(self.p90),
+        ]
+    }
+}
+
+/// Completion statistics.
+#[derive(serde::Serialize)]
+pub struct Summary {
+    pub completed: usize,
+    pub current_streak: u64,
+    pub longest_streak: u64,
+    pub this_week: usize,
+    pub this_month: usize,
+    /// Empty if nothing has been completed.
+    pub busiest_weekday: String,
+}
+
+impl Row for Summary {
+    const HEADER: &'static [&'static str] = &[
+        "Completed",
+        "Current streak",
+        "Longest streak",
+        "This week",
+        "This month",
+        "Busiest weekday",
+    ];
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.completed.to_string(),
+            self.current_streak.to_string(),
+            self.longest_streak.to_string(),
+            self.this_week.to_string(),
+            self.this_month.to_string(),
+            self.busiest_weekday.clone(),
+        ]
+    }
+}
+
+/// Time from creation to completion of the completed tasks, grouped by tag.
+///
+/// Untagged tasks are grouped under `(untagged)`; cancelled tasks are excluded.
+pub fn latency(open: &TodoTasks, done: &DoneTasks) -> Vec<Latency> {
+    let mut by_tag = BTreeMap::<String, Vec<u64>>::new();
+    let mut push = |tags: Vec<&str>, secs: u64| {
+        if tags.is_empty() {
+            by_tag.entry("(untagged)".into()).or_default().push(secs);
+        }
+        for tag in tags {
+            by_tag.entry(tag.to_string()).or_default().push(secs);
+        }
+    };
+
+    for t in open.iter() {
+        if let Some(f) = t.finished_at() {
+            push(t.tags().collect(), f.saturating_sub(t.created()));
+        }
+    }
+    for t in done.iter().filter(|t| !t.is_cancelled()) {
+        if let Some(f) = t.finished_at() {
+            push(t.tags().collect(), f.saturating_sub(t.created()));
+        }
+    }
+
+    let mut latencies = by_tag
+        .into_iter()
+        .map(|(tag, mut xs)| {
+            xs.sort_unstable();
+            Latency {
+                tag,
+                count: xs.len(),
+                median: percentile(&xs, 50),
+                p90: percentile(&xs, 90),
+            }
+        })
+        .collect::<Vec<_>>();
+    latencies.sort_by_key(|l| std::cmp::Reverse(l.median));
+    latencies
+}
+
+/// Nearest-rank percentile of sorted values.
+fn percentile(sorted: &[u64], p: usize) -> u64 {
+    let rank = (p * sorted.len()).div_ceil(100).max(1);
+    sorted.get(rank - 1).copied().unwrap_or_default()
+}
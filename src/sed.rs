@@ -0,0 +1,50 @@
+//! `s/pattern/replacement/flags` substitutions over task text.
+use regex::{Regex, RegexBuilder};
+use std::{borrow::Cow, str::FromStr};
+
+/// A sed style substitution.
+///
+/// Any character after the `s` is the delimiter. Flags are `g` to replace every match
+/// (otherwise only the first), and `i` to ignore case. The replacement may use `$1` groups.
+#[derive(Clone, Debug)]
+pub struct Substitution {
+    regex: Regex,
+    replacement: String,
+    global: bool,
+}
+
+impl FromStr for Substitution {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rest = s
+            .strip_prefix('s')
+            .ok_or("expected a substitution like s/old/new/")?;
+        let delim = rest.chars().next().ok_or("missing the delimiter")?;
+        let parts = rest[delim.len_utf8()..].split(delim).collect::<Vec<_>>();
+        let [pattern, replacement, flags] = parts[..] else {
+            return Err(format!("expected s{delim}old{delim}new{delim}"));
+        };
+        if let Some(x) = flags.chars().find(|c| !matches!(c, 'g' | 'i')) {
+            return Err(format!("unknown flag '{x}'"));
+        }
+        let regex = RegexBuilder::new(pattern)
+            .case_insensitive(flags.contains('i'))
+            .build()
+            .map_err(|e| e.to_string())?;
+        Ok(Substitution {
+            regex,
+            replacement: replacement.to_string(),
+            global: flags.contains('g'),
+        })
+    }
+}
+
+impl Substitution {
+    pub fn apply<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        if self.global {
+            self.regex.replace_all(text, self.replacement.as_str())
+        } else {
+            self.regex.replace(text, self.replacement.as_str())
+        }
+    }
+}
@@ -0,0 +1,97 @@
+/// Similarity of two strings from 0 (nothing in common) to 1 (identical, ignoring case).
+///
+/// Uses the Sørensen–Dice coefficient over character bigrams of each word.
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (bigrams(a), bigrams(b));
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+
+    let mut b_ = b.clone();
+    let common = a
+        .iter()
+        .filter(|x| match b_.iter().position(|y| y == *x) {
+            Some(i) => {
+                b_.swap_remove(i);
+                true
+            }
+            None => false,
+        })
+        .count();
+    (2 * common) as f64 / (a.len() + b.len()) as f64
+}
+
+fn bigrams(s: &str) -> Vec<(char, char)> {
+    s.split_whitespace()
+        .flat_map(|w| {
+            let w = w.to_lowercase().chars().collect::<Vec<_>>();
+            w.windows(2).map(|x| (x[0], x[1])).collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Tags suggested for a description, from the tags of past tasks sharing most of its words.
+///
+/// Words of two letters or fewer are ignored. Tags are ordered by how many past tasks had them.
+pub fn suggest_tags<'a>(
+    description: &str,
+    history: impl Iterator<Item = (&'a str, &'a [String])>,
+) -> Vec<String> {
+    let words = tokens(description);
+    if words.is_empty() {
+        return Vec::new();
+    }
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for (desc, tags) in history {
+        let other = tokens(desc);
+        let shared = words.iter().filter(|w| other.contains(w)).count();
+        if shared * 2 < words.len() || shared == 0 {
+            continue;
+        }
+        for tag in tags {
+            match counts.iter_mut().find(|(t, _)| t == tag) {
+                Some((_, n)) => *n += 1,
+                None => counts.push((tag, 1)),
+            }
+        }
+    }
+    counts.sort_by_key(|(_, n)| std::cmp::Reverse(*n));
+    counts.into_iter().map(|(t, _)| t.to_string()).collect()
+}
+
+fn tokens(s: &str) -> Vec<String> {
+    let mut words = s
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.chars().count() > 2)
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>();
+    words.sort();
+    words.dedup();
+    words
+}
+
+/// How well `query` fuzzily matches `text`, higher being better, or `None` if it doesn't.
+///
+/// Every query character must appear in order, ignoring case. Consecutive runs and matches at
+/// the start of words score higher; a gap between matches costs a little.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let text = text.to_lowercase().chars().collect::<Vec<_>>();
+    let mut score = 0;
+    let mut at = 0;
+    let mut prev: Option<usize> = None;
+    for q in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let i = at + text[at..].iter().position(|c| *c == q)?;
+        score += 1;
+        if prev.is_some_and(|p| p + 1 == i) {
+            score += 5;
+        } else if let Some(p) = prev {
+            score -= (i - p).min(5) as i64;
+        }
+        if i == 0 || !text[i - 1].is_alphanumeric() {
+            score += 3;
+        }
+        prev = Some(i);
+        at = i + 1;
+    }
+    Some(score)
+}
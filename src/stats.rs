@@ -0,0 +1,176 @@
+use crate::task::{DoneTasks, TodoTasks};
+use std::collections::{btree_map::Entry, BTreeMap, BTreeSet};
+
+pub const DAY: u64 = 60 * 60 * 24;
+
+const WEEKDAYS: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+
+/// When (seconds since UNIX epoch) each task was completed, excluding cancelled ones.
+pub fn completion_times(open: &TodoTasks, done: &DoneTasks) -> Vec<u64> {
+    let open = open.iter().filter_map(|t| t.finished_at());
+    let done = done
+        .iter()
+        .filter(|t| !t.is_cancelled())
+        .filter_map(|t| t.finished_at());
+    open.chain(done).collect()
+}
+
+/// The UTC days (since the UNIX epoch) with at least one completed task.
+pub fn completion_days(open: &TodoTasks, done: &DoneTasks) -> BTreeSet<u64> {
+    completion_times(open, done)
+        .into_iter()
+        .map(|x| x / DAY)
+        .collect()
+}
+
+/// A UTC day's completed tasks, and the tasks open at its end.
+#[derive(Debug, PartialEq)]
+pub struct Day {
+    /// Days since the UNIX epoch.
+    pub day: u64,
+    pub completed: usize,
+    pub open: usize,
+}
+
+/// The last `days` UTC days, ending today, from the tasks' created and finished times.
+pub fn daily(open: &TodoTasks, done: &DoneTasks, days: u64) -> Vec<Day> {
+    let today = crate::now() / DAY;
+    let times = completion_times(open, done);
+    // (created, closed) for every task, closed being when it was finished or cancelled
+    let spans = open
+        .iter()
+        .map(|t| (t.created(), t.finished_at()))
+        .chain(done.iter().map(|t| (t.created(), t.finished_at())))
+        .collect::<Vec<_>>();
+    (today.saturating_sub(days.saturating_sub(1))..=today)
+        .map(|day| {
+            let end = (day + 1) * DAY;
+            Day {
+                day,
+                completed: times.iter().filter(|&&x| x / DAY == day).count(),
+                open: spans
+                    .iter()
+                    .filter(|(created, closed)| *created < end && closed.is_none_or(|x| x >= end))
+                    .count(),
+            }
+        })
+        .collect()
+}
+
+/// The UTC week (since the UNIX epoch, starting Mondays) of a time in seconds.
+pub fn week(secs: u64) -> u64 {
+    // days since epoch start on a Thursday
+    (secs / DAY + 3) / 7
+}
+
+/// Tasks completed in the current UTC week (from Monday) and calendar month.
+pub fn this_week_and_month(times: &[u64]) -> (usize, usize) {
+    let month = |secs: u64| crate::date(secs)[.."YYYY-MM".len()].to_string();
+    let now = crate::now();
+    let (this_week, this_month) = (week(now), month(now));
+    let w = times.iter().filter(|&&x| week(x) == this_week).count();
+    let m = times.iter().filter(|&&x| month(x) == this_month).count();
+    (w, m)
+}
+
+/// The UTC weekday the most tasks were completed on, with how many.
+pub fn busiest_weekday(times: &[u64]) -> Option<(&'static str, usize)> {
+    let mut counts = [0; 7];
+    for x in times {
+        counts[((x / DAY + 3) % 7) as usize] += 1;
+    }
+    // the first weekday wins a tie
+    let (i, n) = counts
+        .into_iter()
+        .enumerate()
+        .rev()
+        .max_by_key(|(_, n)| *n)?;
+    (n > 0).then_some((WEEKDAYS[i], n))
+}
+
+/// The number of completed tasks, excluding cancelled ones.
+pub fn completed(open: &TodoTasks, done: &DoneTasks) -> usize {
+    open.iter().filter(|t| t.is_finished()).count()
+        + done.iter().filter(|t| !t.is_cancelled()).count()
+}
+
+pub struct Streaks {
+    /// Consecutive days ending today, or yesterday if nothing has been completed today yet.
+    pub current: u64,
+    pub longest: u64,
+}
+
+pub fn streaks(days: &BTreeSet<u64>) -> Streaks {
+    let today = crate::now() / DAY;
+
+    let mut longest = 0;
+    let mut run = 0;
+    let mut prev = None;
+    for &day in days {
+        run = if prev == Some(day.wrapping_sub(1)) {
+            run + 1
+        } else {
+            1
+        };
+        longest = longest.max(run);
+        prev = Some(day);
+    }
+
+    let current = match prev {
+        Some(last) if last + 1 >= today => run,
+        _ => 0,
+    };
+
+    Streaks { current, longest }
+}
+
+enum Milestone {
+    Completed(usize),
+    Streak(u64),
+}
+
+const BADGES: &[(&str, Milestone)] = &[
+    ("First steps: 10 tasks done", Milestone::Completed(10)),
+    ("Half century: 50 tasks done", Milestone::Completed(50)),
+    ("Centurion: 100 tasks done", Milestone::Completed(100)),
+    ("Machine: 500 tasks done", Milestone::Completed(500)),
+    ("Legend: 1000 tasks done", Milestone::Completed(1000)),
+    ("On a roll: 7 day streak", Milestone::Streak(7)),
+    ("Habit formed: 30 day streak", Milestone::Streak(30)),
+    ("Unstoppable: 100 day streak", Milestone::Streak(100)),
+];
+
+/// Badges earned, with the seconds since UNIX epoch they were earned.
+#[derive(serde::Deserialize, serde::Serialize, Default)]
+#[serde(transparent)]
+pub struct Badges(pub BTreeMap<String, u64>);
+
+impl Badges {
+    /// Award any badges reached, returning the newly earned ones.
+    pub fn award(&mut self, completed: usize, streaks: &Streaks) -> Vec<&'static str> {
+        let now = crate::now();
+        BADGES
+            .iter()
+            .filter(|(_, m)| match m {
+                Milestone::Completed(n) => completed >= *n,
+                Milestone::Streak(n) => streaks.longest >= *n,
+            })
+            .filter(|(name, _)| match self.0.entry(name.to_string()) {
+                Entry::Vacant(e) => {
+                    e.insert(now);
+                    true
+                }
+                Entry::Occupied(_) => false,
+            })
+            .map(|(name, _)| *name)
+            .collect()
+    }
+}
@@ -1,3 +1,4 @@
+use crate::task::{State, Task};
 use colored::{Color, ColoredString, Colorize};
 use std::{collections::BTreeMap, ops::Deref, str::FromStr};
 
@@ -34,19 +35,56 @@ impl Tags {
     }
 
     pub fn colourise(&self, tag: &str, text: &str) -> ColoredString {
-        match self.0.get(tag) {
-            Some(Style { fg, bg }) => {
-                let mut s = text.color(fg.parse().unwrap_or(Color::White));
-                if let Some(bg) = bg.as_ref().and_then(|x| x.parse::<Color>().ok()) {
-                    s = s.on_color(bg);
+        match self.colours(tag) {
+            Some((fg, bg)) => {
+                let s = text.color(fg);
+                match bg {
+                    Some(bg) => s.on_color(bg),
+                    None => s,
                 }
-                s
             }
             None => text.into(),
         }
     }
+
+    /// The foreground and background colours of a styled tag.
+    pub fn colours(&self, tag: &str) -> Option<(Color, Option<Color>)> {
+        let Style { fg, bg } = self.0.get(tag)?;
+        let bg = bg.as_ref().and_then(|x| x.parse::<Color>().ok());
+        Some((fg.parse().unwrap_or(Color::White), bg))
+    }
 }
 
+impl Tags {
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+
+    /// Add the name with the default style, if it is not already styled.
+    pub fn insert(&mut self, name: &str) {
+        self.0.entry(name.to_string()).or_default();
+    }
+
+    /// Forget the style. Returns `false` if `name` is unknown.
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.0.remove(name).is_some()
+    }
+
+    /// Rename, keeping the style. Returns `false` if `from` is unknown.
+    pub fn rename(&mut self, from: &str, to: &str) -> bool {
+        match self.0.remove(from) {
+            Some(style) => {
+                self.0.insert(to.to_string(), style);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Projects are styled like tags, keyed by the project name.
+pub type Projects = Tags;
+
 fn colour_string(c: Color) -> String {
     match c {
         Color::Black => "black",
@@ -93,6 +131,114 @@ impl FromStr for AddTag {
     }
 }
 
+/// A place or situation a task can be done in, written `@home`.
+#[derive(Clone)]
+pub struct Context(pub String);
+
+impl Deref for Context {
+    type Target = str;
+    fn deref(&self) -> &Self::Target {
+        self.0.as_str()
+    }
+}
+impl FromStr for Context {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.strip_prefix('@')
+            .map(|x| Context(x.to_string()))
+            .ok_or("context must start with @")
+    }
+}
+
+/// A `+tag` or `@context` given to a new task.
+#[derive(Clone)]
+pub enum Label {
+    Tag(AddTag),
+    Context(Context),
+}
+
+impl FromStr for Label {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        AddTag::from_str(s)
+            .map(Label::Tag)
+            .or_else(|_| Context::from_str(s).map(Label::Context))
+            .map_err(|_| "label must start with + or @")
+    }
+}
+
+impl std::fmt::Display for Label {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Label::Tag(x) => write!(f, "+{}", &**x),
+            Label::Context(x) => write!(f, "@{}", &**x),
+        }
+    }
+}
+
+impl Label {
+    pub fn apply<S>(self, task: &mut Task<S>) {
+        match self {
+            Label::Tag(x) => task.add_tag(x),
+            Label::Context(x) => task.add_context(x.0),
+        }
+    }
+
+    /// The task has this label.
+    pub fn is_on<S>(&self, task: &Task<S>) -> bool {
+        match self {
+            Label::Tag(x) => task.tags().any(|t| t == &**x),
+            Label::Context(x) => task.contexts().any(|c| c == &**x),
+        }
+    }
+
+    pub fn remove_from<S>(&self, task: &mut Task<S>) {
+        match self {
+            Label::Tag(x) => task.remove_tag(x),
+            Label::Context(x) => task.remove_context(x),
+        }
+    }
+}
+
+/// Pull `+tag` and `@context` words out of a free text description.
+///
+/// Returns the description without the labels, and the labels found. Each label is removed with
+/// the space before it (or after it, at the start), so the rest keeps its spacing.
+///
+/// Dates, times, and recurrences are not parsed: tasks have no due dates, as the six are chosen
+/// each day.
+pub fn extract_inline(description: &str) -> (String, Vec<Label>) {
+    let mut labels = Vec::new();
+    let mut kept = String::new();
+    let mut last = 0;
+    let mut leading = false;
+    for word in regex::Regex::new(r"\S+")
+        .expect("valid")
+        .find_iter(description)
+    {
+        let gap = &description[last..word.start()];
+        last = word.end();
+        match word.as_str().parse::<Label>() {
+            Ok(Label::Tag(x)) if x.is_empty() => (),
+            Ok(Label::Context(x)) if x.is_empty() => (),
+            Ok(label) => {
+                labels.push(label);
+                leading |= kept.is_empty();
+                continue;
+            }
+            _ => (),
+        }
+        if !std::mem::take(&mut leading) {
+            kept.push_str(gap);
+        }
+        kept.push_str(word.as_str());
+    }
+    if !leading {
+        kept.push_str(&description[last..]);
+    }
+    (kept, labels)
+}
+
 #[derive(Clone)]
 pub struct NegTag(String);
 
@@ -120,6 +266,8 @@ impl FromStr for NegTag {
 pub enum FilterTag {
     Add(AddTag),
     Neg(NegTag),
+    Context(Context),
+    NegContext(Context),
 }
 
 impl Deref for FilterTag {
@@ -128,36 +276,50 @@ impl Deref for FilterTag {
         match self {
             Self::Add(x) => x,
             Self::Neg(x) => x,
+            Self::Context(x) | Self::NegContext(x) => x,
         }
     }
 }
-impl From<FilterTag> for String {
-    fn from(value: FilterTag) -> Self {
-        match value {
-            FilterTag::Add(x) => String::from(x),
-            FilterTag::Neg(x) => String::from(x),
+impl From<Label> for FilterTag {
+    fn from(label: Label) -> Self {
+        match label {
+            Label::Tag(x) => Self::Add(x),
+            Label::Context(x) => Self::Context(x),
         }
     }
 }
+
 impl FromStr for FilterTag {
     type Err = &'static str;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(x) = s.strip_prefix("/@") {
+            return Ok(FilterTag::NegContext(Context(x.to_string())));
+        }
         AddTag::from_str(s)
             .map(FilterTag::Add)
             .or_else(|_| NegTag::from_str(s).map(FilterTag::Neg))
-            .map_err(|_| "filter tag must start with + or /")
+            .or_else(|_| Context::from_str(s).map(FilterTag::Context))
+            .map_err(|_| "filter tag must start with +, /, @, or /@")
     }
 }
 
 impl FilterTag {
-    pub fn is_neg(&self) -> bool {
-        matches!(self, Self::Neg(_))
+    pub fn filter<S: State>(&self, task: &Task<S>) -> bool {
+        match self {
+            Self::Add(f) => task.tags().any(|t| t.eq(f.deref())),
+            Self::Neg(f) => task.tags().all(|t| t.ne(f.deref())),
+            Self::Context(f) => task.contexts().any(|c| c.eq(f.deref())),
+            Self::NegContext(f) => task.contexts().all(|c| c.ne(f.deref())),
+        }
     }
 
-    pub fn filter<'a>(&self, mut tags: impl Iterator<Item = &'a str>) -> bool {
+    /// Add or remove the tag or context from the task.
+    pub fn apply<S>(self, task: &mut Task<S>) {
         match self {
-            Self::Add(f) => tags.any(|t| t.eq(f.deref())),
-            Self::Neg(f) => tags.all(|t| t.ne(f.deref())),
+            Self::Add(x) => task.add_tag(x),
+            Self::Neg(x) => task.remove_tag(&x),
+            Self::Context(x) => task.add_context(x.0),
+            Self::NegContext(x) => task.remove_context(&x),
         }
     }
 }
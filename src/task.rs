@@ -1,5 +1,7 @@
 use std::{
+    collections::HashSet,
     ops::{Deref, DerefMut},
+    sync::{Mutex, OnceLock},
     time::Duration,
 };
 
@@ -20,21 +22,235 @@ pub struct Task<S> {
     #[serde(default)]
     pub tags: Vec<String>,
 
+    /// Where the task can be done (`@home`), stored without the `@`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub contexts: Vec<String>,
+
+    /// Who the task has been delegated to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub assignee: Option<String>,
+
+    /// Estimated effort, in seconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub estimate: Option<u64>,
+
+    #[serde(skip_serializing_if = "Priority::is_normal")]
+    #[serde(default)]
+    pub priority: Priority,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub checklist: Vec<Item>,
+
+    /// How much energy the task takes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub energy: Option<Energy>,
+
+    /// Periods of time worked on the task.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub intervals: Vec<Interval>,
+
+    /// URLs or file paths related to the task.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub links: Vec<String>,
+
+    /// The project the task belongs to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub project: Option<String>,
+
+    /// How many days the task has been carried over, unfinished, in the top six.
+    #[serde(skip_serializing_if = "is_zero")]
+    #[serde(default)]
+    pub carried: u32,
+
     state: S,
 }
 
+fn is_zero(x: &u32) -> bool {
+    *x == 0
+}
+
+/// A period of work, in seconds since UNIX epoch. A missing end is still running.
+#[derive(serde::Deserialize, serde::Serialize, Clone)]
+pub struct Interval {
+    pub start: u64,
+    pub end: Option<u64>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, clap::ValueEnum, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Energy {
+    Low,
+    Medium,
+    High,
+}
+
+/// An energy to set on a task, or `none` to clear it.
+#[derive(clap::ValueEnum, Copy, Clone)]
+pub enum EnergyChange {
+    Low,
+    Medium,
+    High,
+    None,
+}
+
+impl From<EnergyChange> for Option<Energy> {
+    fn from(e: EnergyChange) -> Self {
+        match e {
+            EnergyChange::Low => Some(Energy::Low),
+            EnergyChange::Medium => Some(Energy::Medium),
+            EnergyChange::High => Some(Energy::High),
+            EnergyChange::None => None,
+        }
+    }
+}
+
+/// A checklist item of a task.
+#[derive(serde::Deserialize, serde::Serialize, Clone, PartialEq)]
+pub struct Item {
+    pub text: String,
+    #[serde(default)]
+    pub done: bool,
+}
+
+/// How important a task is, independent of its place in the list.
+#[derive(
+    serde::Deserialize, serde::Serialize, clap::ValueEnum, Copy, Clone, Default, PartialEq, Eq,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    High,
+    #[default]
+    Normal,
+    Low,
+}
+
+impl Priority {
+    pub fn is_normal(&self) -> bool {
+        *self == Priority::Normal
+    }
+
+    /// The next priority, wrapping from low back to high.
+    pub fn cycle(self) -> Self {
+        match self {
+            Priority::High => Priority::Normal,
+            Priority::Normal => Priority::Low,
+            Priority::Low => Priority::High,
+        }
+    }
+
+    /// A short marker for the priority, empty for normal.
+    pub fn marker(&self) -> &'static str {
+        match self {
+            Priority::High => "▲",
+            Priority::Normal => "",
+            Priority::Low => "▼",
+        }
+    }
+}
+
 pub type TodoTask = Task<Todo>;
 pub type DoneTask = Task<Done>;
+pub type TrashedTask = Task<Trashed>;
 
-#[derive(serde::Deserialize, serde::Serialize, Default, Clone, Copy)]
+#[derive(serde::Deserialize, serde::Serialize, Default, Clone)]
 pub struct Todo {
     marked: Option<Done>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    waiting: Option<Waiting>,
+
+    /// Seconds since UNIX epoch that the task is hidden until.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    snoozed_until: Option<u64>,
+
+    /// The task cannot be reordered out of the top six.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    #[serde(default)]
+    pinned: bool,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone)]
+pub struct Waiting {
+    /// What the task is waiting on.
+    pub on: String,
+    /// Seconds since UNIX epoch that the task resurfaces.
+    pub until: Option<u64>,
 }
 
-#[derive(serde::Deserialize, serde::Serialize, Clone, Copy)]
+#[derive(serde::Deserialize, serde::Serialize, Clone)]
 pub struct Done {
     /// Seconds since UNIX epoch.
     completed: u64,
+
+    /// Set if the task was cancelled rather than completed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    cancelled: Option<Cancelled>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone)]
+pub struct Cancelled {
+    #[serde(skip_serializing_if = "String::is_empty")]
+    #[serde(default)]
+    reason: String,
+}
+
+/// A removed task, kept in the trash until it is emptied.
+#[derive(serde::Deserialize, serde::Serialize, Clone)]
+pub struct Trashed {
+    /// Seconds since UNIX epoch.
+    deleted: u64,
+    /// The state the task was removed from.
+    was: Was,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone)]
+enum Was {
+    Todo(Todo),
+    Done(Done),
+}
+
+/// A task restored from the trash, back into the list it was removed from.
+pub enum Restored {
+    Todo(TodoTask),
+    Done(DoneTask),
+}
+
+/// A task's state.
+pub trait State {
+    /// Seconds since UNIX epoch that the task was finished, if it has been.
+    fn finished(&self) -> Option<u64>;
+}
+
+impl State for Todo {
+    fn finished(&self) -> Option<u64> {
+        self.marked.as_ref().map(|d| d.completed)
+    }
+}
+
+impl State for Done {
+    fn finished(&self) -> Option<u64> {
+        Some(self.completed)
+    }
+}
+
+impl State for Trashed {
+    fn finished(&self) -> Option<u64> {
+        match &self.was {
+            Was::Todo(x) => x.finished(),
+            Was::Done(x) => x.finished(),
+        }
+    }
 }
 
 impl Done {
@@ -44,14 +260,147 @@ impl Done {
     }
 }
 
+/// How new task IDs are generated. Existing IDs are kept whichever is used.
+#[derive(serde::Deserialize, serde::Serialize, Copy, Clone, PartialEq, Eq, Default, Debug)]
+pub enum IdScheme {
+    /// Four random characters (`qw8y`).
+    #[default]
+    Nanoid,
+    /// Two short words (`brisk-otter`), easier to type.
+    Words,
+}
+
+const ADJECTIVES: [&str; 64] = [
+    "amber", "bold", "brave", "brisk", "calm", "clever", "cosy", "crisp", "curly", "dandy",
+    "eager", "early", "fancy", "fast", "fierce", "fluffy", "fond", "fresh", "gentle", "giddy",
+    "glad", "grand", "happy", "hardy", "honest", "humble", "jolly", "keen", "kind", "lively",
+    "lucky", "merry", "mighty", "misty", "modest", "neat", "nimble", "noble", "odd", "plucky",
+    "polite", "proud", "quick", "quiet", "rapid", "rosy", "rustic", "shiny", "shy", "silly",
+    "sleek", "smart", "snug", "sunny", "swift", "tidy", "tiny", "witty", "vivid", "warm", "wild",
+    "wise", "young", "zesty",
+];
+
+const ANIMALS: [&str; 64] = [
+    "ant", "badger", "bat", "bear", "beaver", "bee", "bison", "boar", "camel", "cat", "cobra",
+    "crab", "crane", "crow", "deer", "dingo", "dodo", "dog", "duck", "eagle", "eel", "elk", "emu",
+    "ferret", "finch", "fox", "frog", "gecko", "goat", "goose", "hare", "hawk", "heron", "ibis",
+    "koala", "lark", "lemur", "lion", "llama", "lynx", "magpie", "mole", "moose", "moth", "mouse",
+    "newt", "otter", "owl", "panda", "puffin", "quail", "raven", "robin", "seal", "shrew", "sloth",
+    "snail", "swan", "tapir", "tiger", "toad", "trout", "wombat", "yak",
+];
+
+/// Generate a new task ID in the scheme.
+///
+/// If the `IVLY_ID_SEED` environment variable is set to a number, IDs are drawn from a
+/// deterministic sequence seeded by it, so generated files are reproducible.
+///
+/// Nanoids never start with `-`, which would read as a flag, or are all digits, which would
+/// read as a task number.
+pub fn new_id(scheme: IdScheme) -> String {
+    let random = |n| match id_seed() {
+        Some(_) => seeded_random(n),
+        None => nanoid::rngs::default(n),
+    };
+    match scheme {
+        IdScheme::Nanoid => loop {
+            let id = nanoid::format(random, &nanoid::alphabet::SAFE, 4);
+            if !id.starts_with('-') && id.parse::<usize>().is_err() {
+                break id;
+            }
+        },
+        IdScheme::Words => {
+            let x = random(2);
+            let pick = |words: &[&str], b: u8| words[usize::from(b) % words.len()].to_string();
+            pick(&ADJECTIVES, x[0]) + "-" + &pick(&ANIMALS, x[1])
+        }
+    }
+}
+
+/// The task IDs in use, so new tasks are given IDs no other task has.
+#[derive(Default)]
+pub struct Ids {
+    taken: HashSet<String>,
+    scheme: IdScheme,
+}
+
+impl Ids {
+    /// No IDs taken yet, generating new IDs in the scheme.
+    pub fn new(scheme: IdScheme) -> Self {
+        Ids {
+            taken: HashSet::new(),
+            scheme,
+        }
+    }
+
+    pub fn extend<S>(&mut self, tasks: &Tasks<S>) {
+        self.taken.extend(tasks.iter().map(|t| t.id().to_string()));
+    }
+
+    /// Hold the ID as taken, returning whether it was free.
+    pub fn insert(&mut self, id: &str) -> bool {
+        self.taken.insert(id.to_string())
+    }
+
+    /// Give a new task a fresh ID, holding it as taken.
+    pub fn assign<S>(&mut self, task: &mut Task<S>) {
+        task.unique_id(self.scheme, |id| self.taken.contains(id));
+        self.taken.insert(task.id().to_string());
+    }
+
+    /// Keep the task's ID if no other task has it, otherwise give it a fresh one.
+    pub fn keep<S>(&mut self, task: &mut Task<S>) {
+        if !self.insert(task.id()) {
+            self.assign(task);
+        }
+    }
+}
+
+fn id_seed() -> Option<&'static Mutex<u64>> {
+    static SEED: OnceLock<Option<Mutex<u64>>> = OnceLock::new();
+    SEED.get_or_init(|| {
+        std::env::var("IVLY_ID_SEED")
+            .ok()
+            .and_then(|x| x.parse().ok())
+            .map(Mutex::new)
+    })
+    .as_ref()
+}
+
+/// SplitMix64 over the seed; good enough for IDs, and deterministic.
+fn seeded_random(size: usize) -> Vec<u8> {
+    let mut state = id_seed()
+        .expect("only used when seeded")
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    (0..size)
+        .map(|_| {
+            *state = state.wrapping_add(0x9E3779B97F4A7C15);
+            let mut z = *state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+            (z ^ (z >> 31)) as u8
+        })
+        .collect()
+}
+
 impl Default for Task<Todo> {
     fn default() -> Self {
         Self {
-            id: nanoid::nanoid!(4),
+            id: new_id(IdScheme::default()),
             description: String::new(),
             note: String::new(),
             created: crate::now(),
             tags: Vec::new(),
+            contexts: Vec::new(),
+            assignee: None,
+            estimate: None,
+            priority: Priority::Normal,
+            checklist: Vec::new(),
+            energy: None,
+            intervals: Vec::new(),
+            links: Vec::new(),
+            project: None,
+            carried: 0,
             state: Todo::default(),
         }
     }
@@ -62,6 +411,25 @@ impl<S> Task<S> {
         &self.id
     }
 
+    /// Give the task a freshly generated ID in the scheme, until `taken` is false for it.
+    ///
+    /// After many clashes a number is appended, so a full ID space still terminates.
+    pub fn unique_id(&mut self, scheme: IdScheme, taken: impl Fn(&str) -> bool) {
+        self.id = new_id(scheme);
+        for _ in 0..100 {
+            if !taken(&self.id) {
+                return;
+            }
+            self.id = new_id(scheme);
+        }
+        let base = self.id.clone();
+        let mut n = 2;
+        while taken(&self.id) {
+            self.id = format!("{base}-{n}");
+            n += 1;
+        }
+    }
+
     pub fn tags(&self) -> impl ExactSizeIterator<Item = &str> {
         self.tags.iter().map(String::as_str)
     }
@@ -77,10 +445,120 @@ impl<S> Task<S> {
         self.tags.retain(|t| t != tag);
     }
 
+    pub fn contexts(&self) -> impl ExactSizeIterator<Item = &str> {
+        self.contexts.iter().map(String::as_str)
+    }
+
+    pub fn add_context(&mut self, context: impl Into<String>) {
+        let context = context.into();
+        if !self.contexts.contains(&context) {
+            self.contexts.push(context);
+        }
+    }
+
+    pub fn remove_context(&mut self, context: &str) {
+        self.contexts.retain(|c| c != context);
+    }
+
+    /// The number of checklist items done, and the total, if there is a checklist.
+    pub fn progress(&self) -> Option<(usize, usize)> {
+        let done = self.checklist.iter().filter(|x| x.done).count();
+        (!self.checklist.is_empty()).then_some((done, self.checklist.len()))
+    }
+
+    /// Total time worked, including a running interval.
+    pub fn time_tracked(&self) -> Duration {
+        let now = crate::now();
+        let secs = self
+            .intervals
+            .iter()
+            .map(|x| x.end.unwrap_or(now).saturating_sub(x.start))
+            .sum();
+        Duration::from_secs(secs)
+    }
+
+    pub fn is_tracking(&self) -> bool {
+        self.intervals.iter().any(|x| x.end.is_none())
+    }
+
+    pub fn start_tracking(&mut self) {
+        if !self.is_tracking() {
+            self.intervals.push(Interval {
+                start: crate::now(),
+                end: None,
+            });
+        }
+    }
+
+    pub fn stop_tracking(&mut self) {
+        let now = crate::now();
+        for x in self.intervals.iter_mut().filter(|x| x.end.is_none()) {
+            x.end = Some(now);
+        }
+    }
+
+    pub fn estimate(&self) -> Option<Duration> {
+        self.estimate.map(Duration::from_secs)
+    }
+
     pub fn duration_since_creation(&self) -> Duration {
         let secs = (crate::now() - self.created).max(0);
         Duration::from_secs(secs)
     }
+
+    /// Seconds since UNIX epoch.
+    pub fn created(&self) -> u64 {
+        self.created
+    }
+}
+
+impl<S> Task<S> {
+    /// The task with its state replaced.
+    fn with_state<T>(self, state: T) -> Task<T> {
+        let Self {
+            id,
+            description,
+            note,
+            created,
+            tags,
+            contexts,
+            assignee,
+            estimate,
+            priority,
+            checklist,
+            energy,
+            intervals,
+            links,
+            project,
+            carried,
+            state: _,
+        } = self;
+        Task {
+            id,
+            description,
+            note,
+            created,
+            tags,
+            contexts,
+            assignee,
+            estimate,
+            priority,
+            checklist,
+            energy,
+            intervals,
+            links,
+            project,
+            carried,
+            state,
+        }
+    }
+}
+
+impl<S: State> Task<S> {
+    /// Seconds since UNIX epoch that the task was finished, if it has been.
+    pub fn finished_at(&self) -> Option<u64> {
+        self.state.finished()
+    }
 }
 
 impl TodoTask {
@@ -91,12 +569,25 @@ impl TodoTask {
         }
     }
 
+    /// Use the given ID rather than a generated one, such as an ID from another tool.
+    ///
+    /// The ID is not checked for clashes; see [`Ids::keep`].
+    pub fn with_id(mut self, id: impl Into<String>) -> Self {
+        self.id = id.into();
+        self
+    }
+
     pub fn finish(&mut self) {
+        self.stop_tracking();
         if self.state.marked.is_none() {
             self.state = Todo {
                 marked: Some(Done {
                     completed: crate::now(),
+                    cancelled: None,
                 }),
+                waiting: None,
+                snoozed_until: None,
+                pinned: false,
             };
         }
     }
@@ -105,6 +596,65 @@ impl TodoTask {
         self.state.marked.is_some()
     }
 
+    /// Clear the finished mark, before the task is swept.
+    pub fn unfinish(&mut self) {
+        self.state.marked = None;
+    }
+
+    /// Put the task into a waiting state, optionally resurfacing after `until` elapses.
+    pub fn wait(&mut self, on: impl Into<String>, until: Option<Duration>) {
+        self.state.waiting = Some(Waiting {
+            on: on.into(),
+            until: until.map(|d| crate::now() + d.as_secs()),
+        });
+    }
+
+    pub fn stop_waiting(&mut self) {
+        self.state.waiting = None;
+    }
+
+    pub fn waiting(&self) -> Option<&Waiting> {
+        self.state.waiting.as_ref()
+    }
+
+    /// The task is waiting and its `until` time has not passed.
+    pub fn is_waiting(&self) -> bool {
+        self.waiting()
+            .is_some_and(|w| w.until.is_none_or(|u| u > crate::now()))
+    }
+
+    /// Hide the task from the priority list until `duration` elapses.
+    pub fn snooze(&mut self, duration: Duration) {
+        self.state.snoozed_until = Some(crate::now() + duration.as_secs());
+    }
+
+    /// The time the task wakes, if it is snoozed.
+    pub fn snoozed_until(&self) -> Option<u64> {
+        self.state.snoozed_until.filter(|&u| u > crate::now())
+    }
+
+    pub fn pin(&mut self) {
+        self.state.pinned = true;
+    }
+
+    pub fn unpin(&mut self) {
+        self.state.pinned = false;
+    }
+
+    pub fn is_pinned(&self) -> bool {
+        self.state.pinned
+    }
+
+    /// The task is unfinished and was created more than `after` ago.
+    pub fn is_stale(&self, after: Duration) -> bool {
+        !self.is_finished() && self.duration_since_creation() > after
+    }
+
+    /// The task is waiting or snoozed, and so hidden from the priority list.
+    pub fn is_hidden(&self) -> bool {
+        self.is_waiting() || self.snoozed_until().is_some()
+    }
+
     pub fn duration_since_finished(&self) -> Option<Duration> {
         self.state
             .marked
@@ -119,10 +669,21 @@ impl TodoTask {
             note,
             created,
             tags,
+            contexts,
+            assignee,
+            estimate,
+            priority,
+            checklist,
+            energy,
+            intervals,
+            links,
+            project,
+            carried,
             state,
         } = self;
         let state = state.marked.unwrap_or_else(|| Done {
             completed: crate::now(),
+            cancelled: None,
         });
         DoneTask {
             id,
@@ -130,15 +691,141 @@ impl TodoTask {
             note,
             created,
             tags,
+            contexts,
+            assignee,
+            estimate,
+            priority,
+            checklist,
+            energy,
+            intervals,
+            links,
+            project,
+            carried,
             state,
         }
     }
+
+    /// Merge another task into this one.
+    ///
+    /// The description is kept, notes are concatenated, tags are unioned, and the earlier
+    /// creation time is kept.
+    pub fn merge(&mut self, other: TodoTask) {
+        if !other.note.is_empty() && other.note != self.note {
+            if !self.note.is_empty() {
+                self.note.push('\n');
+            }
+            self.note += &other.note;
+        }
+        for tag in other.tags {
+            self.add_tag(tag);
+        }
+        for context in other.contexts {
+            self.add_context(context);
+        }
+        if self.assignee.is_none() {
+            self.assignee = other.assignee;
+        }
+        self.checklist.extend(other.checklist);
+        self.energy = self.energy.or(other.energy);
+        self.project = self.project.take().or(other.project);
+        self.carried = self.carried.max(other.carried);
+        self.intervals.extend(other.intervals);
+        for link in other.links {
+            if !self.links.contains(&link) {
+                self.links.push(link);
+            }
+        }
+        self.created = self.created.min(other.created);
+    }
+
+    /// Move the task into the trash.
+    pub fn trash(self) -> TrashedTask {
+        let was = Was::Todo(self.state.clone());
+        self.with_state(Trashed {
+            deleted: crate::now(),
+            was,
+        })
+    }
+
+    /// Move the task into the done state, flagged as cancelled rather than completed.
+    pub fn cancel(self, reason: Option<String>) -> DoneTask {
+        let mut task = self.complete();
+        task.state = Done {
+            completed: crate::now(),
+            cancelled: Some(Cancelled {
+                reason: reason.unwrap_or_default(),
+            }),
+        };
+        task
+    }
 }
 
 impl DoneTask {
     pub fn duration_since_completed(&self) -> Duration {
         self.state.duration_since_completed()
     }
+
+    /// Move the task back into the todo state.
+    pub fn reopen(self) -> TodoTask {
+        let Self {
+            id,
+            description,
+            note,
+            created,
+            tags,
+            contexts,
+            assignee,
+            estimate,
+            priority,
+            checklist,
+            energy,
+            intervals,
+            links,
+            project,
+            carried,
+            state: _,
+        } = self;
+        TodoTask {
+            id,
+            description,
+            note,
+            created,
+            tags,
+            contexts,
+            assignee,
+            estimate,
+            priority,
+            checklist,
+            energy,
+            intervals,
+            links,
+            project,
+            carried,
+            state: Todo::default(),
+        }
+    }
+
+    /// Move the task into the trash.
+    pub fn trash(self) -> TrashedTask {
+        let was = Was::Done(self.state.clone());
+        self.with_state(Trashed {
+            deleted: crate::now(),
+            was,
+        })
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.is_some()
+    }
+
+    /// The reason given for cancelling the task, if any.
+    pub fn cancel_reason(&self) -> Option<&str> {
+        self.state
+            .cancelled
+            .as_ref()
+            .map(|c| c.reason.as_str())
+            .filter(|r| !r.is_empty())
+    }
 }
 
 #[derive(serde::Deserialize, serde::Serialize)]
@@ -147,6 +834,7 @@ pub struct Tasks<T>(pub Vec<Task<T>>);
 
 pub type TodoTasks = Tasks<Todo>;
 pub type DoneTasks = Tasks<Done>;
+pub type TrashedTasks = Tasks<Trashed>;
 
 impl<T> Deref for Tasks<T> {
     type Target = Vec<Task<T>>;
@@ -171,6 +859,122 @@ impl<T> Tasks<T> {
     }
 }
 
+/// An open task given on the command line: its task number, its ID, or a unique prefix of its ID.
+#[derive(Clone, Debug)]
+pub struct TaskRef(String);
+
+impl std::str::FromStr for TaskRef {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            Err("expected a task number or ID")
+        } else {
+            Ok(TaskRef(s.to_string()))
+        }
+    }
+}
+
+impl std::fmt::Display for TaskRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl TaskRef {
+    /// The task number, if the reference is one.
+    pub fn num(&self) -> Option<usize> {
+        self.0.parse().ok()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TrashedTask {
+    /// Seconds since UNIX epoch that the task was removed.
+    pub fn deleted(&self) -> u64 {
+        self.state.deleted
+    }
+
+    /// The task was removed from the open list, rather than the done list.
+    pub fn was_open(&self) -> bool {
+        matches!(self.state.was, Was::Todo(_))
+    }
+
+    /// Take the task out of the trash, in the state it was removed in.
+    pub fn restore(self) -> Restored {
+        match self.state.was.clone() {
+            Was::Todo(state) => Restored::Todo(self.with_state(state)),
+            Was::Done(state) => Restored::Done(self.with_state(state)),
+        }
+    }
+}
+
+impl TodoTasks {
+    /// Move the task at `from` to be at `to`.
+    ///
+    /// The move is undone, returning `false`, if it pushes a pinned task out of the `top` shown,
+    /// or changes which of the `top` are in the `locked` plan (if there is one).
+    pub fn reorder(&mut self, from: usize, to: usize, top: usize, locked: &[String]) -> bool {
+        let before = self.top_shown(top);
+        let task = self.remove(from);
+        self.insert(to, task);
+        if self.keeps_top(top, &before, locked) {
+            true
+        } else {
+            let task = self.remove(to);
+            self.insert(from, task);
+            false
+        }
+    }
+
+    /// Exchange the positions of the tasks at `a` and `b`.
+    ///
+    /// Like [`reorder`](Self::reorder), the swap is undone, returning `false`, if it pushes
+    /// a pinned task out of the `top` shown or changes the `locked` plan.
+    pub fn exchange(&mut self, a: usize, b: usize, top: usize, locked: &[String]) -> bool {
+        let before = self.top_shown(top);
+        self.swap(a, b);
+        if self.keeps_top(top, &before, locked) {
+            true
+        } else {
+            self.swap(a, b);
+            false
+        }
+    }
+
+    /// The pinned tasks of the `before` top are still in it,
+    /// and the `locked` plan (if there is one) is unchanged.
+    fn keeps_top(&self, top: usize, before: &[(String, bool)], locked: &[String]) -> bool {
+        let after = self.top_shown(top);
+        let stays = |(id, _): &&(String, bool)| after.iter().any(|(x, _)| x == id);
+        let pins_kept = before
+            .iter()
+            .filter(|(_, pinned)| *pinned)
+            .all(|x| stays(&x));
+        let plan_kept = locked.is_empty()
+            || before
+                .iter()
+                .filter(|(id, _)| locked.contains(id))
+                .all(|x| stays(&x))
+                && after
+                    .iter()
+                    .all(|(id, _)| locked.contains(id) || before.iter().any(|(x, _)| x == id));
+        pins_kept && plan_kept
+    }
+
+    /// IDs of the `top` tasks shown, and whether each is pinned.
+    fn top_shown(&self, top: usize) -> Vec<(String, bool)> {
+        self.iter()
+            .filter(|t| !t.is_hidden())
+            .take(top)
+            .map(|t| (t.id().to_string(), t.is_pinned()))
+            .collect()
+    }
+}
+
 impl DoneTasks {
     /// Sorts the tasks as most recently closed to oldest closed.
     pub fn sort(&mut self) {
@@ -1,10 +1,23 @@
-use crate::{io, op, print, tags::Tags, AddTag};
+use crate::{
+    agenda, changelog, diff, filter, io, journal, markdown, op, print, report, similar, stats,
+    tags::{self, FilterTag, Tags},
+    task::{self, IdScheme, Tasks, TodoTask},
+    tui,
+};
+use crossterm::event::KeyCode;
 use std::path::Path;
 
+fn task(description: &str, note: &str, tags: &[&str]) -> TodoTask {
+    let mut task = TodoTask::new(description);
+    task.note = note.to_string();
+    for tag in tags {
+        task.add_tag(*tag);
+    }
+    task
+}
+
 #[test]
 fn main_integration_test() {
-    colored::control::set_override(true); // always colour for testing
-
     let dir = Path::new("./target/integration-test");
     std::fs::remove_dir_all(dir).ok();
     std::fs::create_dir_all(dir).unwrap();
@@ -16,33 +29,37 @@ fn main_integration_test() {
     settings.add_redaction("[].state.marked.completed", "[completed]");
     let _settings = settings.bind_to_scope();
 
-    op::add(dir, "This is a new task".into(), None, Vec::new()).unwrap();
+    op::add(dir, TodoTask::new("This is a new task"), Default::default()).unwrap();
     let tasks = io::read_open_tasks(dir);
     insta::assert_ron_snapshot!(tasks);
 
     op::add(
         dir,
-        "This is a new task 2".into(),
-        Some("with a note".to_string()),
-        vec![AddTag("tag1".into()), AddTag("tag-2".into())],
+        task("This is a new task 2", "with a note", &["tag1", "tag-2"]),
+        Default::default(),
     )
     .unwrap();
     let tasks = io::read_open_tasks(dir);
     insta::assert_ron_snapshot!(tasks);
 
-    op::finish(dir, None).unwrap();
+    op::finish(dir, None, false, false).unwrap();
     let open = io::read_open_tasks(dir);
     let done = io::read_done_tasks(dir);
     insta::assert_ron_snapshot!(open);
     insta::assert_ron_snapshot!(done);
 
-    op::sweep(dir).unwrap();
+    op::sweep(dir, false, false).unwrap();
     let open = io::read_open_tasks(dir);
     let done = io::read_done_tasks(dir);
     insta::assert_ron_snapshot!(open);
     insta::assert_ron_snapshot!(done);
 
-    op::add(dir, "This is a new task 3".into(), None, Vec::new()).unwrap();
+    op::add(
+        dir,
+        TodoTask::new("This is a new task 3"),
+        Default::default(),
+    )
+    .unwrap();
     op::bump(dir, 1).unwrap();
     let open = io::read_open_tasks(dir);
     insta::assert_ron_snapshot!(open);
@@ -51,7 +68,7 @@ fn main_integration_test() {
     let open = io::read_open_tasks(dir);
     insta::assert_ron_snapshot!(open);
 
-    op::finish(dir, Some(2)).unwrap();
+    op::finish(dir, Some(2), false, false).unwrap();
     let open = io::read_open_tasks(dir);
     insta::assert_ron_snapshot!(open);
 
@@ -66,9 +83,29 @@ fn main_integration_test() {
     insta::assert_ron_snapshot!(tags);
 }
 
+/// The binary, against a test directory.
+fn ivly(dir: &str) -> assert_cmd::Command {
+    let mut cmd = assert_cmd::Command::cargo_bin("ivly").unwrap();
+    cmd.args(["--dir", dir]);
+    cmd
+}
+
+#[test]
+fn debug_builds_ignore_ivly_dir() {
+    let dir = "./target/ivly-dir-test";
+    std::fs::remove_dir_all(dir).ok();
+    assert_cmd::Command::cargo_bin("ivly")
+        .unwrap()
+        .env("IVLY_DIR", dir)
+        .arg("list")
+        .assert()
+        .success();
+    assert!(!Path::new(dir).exists());
+}
+
 #[test]
 fn cli_tests() {
-    let cmd = || assert_cmd::Command::cargo_bin("ivly").unwrap();
+    let cmd = || ivly("./target/cli-test");
     cmd().arg("add").arg("Hello, world!").assert().success();
     cmd().arg("a").arg("Hello, world!").assert().success();
     cmd().arg("finish").assert().success();
@@ -83,15 +120,2446 @@ fn cli_tests() {
 
 #[test]
 fn print_tags() {
+    let dir = "./target/print-tags-test";
+    std::fs::remove_dir_all(dir).ok();
+    ivly(dir)
+        .args(["tag", "tag1", "--fg", "blue"])
+        .assert()
+        .success();
+    ivly(dir)
+        .args(["tag", "tag2", "--fg", "red", "--bg", "blue"])
+        .assert()
+        .success();
+    ivly(dir)
+        .args(["tag", "tag3", "--bg", "green"])
+        .assert()
+        .success();
+
+    // piped output is plain, unless colour is forced
+    let tags = |force: bool| {
+        let mut cmd = ivly(dir);
+        cmd.arg("tag").env_remove("NO_COLOR");
+        if force {
+            cmd.env("CLICOLOR_FORCE", "1");
+        }
+        let out = cmd.assert().success().get_output().stdout.clone();
+        String::from_utf8(out).unwrap()
+    };
+    assert!(!tags(false).contains('\x1b'));
+    let o = tags(true);
+    insta::assert_snapshot!(o);
+}
+
+#[test]
+fn print_task_plain() {
+    let mut t = task("Write docs", "with **bold** notes\n- a bullet", &["tag1"]);
+    t.add_context("home");
+    t.priority = crate::task::Priority::High;
     let mut tags = Tags::default();
     tags.set_fg("tag1", colored::Color::Blue);
-    tags.set_fg("tag2", colored::Color::Red);
-    tags.set_bg("tag2", colored::Color::Blue);
-    tags.set_bg("tag3", colored::Color::Green);
+
+    let o = print::render_todo_task(0, &t, &tags, &Default::default(), print::Render::plain());
+    let o = regex::Regex::new(r"\S+ ago").unwrap().replace(&o, "[age]");
+    insta::assert_snapshot!(o);
+}
+
+#[test]
+fn output_is_captured() {
+    let dir = Path::new("./target/capture-test");
+    std::fs::remove_dir_all(dir).ok();
+    std::fs::create_dir_all(dir).unwrap();
+    op::add(dir, TodoTask::new("Captured"), Default::default()).unwrap();
 
     let mut o = Vec::new();
-    print::tags(&tags, &mut o);
-    let o = String::from_utf8(o).unwrap();
+    let opts = op::ListOptions {
+        only_open: true,
+        ..Default::default()
+    };
+    op::list(
+        dir,
+        opts,
+        Default::default(),
+        print::Render::plain(),
+        &mut o,
+    );
+    assert!(String::from_utf8(o).unwrap().contains("Captured"));
 
-    insta::assert_snapshot!(o);
+    let mut o = Vec::new();
+    let tasks = io::read_open_tasks(dir);
+    print::todo_task(
+        0,
+        &tasks[0],
+        &Tags::default(),
+        &Default::default(),
+        print::Render::plain(),
+        &mut o,
+    );
+    assert!(String::from_utf8(o).unwrap().contains("Captured"));
+}
+
+#[test]
+fn seeded_ids_are_deterministic() {
+    // a fresh directory each time, as an ID already taken is regenerated
+    let add = || {
+        std::fs::remove_dir_all("./target/seed-test").ok();
+        let out = ivly("./target/seed-test")
+            .env("IVLY_ID_SEED", "42")
+            .args(["add", "Seeded"])
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+        let out = String::from_utf8(out).unwrap();
+        out.lines().next().unwrap().to_string()
+    };
+    assert_eq!(add(), add());
+}
+
+#[test]
+fn new_ids_are_unique_across_files() {
+    let dir = "./target/unique-ids-test";
+    std::fs::remove_dir_all(dir).ok();
+    std::fs::create_dir_all(dir).unwrap();
+    std::fs::write(Path::new(dir).join("config.ron"), "(ids: Words)").unwrap();
+    let md = Path::new(dir).join("import.md");
+    std::fs::write(&md, "- [ ] Four\n- [x] Five").unwrap();
+    // the same seed draws the same IDs each run, so each must dodge those already taken
+    let run = |args: &[&str]| {
+        ivly(dir)
+            .env("IVLY_ID_SEED", "7")
+            .args(args)
+            .assert()
+            .success();
+    };
+    run(&["add", "One"]);
+    run(&["tomorrow", "add", "Two"]);
+    run(&["in", "Three"]);
+    run(&["import", "--format", "md", md.to_str().unwrap()]);
+
+    let dir = Path::new(dir);
+    let ids = io::read_open_tasks(dir)
+        .iter()
+        .map(|t| t.id().to_string())
+        .chain(io::read_done_tasks(dir).iter().map(|t| t.id().to_string()))
+        .chain(io::read_tomorrow(dir).iter().map(|t| t.id().to_string()))
+        .chain(io::read_inbox(dir).iter().map(|t| t.id().to_string()))
+        .collect::<Vec<_>>();
+    assert_eq!(ids.len(), 5);
+    let unique = ids.iter().collect::<std::collections::HashSet<_>>();
+    assert_eq!(unique.len(), 5, "{ids:?}");
+    assert!(ids.iter().all(|id| id.contains('-')), "{ids:?}");
+}
+
+#[test]
+fn sweep_only_writes_when_tasks_swept() {
+    let dir = Path::new("./target/sweep-test");
+    std::fs::remove_dir_all(dir).ok();
+    std::fs::create_dir_all(dir).unwrap();
+
+    op::add(dir, TodoTask::new("Open task"), Default::default()).unwrap();
+    op::sweep(dir, false, false).unwrap();
+    assert!(!dir.join("done.ron").exists());
+    assert!(!dir.join("open.bak.ron").exists());
+
+    op::finish(dir, None, false, false).unwrap();
+    op::sweep(dir, false, false).unwrap();
+    assert_eq!(io::read_done_tasks(dir).len(), 1);
+    assert!(io::read_open_tasks(dir).is_empty());
+}
+
+#[test]
+fn unchanged_files_are_not_written() {
+    let dir = Path::new("./target/unchanged-write-test");
+    std::fs::remove_dir_all(dir).ok();
+    std::fs::create_dir_all(dir).unwrap();
+
+    op::add(dir, TodoTask::new("First"), Default::default()).unwrap();
+    op::add(dir, TodoTask::new("Second"), Default::default()).unwrap();
+    let bak = std::fs::read_to_string(dir.join("open.bak.ron")).unwrap();
+    let modified = || {
+        std::fs::metadata(dir.join("open.ron"))
+            .unwrap()
+            .modified()
+            .unwrap()
+    };
+    let before = modified();
+
+    // writing the same tasks leaves the file, and its backup of the previous version, alone
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    io::write_open_tasks(dir, &io::read_open_tasks(dir)).unwrap();
+    assert_eq!(modified(), before);
+    assert_eq!(
+        std::fs::read_to_string(dir.join("open.bak.ron")).unwrap(),
+        bak
+    );
+}
+
+#[test]
+fn add_applies_config_rules() {
+    let dir = Path::new("./target/rules-test");
+    std::fs::remove_dir_all(dir).ok();
+    std::fs::create_dir_all(dir).unwrap();
+    std::fs::write(
+        dir.join("config.ron"),
+        r#"(rules: [(match: "(?i)review pr", tags: ["code", "review"])])"#,
+    )
+    .unwrap();
+
+    op::add(dir, TodoTask::new("Review PR #12"), Default::default()).unwrap();
+    op::add(dir, TodoTask::new("Water plants"), Default::default()).unwrap();
+    let tasks = io::read_open_tasks(dir);
+    assert_eq!(tasks[0].tags, ["code", "review"]);
+    assert!(tasks[1].tags.is_empty());
+
+    // a rule with a workspace routes matching tasks there, tagged by the rules here too
+    std::fs::write(
+        dir.join("config.ron"),
+        r#"(rules: [(match: "(?i)invoice", tags: ["billing"], workspace: Some("work"))])"#,
+    )
+    .unwrap();
+    op::new_workspace(dir, "work").unwrap();
+    let work = dir.join("workspaces/work");
+    op::add_routed(dir, dir, TodoTask::new("Send invoice"), Default::default()).unwrap();
+    op::add_routed(dir, dir, TodoTask::new("Buy milk"), Default::default()).unwrap();
+    assert_eq!(io::read_open_tasks(dir).len(), 3);
+    let routed = io::read_open_tasks(&work);
+    assert_eq!(routed.len(), 1);
+    assert_eq!(routed[0].tags, ["billing"]);
+    // journaled in its workspace, so it can be undone there
+    journal::undo(&work, false).unwrap();
+    assert!(io::read_open_tasks(&work).is_empty());
+}
+
+#[test]
+fn description_similarity() {
+    assert_eq!(similar::similarity("Write report", "write REPORT"), 1.0);
+    assert!(similar::similarity("Write the report", "Write report") > 0.75);
+    assert!(similar::similarity("Write report", "Water plants") < 0.5);
+}
+
+#[test]
+fn description_hints() {
+    let hints = crate::config::Hints::default();
+    assert!(hints.check("Write the quarterly report").is_empty());
+    assert_eq!(hints.check("Stuff").len(), 1);
+    assert_eq!(hints.check(&"a".repeat(81)).len(), 1);
+
+    let off = crate::config::Hints {
+        max_length: None,
+        vague_words: Vec::new(),
+    };
+    assert!(off.check("stuff").is_empty());
+}
+
+#[test]
+fn unique_task_ids() {
+    let mut task = TodoTask::new("a");
+    let first = task.id().to_string();
+    task.unique_id(IdScheme::Nanoid, |id| id == first);
+    assert_ne!(task.id(), first);
+
+    // a full ID space still gives a unique ID
+    task.unique_id(IdScheme::Nanoid, |id| !id.ends_with("-3"));
+    assert!(task.id().ends_with("-3"));
+
+    // IDs are usable as arguments, never read as a flag or task number
+    for _ in 0..2000 {
+        let id = crate::task::new_id(IdScheme::Nanoid);
+        assert!(!id.starts_with('-') && id.parse::<usize>().is_err(), "{id}");
+    }
+
+    // two short words, easier to type
+    task.unique_id(IdScheme::Words, |_| false);
+    let words = task.id().split('-').collect::<Vec<_>>();
+    assert_eq!(words.len(), 2, "{}", task.id());
+    assert!(words
+        .iter()
+        .all(|w| w.chars().all(|c| c.is_ascii_lowercase())));
+}
+
+#[test]
+fn fuzzy_matching() {
+    assert!(similar::fuzzy_score("wtp", "Water the plants").is_some());
+    assert!(similar::fuzzy_score("ptw", "Water the plants").is_none());
+    let a = similar::fuzzy_score("plan", "Water the plants").unwrap();
+    let b = similar::fuzzy_score("plan", "Pay the loan").unwrap();
+    assert!(a > b);
+}
+
+#[test]
+fn tag_suggestions() {
+    let finance = vec!["finance".to_string()];
+    let home = vec!["home".to_string(), "finance".to_string()];
+    let history = [
+        ("Pay the electricity bill", finance.as_slice()),
+        ("Pay the water bill", home.as_slice()),
+        ("Mow the lawn", &["garden".to_string()][..]),
+    ];
+    let tags = similar::suggest_tags("pay gas bill", history.iter().copied());
+    assert_eq!(tags, ["finance", "home"]);
+    assert!(similar::suggest_tags("go", history.iter().copied()).is_empty());
+}
+
+#[test]
+fn merge_tasks_combines_notes_and_tags() {
+    let dir = Path::new("./target/merge-test");
+    std::fs::remove_dir_all(dir).ok();
+    std::fs::create_dir_all(dir).unwrap();
+
+    op::add(dir, task("Task A", "note a", &["x"]), Default::default()).unwrap();
+    op::add(dir, task("Task B", "note b", &["y"]), Default::default()).unwrap();
+    let tasks = io::read_open_tasks(dir);
+    let (a, b) = (tasks[0].id().to_string(), tasks[1].id().to_string());
+
+    op::merge_tasks(dir, &a, &b).unwrap();
+    let tasks = io::read_open_tasks(dir);
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].description, "Task A");
+    assert_eq!(tasks[0].note, "note a\nnote b");
+    assert_eq!(tasks[0].tags, ["x", "y"]);
+    assert!(op::merge_tasks(dir, &a, &a).is_err());
+}
+
+#[test]
+fn busiest_weekday() {
+    // 1970-01-01 was a Thursday
+    let thu = 0;
+    let fri = stats::DAY;
+    let times = [thu, fri, fri + 60, fri + 7 * stats::DAY];
+    assert_eq!(stats::busiest_weekday(&times), Some(("Friday", 3)));
+    assert_eq!(stats::busiest_weekday(&[]), None);
+
+    let now = crate::now();
+    assert_eq!(stats::this_week_and_month(&[now, now, 0]), (2, 2));
+}
+
+#[test]
+fn daily_chart_counts() {
+    let mut open = Tasks(vec![TodoTask::new("a"), TodoTask::new("b")]);
+    open[0].finish();
+    let days = stats::daily(&open, &Tasks::new(), 3);
+    let today = crate::now() / stats::DAY;
+    assert_eq!(days.len(), 3);
+    assert_eq!(
+        days[2],
+        stats::Day {
+            day: today,
+            completed: 1,
+            open: 1,
+        }
+    );
+    assert_eq!(days[0].open, 0);
+}
+
+#[test]
+fn svg_summary_hides_titles() {
+    let titles = ["Fix <b>".to_string()];
+    let mut summary = crate::export::Summary {
+        week_of: "2024-01-01".into(),
+        completed: 1,
+        streak: 4,
+        titles: None,
+    };
+    let svg = crate::export::svg_summary(&summary);
+    assert!(svg.starts_with("<svg") && svg.contains("1 done") && svg.contains("4 day streak"));
+    assert!(!svg.contains("Fix"));
+
+    summary.titles = Some(&titles);
+    assert!(crate::export::svg_summary(&summary).contains("Fix &lt;b&gt;"));
+}
+
+#[test]
+fn completion_streaks() {
+    let today = crate::now() / stats::DAY;
+
+    let s = stats::streaks(&[today - 3, today - 2, today - 1].into());
+    assert_eq!((s.current, s.longest), (3, 3));
+
+    let s = stats::streaks(&[today - 9, today - 8, today - 7, today].into());
+    assert_eq!((s.current, s.longest), (1, 3));
+
+    let s = stats::streaks(&[today - 5, today - 4].into());
+    assert_eq!((s.current, s.longest), (0, 2));
+}
+
+#[test]
+fn agenda_free_time() {
+    let ics = "BEGIN:VCALENDAR\r
+BEGIN:VEVENT\r
+DTSTART:20240101T100000Z\r
+DTEND:20240101T110000Z\r
+SUMMARY:Stand\r
+ up\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART:20240101T103000Z\r
+DTEND:20240101T113000Z\r
+SUMMARY:Review\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART;VALUE=DATE:20240101\r
+SUMMARY:Holiday\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART:20240101T140000Z\r
+DTEND:20240101T150000Z\r
+TRANSP:TRANSPARENT\r
+SUMMARY:Optional\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+    let utc = jiff::tz::TimeZone::UTC;
+    let (busy, warnings) = agenda::parse_ics(ics, &utc);
+    assert_eq!(busy.len(), 2);
+    assert_eq!(busy[0].summary, "Standup");
+    assert!(warnings.is_empty());
+
+    let nine = busy[0].start - 3600;
+    let five = nine + 8 * 3600;
+    assert_eq!(agenda::free_time(&busy, nine, five), (8 * 60 - 90) * 60);
+
+    // working hours are on the local clock
+    let (midnight, next) = agenda::day(nine, &utc).unwrap();
+    assert_eq!(
+        (agenda::at(midnight, 9 * 3600, &utc), next - midnight),
+        (Some(nine), 86400)
+    );
+    assert_eq!(agenda::clock(nine, &utc), "09:00");
+}
+
+#[test]
+fn agenda_time_zones() {
+    let ics = "BEGIN:VEVENT
+DTSTART;TZID=Australia/Perth:20240101T090000
+DTEND;TZID=\"Australia/Perth\":20240101T100000
+SUMMARY:Zoned
+END:VEVENT
+BEGIN:VEVENT
+DTSTART:20240101T090000
+DTEND:20240101T100000
+SUMMARY:Floating
+END:VEVENT
+BEGIN:VEVENT
+DTSTART;TZID=Nowhere Standard Time:20240101T090000
+DTEND;TZID=Nowhere Standard Time:20240101T100000
+SUMMARY:Unknown
+END:VEVENT
+";
+    let utc_nine = 1704099600; // 2024-01-01T09:00:00Z
+    let local = jiff::tz::TimeZone::fixed(jiff::tz::offset(10));
+    let (busy, warnings) = agenda::parse_ics(ics, &local);
+    let starts = busy
+        .iter()
+        .map(|b| (b.summary.as_str(), b.start))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        starts,
+        [
+            ("Floating", utc_nine - 10 * 3600),
+            ("Unknown", utc_nine - 10 * 3600),
+            ("Zoned", utc_nine - 8 * 3600),
+        ]
+    );
+    assert_eq!(busy[2].end - busy[2].start, 3600);
+    assert_eq!(
+        warnings,
+        ["unknown time zone 'Nowhere Standard Time', so its times are read as local"]
+    );
+    assert_eq!(agenda::clock(busy[2].start, &local), "11:00");
+}
+
+#[test]
+fn finish_requires_checklist_done() {
+    let dir = Path::new("./target/checklist-test");
+    std::fs::remove_dir_all(dir).ok();
+    std::fs::create_dir_all(dir).unwrap();
+
+    op::add(dir, TodoTask::new("Release"), Default::default()).unwrap();
+    let id = io::read_open_tasks(dir)[0].id().to_string();
+    op::check(dir, &id, Some("Tag".into()), None).unwrap();
+    op::check(dir, &id, Some("Publish".into()), Some(1)).unwrap();
+    assert_eq!(io::read_open_tasks(dir)[0].progress(), Some((1, 2)));
+
+    assert!(op::finish(dir, None, false, false).is_err());
+    assert!(!io::read_open_tasks(dir)[0].is_finished());
+    op::finish(dir, None, false, true).unwrap();
+    assert!(io::read_open_tasks(dir)[0].is_finished());
+}
+
+#[test]
+fn contexts_are_separate_from_tags() {
+    let (desc, labels) = tags::extract_inline("Pay rent +admin @home");
+    assert_eq!(desc, "Pay rent");
+    // labels are taken out in place, keeping the spacing of the rest
+    let (desc, _) = tags::extract_inline("+admin Pay  rent +x\tby Friday");
+    assert_eq!(desc, "Pay  rent\tby Friday");
+    assert_eq!(tags::extract_inline("Learn C++ + 2").0, "Learn C++ + 2");
+    let mut task = TodoTask::new(desc);
+    for label in labels {
+        label.apply(&mut task);
+    }
+    assert_eq!(task.tags().collect::<Vec<_>>(), ["admin"]);
+    assert_eq!(task.contexts().collect::<Vec<_>>(), ["home"]);
+
+    let home: FilterTag = "@home".parse().unwrap();
+    let not_home: FilterTag = "/@home".parse().unwrap();
+    let tag: FilterTag = "+home".parse().unwrap();
+    assert!(home.filter(&task));
+    assert!(!not_home.filter(&task));
+    assert!(!tag.filter(&task));
+}
+
+#[test]
+fn snoozed_tasks_are_hidden() {
+    let dir = Path::new("./target/snooze-test");
+    std::fs::remove_dir_all(dir).ok();
+    std::fs::create_dir_all(dir).unwrap();
+
+    op::add(dir, TodoTask::new("Later"), Default::default()).unwrap();
+    op::snooze(dir, 1, std::time::Duration::from_secs(3600)).unwrap();
+    let task = &io::read_open_tasks(dir)[0];
+    assert!(task.is_hidden());
+    assert!(task.snoozed_until().unwrap() > crate::now());
+
+    let mut task = TodoTask::new("Now");
+    task.snooze(std::time::Duration::ZERO);
+    assert!(!task.is_hidden());
+}
+
+#[test]
+fn pinned_tasks_stay_in_top_six() {
+    let mut tasks = Tasks((1..=8).map(|i| TodoTask::new(i.to_string())).collect());
+    tasks[5].pin();
+    // moving a backlog task in front would push the pin out
+    assert!(!tasks.reorder(7, 0, 6, &[]));
+    assert_eq!(tasks[0].description, "1");
+    assert_eq!(tasks[7].description, "8");
+    // reordering within the six is fine
+    assert!(tasks.reorder(0, 4, 6, &[]));
+    assert!(tasks[5].is_pinned());
+    // as is displacing an unpinned task
+    tasks[5].unpin();
+    tasks[0].pin();
+    assert!(tasks.reorder(7, 1, 6, &[]));
+    assert!(!tasks.reorder(0, 7, 6, &[]));
+}
+
+#[test]
+fn locked_plan_keeps_the_six() {
+    let dir = Path::new("./target/plan-lock-test");
+    std::fs::remove_dir_all(dir).ok();
+    std::fs::create_dir_all(dir).unwrap();
+
+    for i in 1..=7 {
+        op::add(dir, TodoTask::new(i.to_string()), Default::default()).unwrap();
+    }
+    op::lock_plan(dir).unwrap();
+    assert_eq!(io::read_plan_lock(dir).len(), 6);
+
+    op::add(dir, TodoTask::new("8"), Default::default()).unwrap();
+    assert!(op::move_(dir, 8, 1).is_err());
+    // within the six, and within the backlog, is fine
+    op::move_(dir, 2, 1).unwrap();
+    op::move_(dir, 8, 7).unwrap();
+    let tasks = io::read_open_tasks(dir);
+    assert_eq!(tasks[0].description, "2");
+    assert_eq!(tasks[6].description, "8");
+
+    op::unlock_plan(dir).unwrap();
+    assert!(io::read_plan_lock(dir).is_empty());
+    op::move_(dir, 8, 1).unwrap();
+}
+
+#[test]
+fn strict_mode_keeps_to_six() {
+    let dir = Path::new("./target/strict-test");
+    std::fs::remove_dir_all(dir).ok();
+    std::fs::create_dir_all(dir).unwrap();
+    std::fs::write(dir.join("config.ron"), "(strict: true, top: Some(8))").unwrap();
+
+    for i in 1..=6 {
+        op::add(dir, TodoTask::new(i.to_string()), Default::default()).unwrap();
+    }
+    assert!(op::add(dir, TodoTask::new("7"), Default::default()).is_err());
+    let force = op::AddOptions {
+        force: true,
+        ..Default::default()
+    };
+    op::add(dir, TodoTask::new("7"), force).unwrap();
+    op::add(dir, TodoTask::new("8"), force).unwrap();
+    // a plain append into the backlog is never refused
+    op::add(dir, TodoTask::new("9"), Default::default()).unwrap();
+    assert_eq!(io::read_open_tasks(dir).len(), 9);
+}
+
+#[test]
+fn tomorrow_tasks_promoted_on_a_new_day() {
+    let dir = Path::new("./target/tomorrow-test");
+    std::fs::remove_dir_all(dir).ok();
+    std::fs::create_dir_all(dir).unwrap();
+    let descs = |dir: &Path| {
+        io::read_open_tasks(dir)
+            .iter()
+            .map(|t| t.description.clone())
+            .collect::<Vec<_>>()
+    };
+
+    op::add(dir, TodoTask::new("today"), Default::default()).unwrap();
+    op::add_tomorrow(dir, TodoTask::new("first")).unwrap();
+    op::add_tomorrow(dir, TodoTask::new("second")).unwrap();
+    assert_eq!(io::read_open_tasks(dir).len(), 1);
+
+    // maintenance already ran today
+    let today = crate::now() / stats::DAY;
+    io::write_last_run(dir, today).unwrap();
+    op::maintain(dir).unwrap();
+    assert_eq!(io::read_tomorrow(dir).len(), 2);
+
+    // the first run of a new day moves them to the top
+    io::write_last_run(dir, today - 1).unwrap();
+    op::maintain(dir).unwrap();
+    assert!(io::read_tomorrow(dir).is_empty());
+    assert_eq!(descs(dir), ["first", "second", "today"]);
+}
+
+#[test]
+fn tomorrow_tasks_wait_to_be_promoted() {
+    let dir = Path::new("./target/tomorrow-wait-test");
+    std::fs::remove_dir_all(dir).ok();
+    std::fs::create_dir_all(dir).unwrap();
+    std::fs::write(
+        dir.join("config.ron"),
+        "(rollover: (promote_tomorrow: false))",
+    )
+    .unwrap();
+
+    op::add(dir, TodoTask::new("today"), Default::default()).unwrap();
+    op::add_tomorrow(dir, TodoTask::new("first")).unwrap();
+    op::add_tomorrow(dir, TodoTask::new("second")).unwrap();
+
+    // with promotion turned off, a new day does not reorder the list
+    io::write_last_run(dir, crate::now() / stats::DAY - 1).unwrap();
+    op::maintain(dir).unwrap();
+    assert_eq!(io::read_tomorrow(dir).len(), 2);
+
+    op::promote_tomorrow(dir).unwrap();
+    assert!(io::read_tomorrow(dir).is_empty());
+    let tasks = io::read_open_tasks(dir);
+    let descs = tasks
+        .iter()
+        .map(|t| t.description.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(descs, ["first", "second", "today"]);
+}
+
+#[test]
+fn rollover_sweeps_finished_tasks() {
+    let dir = Path::new("./target/rollover-test");
+    std::fs::remove_dir_all(dir).ok();
+    std::fs::create_dir_all(dir).unwrap();
+
+    op::add(dir, TodoTask::new("done yesterday"), Default::default()).unwrap();
+    op::add(dir, TodoTask::new("still open"), Default::default()).unwrap();
+    op::finish(dir, Some(1), false, false).unwrap();
+    io::write_last_run(dir, crate::now() / stats::DAY - 1).unwrap();
+    op::maintain(dir).unwrap();
+
+    let open = io::read_open_tasks(dir);
+    assert_eq!(open.len(), 1);
+    assert_eq!(open[0].description, "still open");
+    assert_eq!(open[0].carried, 1);
+    assert_eq!(io::read_done_tasks(dir).len(), 1);
+
+    // can be turned off
+    std::fs::write(dir.join("config.ron"), "(rollover: (sweep: false))").unwrap();
+    op::finish(dir, Some(1), false, false).unwrap();
+    io::write_last_run(dir, crate::now() / stats::DAY - 1).unwrap();
+    op::maintain(dir).unwrap();
+    assert_eq!(io::read_open_tasks(dir).len(), 1);
+}
+
+#[test]
+fn rollover_waits_for_the_bare_view() {
+    let dir = "./target/rollover-view-test";
+    std::fs::remove_dir_all(dir).ok();
+    std::fs::create_dir_all(dir).unwrap();
+    let path = Path::new(dir);
+    op::add(path, TodoTask::new("done yesterday"), Default::default()).unwrap();
+    op::add(path, TodoTask::new("still open"), Default::default()).unwrap();
+    op::finish(path, Some(1), false, false).unwrap();
+    op::lock_plan(path).unwrap();
+    io::write_last_run(path, crate::now() / stats::DAY - 1).unwrap();
+
+    // task 2 still means what was shown yesterday
+    ivly(dir).args(["pin", "2"]).assert().success();
+    assert_eq!(io::read_open_tasks(path).len(), 2);
+    assert!(io::read_open_tasks(path)[1].is_pinned());
+    assert!(!io::read_plan_lock(path).is_empty());
+
+    let out = ivly(dir).assert().success().get_output().stdout.clone();
+    assert!(String::from_utf8(out).unwrap().contains("Today's six"));
+    assert_eq!(io::read_open_tasks(path).len(), 1);
+    assert!(io::read_plan_lock(path).is_empty());
+}
+
+#[test]
+fn changelog_notices_once() {
+    let dir = Path::new("./target/changelog-test");
+    std::fs::remove_dir_all(dir).ok();
+    std::fs::create_dir_all(dir).unwrap();
+
+    let log =
+        "# Changelog\n\n## Unreleased\n\n- soon\n\n## 0.5.0\n\n- five\n\n## 0.4.1\n\n- four\n";
+    assert_eq!(
+        changelog::since(log, "0.4.1", "0.5.0"),
+        [("0.5.0", "- five")]
+    );
+    assert_eq!(
+        changelog::since(log, "0.4.0", "0.4.1"),
+        [("0.4.1", "- four")]
+    );
+    assert!(changelog::since(log, "0.5.0", "0.5.0").is_empty());
+
+    // a fresh directory has nothing to be told
+    changelog::notify(dir).unwrap();
+    assert_eq!(
+        io::read_seen_version(dir).as_deref(),
+        Some(env!("CARGO_PKG_VERSION"))
+    );
+
+    io::write_seen_version(dir, "0.4.1").unwrap();
+    changelog::notify(dir).unwrap();
+    assert_eq!(
+        io::read_seen_version(dir).as_deref(),
+        Some(env!("CARGO_PKG_VERSION"))
+    );
+}
+
+#[test]
+fn add_warns_of_recently_completed() {
+    let dir = "./target/recent-duplicate-test";
+    std::fs::remove_dir_all(dir).ok();
+
+    ivly(dir)
+        .args(["add", "Water the plants"])
+        .assert()
+        .success();
+    ivly(dir).args(["finish", "1"]).assert().success();
+    ivly(dir).arg("sweep").assert().success();
+    let out = ivly(dir)
+        .args(["add", "water the plants"])
+        .output()
+        .unwrap();
+    let out = String::from_utf8(out.stdout).unwrap();
+    assert!(out.contains("You completed a similar task"), "{out}");
+
+    let out = ivly(dir).args(["add", "Pay the bills"]).output().unwrap();
+    let out = String::from_utf8(out.stdout).unwrap();
+    assert!(!out.contains("You completed"), "{out}");
+}
+
+#[test]
+fn retag_replaces_labels() {
+    let dir = "./target/retag-test";
+    std::fs::remove_dir_all(dir).ok();
+
+    ivly(dir)
+        .args(["add", "One", "+old", "+work"])
+        .assert()
+        .success();
+    ivly(dir).args(["add", "Two", "+old"]).assert().success();
+    ivly(dir).args(["add", "Three"]).assert().success();
+    ivly(dir)
+        .args(["retag", "--from", "+old", "--to", "@desk", "+work", "--yes"])
+        .assert()
+        .success();
+
+    let tasks = io::read_open_tasks(Path::new(dir));
+    assert_eq!(tasks[0].tags().collect::<Vec<_>>(), ["work"]);
+    assert_eq!(tasks[0].contexts().collect::<Vec<_>>(), ["desk"]);
+    assert_eq!(tasks[1].tags().collect::<Vec<_>>(), ["old"]);
+}
+
+#[test]
+fn purge_cleans_tasks() {
+    let dir = "./target/purge-test";
+    std::fs::remove_dir_all(dir).ok();
+    std::fs::create_dir_all(dir).unwrap();
+    let mut a = TodoTask::new("A");
+    a.tags = vec![" Work".into(), "work".into(), "".into()];
+    a.contexts = vec!["Desk ".into(), "desk".into()];
+    let mut b = a.clone();
+    b.description = "B".into();
+    let empty = TodoTask::new("  ");
+    let id = a.id().to_string();
+    io::write_open_tasks(Path::new(dir), &Tasks(vec![a.clone(), b, empty])).unwrap();
+    // a done task sharing the open task's ID
+    let mut c = a.clone();
+    c.description = "C".into();
+    io::write_done_tasks(Path::new(dir), &Tasks(vec![c.complete()])).unwrap();
+    std::fs::write(
+        Path::new(dir).join("tags.ron"),
+        r#"{"Work": (fg: "red", bg: None), "work": (fg: "blue", bg: None), "Home": (fg: "red", bg: None)}"#,
+    )
+    .unwrap();
+
+    let out = ivly(dir).arg("purge").output().unwrap();
+    let out = String::from_utf8(out.stdout).unwrap();
+
+    let tasks = io::read_open_tasks(Path::new(dir));
+    assert_eq!(tasks.len(), 2);
+    assert_eq!(tasks[0].id(), id);
+    assert_ne!(tasks[0].id(), tasks[1].id());
+    assert_eq!(tasks[0].tags().collect::<Vec<_>>(), ["work"]);
+    assert_eq!(tasks[0].contexts().collect::<Vec<_>>(), ["desk"]);
+    let done = io::read_done_tasks(Path::new(dir));
+    assert_ne!(done[0].id(), id);
+    assert!(
+        out.contains(&format!("{id} → {} 'B'", tasks[1].id())),
+        "{out}"
+    );
+    assert!(
+        out.contains(&format!("{id} → {} 'C'", done[0].id())),
+        "{out}"
+    );
+
+    let tags = io::read_tags(Path::new(dir));
+    let tags = tags
+        .iter()
+        .map(|(t, s)| (t, s.fg.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(tags, [("home", "red"), ("work", "blue")]);
+
+    // the new IDs take up the history, rather than looking newly added or completed
+    let history = crate::history::read(Path::new(dir));
+    let reided = |id: &str| {
+        history
+            .iter()
+            .filter(|e| e.task == id)
+            .map(|e| e.change.as_str())
+            .collect::<Vec<_>>()
+    };
+    let was = format!("re-IDed from {id}");
+    assert_eq!(reided(tasks[1].id()), [was.as_str()]);
+    assert_eq!(reided(done[0].id()), [was.as_str()]);
+
+    ivly(dir)
+        .arg("purge")
+        .assert()
+        .success()
+        .stdout("Nothing to clean up\n");
+}
+
+#[test]
+fn import_md_checklist() {
+    let md = "# Meeting Notes
+Some discussion.
+- [ ] Send the agenda
+  - [x] Draft it
+  - [ ] Get sign off
+- [x] Book the room
+## Follow Up
+* [ ] Email Sam
+- a plain bullet
+```md
+- [ ] An example, not a task
+```
+- [ ] Plan the offsite
+  - [ ] Pick a venue
+    - [ ] Call the hotel
+";
+    let (tasks, warnings) = crate::import::md(md, &mut task::Ids::default());
+    assert_eq!(tasks.len(), 4);
+    assert_eq!(tasks[0].description, "Send the agenda");
+    assert_eq!(tasks[0].progress(), Some((1, 2)));
+    assert_eq!(tasks[0].tags().collect::<Vec<_>>(), ["meeting-notes"]);
+    assert!(!tasks[0].is_finished() && tasks[1].is_finished());
+    assert_eq!(
+        tasks[2].tags().collect::<Vec<_>>(),
+        ["meeting-notes", "follow-up"]
+    );
+    // too deep to keep as a tree, so flattened with a warning
+    assert_eq!(tasks[3].progress(), Some((0, 2)));
+    assert_eq!(warnings.len(), 1);
+    assert!(
+        warnings[0].starts_with("line 15: 'Call the hotel'"),
+        "{warnings:?}"
+    );
+
+    // upstream IDs are kept, unless taken
+    let mut ids = task::Ids::default();
+    ids.insert("taken");
+    let (tasks, _) = crate::import::md(
+        "- [ ] Renew passport 🆔 up12\n- [ ] Pay rent 🆔 taken\n",
+        &mut ids,
+    );
+    assert_eq!(tasks[0].id(), "up12");
+    assert_eq!(tasks[0].description, "Renew passport");
+    assert_eq!(tasks[1].description, "Pay rent");
+    assert_ne!(tasks[1].id(), "taken");
+
+    let dir = "./target/import-test";
+    std::fs::remove_dir_all(dir).ok();
+    std::fs::create_dir_all(dir).unwrap();
+    let file = format!("{dir}/notes.md");
+    std::fs::write(&file, md).unwrap();
+    let out = ivly(dir)
+        .args(["import", "--format", "md", &file])
+        .assert()
+        .success()
+        .get_output()
+        .stderr
+        .clone();
+    assert!(String::from_utf8(out)
+        .unwrap()
+        .contains("nested more than one level deep"));
+    assert_eq!(io::read_open_tasks(Path::new(dir)).len(), 3);
+    assert_eq!(io::read_done_tasks(Path::new(dir)).len(), 1);
+}
+
+#[test]
+fn ron_export_round_trips() {
+    use crate::task::{Energy, Item, Priority};
+    let dir = "./target/export-ron-test";
+    std::fs::remove_dir_all(dir).ok();
+    std::fs::create_dir_all(dir).unwrap();
+
+    let mut full = task("Everything", "a *note*", &["work", "q3"]);
+    full.contexts = vec!["desk".into()];
+    full.assignee = Some("sam".into());
+    full.estimate = Some(3600);
+    full.priority = Priority::High;
+    full.checklist = vec![Item {
+        text: "step".into(),
+        done: true,
+    }];
+    full.energy = Some(Energy::Low);
+    full.links = vec!["https://example.com".into()];
+    full.project = Some("launch".into());
+    full.carried = 2;
+    full.start_tracking();
+    full.stop_tracking();
+    let mut finished = TodoTask::new("Finished");
+    finished.finish();
+    let path = Path::new(dir);
+    io::write_open_tasks(path, &Tasks(vec![full, finished, TodoTask::new("Plain")])).unwrap();
+    io::write_done_tasks(path, &Tasks(vec![TodoTask::new("Done").complete()])).unwrap();
+    io::write_trash(path, &Tasks(vec![TodoTask::new("Trashed").trash()])).unwrap();
+
+    let export = |dir: &str| {
+        let out = ivly(dir)
+            .args(["export", "--format", "ron", "--verify"])
+            .assert()
+            .success();
+        String::from_utf8(out.get_output().stdout.clone()).unwrap()
+    };
+    let exported = export(dir);
+    assert!(exported.contains("Everything") && exported.contains("Trashed"));
+
+    let copy = "./target/export-ron-test-copy";
+    std::fs::remove_dir_all(copy).ok();
+    std::fs::create_dir_all(copy).unwrap();
+    let file = format!("{copy}/backup.ron");
+    std::fs::write(&file, &exported).unwrap();
+    ivly(copy)
+        .args(["import", "--format", "ron", &file])
+        .assert()
+        .success();
+    assert_eq!(export(copy), exported);
+
+    // importing again adds nothing
+    ivly(copy)
+        .args(["import", "--format", "ron", &file])
+        .assert()
+        .success();
+    assert_eq!(export(copy), exported);
+
+    ivly(dir)
+        .args(["export", "--format", "svg-summary", "--verify"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn capture_into_inbox() {
+    let dir = "./target/inbox-test";
+    std::fs::remove_dir_all(dir).ok();
+    ivly(dir).args(["add", "Open task"]).assert().success();
+    ivly(dir)
+        .args(["in", "maybe", "rewrite", "the", "docs"])
+        .assert()
+        .success();
+
+    let path = Path::new(dir);
+    assert_eq!(io::read_open_tasks(path).len(), 1);
+    let inbox = io::read_inbox(path);
+    assert_eq!(inbox.len(), 1);
+    assert_eq!(inbox[0].description, "maybe rewrite the docs");
+    let out = ivly(dir).assert().success().get_output().stdout.clone();
+    assert!(String::from_utf8(out)
+        .unwrap()
+        .contains("📥 1 in the inbox"));
+}
+
+#[test]
+fn task_refs_resolve() {
+    use crate::task::TaskRef;
+    let dir = Path::new("./target/task-ref-test");
+    std::fs::remove_dir_all(dir).ok();
+    std::fs::create_dir_all(dir).unwrap();
+    // fixed IDs, two sharing a prefix
+    let ids = ["ab12", "ab34", "cd56"].map(String::from);
+    let open = ids
+        .iter()
+        .zip(["One", "Two", "Three"])
+        .map(|(id, desc)| format!("(id: {id:?}, description: {desc:?}, created: 0, state: ())"))
+        .collect::<Vec<_>>();
+    std::fs::write(dir.join("open.ron"), format!("[{}]", open.join(","))).unwrap();
+    let r = |x: &str| op::resolve_ref(dir, &x.parse::<TaskRef>().unwrap());
+
+    assert_eq!(r("2").unwrap(), (2, ids[1].clone()));
+    assert_eq!(r(&ids[2]).unwrap(), (3, ids[2].clone()));
+    assert!(r("4").is_err());
+    assert!(r("zzzzzzzz").is_err());
+    assert_eq!(r("cd").unwrap(), (3, ids[2].clone()));
+    assert_eq!(r("ab3").unwrap(), (2, ids[1].clone()));
+    assert!(r("ab").is_err());
+    assert!("".parse::<TaskRef>().is_err());
+
+    let dir_ = dir.to_str().unwrap();
+    ivly(dir_).args(["finish", &ids[2]]).assert().success();
+    assert!(io::read_open_tasks(dir)[2].is_finished());
+
+    // positions shift, IDs do not
+    ivly(dir_).args(["bump", &ids[0]]).assert().success();
+    ivly(dir_)
+        .args(["move", &ids[2], &ids[1]])
+        .assert()
+        .success();
+    let order = io::read_open_tasks(dir)
+        .iter()
+        .map(|t| t.id().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(order, [ids[2].clone(), ids[1].clone(), ids[0].clone()]);
+}
+
+#[test]
+fn show_prints_every_detail() {
+    let dir = "./target/show-test";
+    std::fs::remove_dir_all(dir).ok();
+    ivly(dir)
+        .args(["add", "Write report", "+work", "--estimate", "2h"])
+        .args(["-n", "line one\nline two is the end of a long note"])
+        .assert()
+        .success();
+    ivly(dir)
+        .args(["link", "1", "https://example.com"])
+        .assert()
+        .success();
+
+    let out = ivly(dir)
+        .args(["show", "1"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let out = String::from_utf8(out).unwrap();
+    let id = io::read_open_tasks(Path::new(dir))[0].id().to_string();
+    for x in [
+        id.as_str(),
+        "Write report",
+        "open, task 1",
+        "+work",
+        "2h",
+        "https://example.com",
+        "line two is the end of a long note",
+        "added",
+    ] {
+        assert!(out.contains(x), "missing {x} in\n{out}");
+    }
+
+    ivly(dir).args(["finish", "1"]).assert().success();
+    ivly(dir).args(["sweep"]).assert().success();
+    let out = ivly(dir)
+        .args(["show", &id])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.contains("done") && out.contains("finished"));
+}
+
+#[test]
+fn broken_tasks_report_the_backup() {
+    let dir = "./target/broken-test";
+    std::fs::remove_dir_all(dir).ok();
+    ivly(dir).args(["add", "One"]).assert().success();
+    ivly(dir).args(["add", "Two"]).assert().success();
+    let path = Path::new(dir);
+    std::fs::write(path.join("open.ron"), "[ (id: \"broken\", description").unwrap();
+
+    let out = ivly(dir)
+        .arg("list")
+        .assert()
+        .success()
+        .get_output()
+        .stderr
+        .clone();
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.contains("Failed to read open.ron: 1:"), "{out}");
+    assert!(out.contains("Opening the backup saved"), "{out}");
+    assert!(
+        out.contains("with 1 tasks (2 differ from open.ron)"),
+        "{out}"
+    );
+    // reported once, however often the tasks are read
+    assert_eq!(out.matches("Failed to read").count(), 1, "{out}");
+    // kept before any write, and the good backup is not overwritten by it
+    let broken = std::fs::read_to_string(path.join("open.ron")).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(path.join("open.broken.ron")).unwrap(),
+        broken
+    );
+    let backup = std::fs::read_to_string(path.join("open.bak.ron")).unwrap();
+    ivly(dir).args(["add", "Three"]).assert().success();
+    assert_eq!(
+        std::fs::read_to_string(path.join("open.bak.ron")).unwrap(),
+        backup
+    );
+    assert_eq!(io::read_open_tasks(path).len(), 2);
+}
+
+#[test]
+fn swap_exchanges_tasks() {
+    let dir = "./target/swap-test";
+    std::fs::remove_dir_all(dir).ok();
+    for desc in ["One", "Two", "Three", "Four", "Five"] {
+        ivly(dir).args(["add", desc]).assert().success();
+    }
+    ivly(dir).args(["swap", "2", "5"]).assert().success();
+    let descs = |dir: &str| {
+        io::read_open_tasks(Path::new(dir))
+            .iter()
+            .map(|t| t.description.clone())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(descs(dir), ["One", "Five", "Three", "Four", "Two"]);
+    ivly(dir).args(["swap", "3", "3"]).assert().failure();
+}
+
+#[test]
+fn after_change_output() {
+    let dir = "./target/after-change-test";
+    std::fs::remove_dir_all(dir).ok();
+    for desc in ["One", "Two", "Three", "Four", "Five", "Six", "Seven"] {
+        ivly(dir).args(["add", desc]).assert().success();
+    }
+    let stdout = |args: &[&str]| {
+        let out = ivly(dir)
+            .args(args)
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+        String::from_utf8(out).unwrap()
+    };
+
+    // the six, then the bumped task below them
+    let out = stdout(&["bump", "2"]);
+    assert!(out.contains("One") && out.contains("Seven") && out.contains("…"));
+    assert!(out.find("Seven").unwrap() < out.rfind("Two").unwrap());
+
+    std::fs::write(Path::new(dir).join("config.ron"), "(after_change: Task)").unwrap();
+    let out = stdout(&["bump", "1"]);
+    assert!(out.contains("One") && !out.contains("Three"));
+
+    std::fs::write(Path::new(dir).join("config.ron"), "(after_change: Nothing)").unwrap();
+    assert_eq!(stdout(&["pin", "1"]).lines().count(), 1);
+}
+
+#[test]
+fn top_sets_tasks_shown() {
+    let dir = "./target/top-test";
+    std::fs::remove_dir_all(dir).ok();
+    for desc in [
+        "One", "Two", "Three", "Four", "Five", "Six", "Seven", "Eight",
+    ] {
+        ivly(dir).args(["add", desc]).assert().success();
+    }
+    let stdout = |args: &[&str]| {
+        let out = ivly(dir)
+            .args(args)
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+        String::from_utf8(out).unwrap()
+    };
+
+    assert!(!stdout(&[]).contains("Seven"));
+    assert!(stdout(&["--top", "7"]).contains("Seven"));
+    assert!(!stdout(&["--top", "2"]).contains("Three"));
+
+    std::fs::write(Path::new(dir).join("config.ron"), "(top: Some(3))").unwrap();
+    assert!(!stdout(&[]).contains("Four"));
+    let out = stdout(&["finish", "1"]);
+    assert!(out.contains("Three") && !out.contains("Four"));
+    let out = stdout(&["sweep"]);
+    assert!(out.contains("Four") && !out.contains("Five"));
+}
+
+#[test]
+fn reopen_tasks() {
+    let dir = "./target/reopen-test";
+    std::fs::remove_dir_all(dir).ok();
+    ivly(dir).args(["add", "One"]).assert().success();
+    ivly(dir).args(["add", "Two"]).assert().success();
+    ivly(dir).args(["finish", "1"]).assert().success();
+    ivly(dir).args(["finish", "2"]).assert().success();
+    ivly(dir).args(["reopen", "2"]).assert().success();
+    ivly(dir).args(["reopen", "2"]).assert().failure();
+
+    let path = Path::new(dir);
+    let open = io::read_open_tasks(path);
+    assert!(open[0].is_finished() && !open[1].is_finished());
+
+    let id = open[0].id().to_string();
+    ivly(dir).arg("sweep").assert().success();
+    assert_eq!(io::read_done_tasks(path).len(), 1);
+    ivly(dir).args(["reopen", &id]).assert().success();
+    assert!(io::read_done_tasks(path).is_empty());
+    let open = io::read_open_tasks(path);
+    assert_eq!(open.len(), 2);
+    assert_eq!(open[1].id(), id);
+    assert!(!open[1].is_finished());
+}
+
+#[test]
+fn stale_task_numbers_warn() {
+    let dir = "./target/stale-numbers-test";
+    std::fs::remove_dir_all(dir).ok();
+    ivly(dir).args(["add", "One"]).assert().success();
+    ivly(dir).args(["add", "Two"]).assert().success();
+    let stderr = |args: &[&str]| {
+        let out = ivly(dir)
+            .args(args)
+            .assert()
+            .success()
+            .get_output()
+            .stderr
+            .clone();
+        String::from_utf8(out).unwrap()
+    };
+
+    // the list was shown after the last change
+    assert!(!stderr(&["pin", "1"]).contains("may have shifted"));
+
+    // a change that prints nothing after it leaves the new numbers unseen
+    let path = Path::new(dir);
+    ivly(dir).args(["pin", "1", "--clear"]).assert().success();
+    std::fs::write(path.join("config.ron"), "(after_change: Nothing)").unwrap();
+    assert!(!stderr(&["bump", "1"]).contains("have shifted"));
+    assert!(stderr(&["pin", "1"]).contains("have shifted"));
+    // until the list is shown again
+    std::fs::remove_file(path.join("config.ron")).unwrap();
+    ivly(dir).assert().success();
+    assert!(!stderr(&["pin", "1"]).contains("have shifted"));
+
+    // changed elsewhere since it was shown
+    let mut tasks = io::read_open_tasks(path);
+    tasks.swap(0, 1);
+    let s = ron::to_string(&tasks).unwrap();
+    std::fs::write(path.join("open.ron"), s).unwrap();
+    let out = stderr(&["pin", "2"]);
+    assert!(out.contains("have shifted") && out.contains("2."), "{out}");
+    // a number still on the same task is fine
+    let mut tasks = io::read_open_tasks(path);
+    tasks.push(TodoTask::new("Three"));
+    std::fs::write(path.join("open.ron"), ron::to_string(&tasks).unwrap()).unwrap();
+    assert!(!stderr(&["pin", "1"]).contains("have shifted"));
+
+    // IDs are not numbers, so do not warn
+    tasks.swap(0, 1);
+    std::fs::write(path.join("open.ron"), ron::to_string(&tasks).unwrap()).unwrap();
+    assert!(!stderr(&["pin", tasks[1].id()]).contains("have shifted"));
+}
+
+#[test]
+fn list_cells_take_tag_colours() {
+    let dir = "./target/list-colour-test";
+    std::fs::remove_dir_all(dir).ok();
+    ivly(dir)
+        .args(["add", "Report", "+work", "+urgent", "@office"])
+        .assert()
+        .success();
+    ivly(dir)
+        .args(["tag", "work", "--fg", "red"])
+        .assert()
+        .success();
+    ivly(dir)
+        .args(["tag", "urgent", "--fg", "blue", "--bg", "white"])
+        .assert()
+        .success();
+    let list = |force: bool| {
+        let mut cmd = ivly(dir);
+        cmd.arg("list").env_remove("NO_COLOR");
+        if force {
+            cmd.env("CLICOLOR_FORCE", "1");
+        }
+        let out = cmd.assert().success().get_output().stdout.clone();
+        String::from_utf8(out).unwrap()
+    };
+
+    // piped output stays plain and aligned
+    let plain = list(false);
+    assert!(!plain.contains('\x1b'));
+    assert!(plain.contains("work,urgent,@office"), "{plain}");
+
+    // each tag takes its own colours
+    let styled = list(true);
+    assert!(styled.contains("\x1b[31mwork\x1b[0m"), "{styled}");
+    assert!(styled.contains("\x1b[47;34murgent\x1b[0m"), "{styled}");
+    assert!(styled.contains("\x1b[36m@office\x1b[0m"), "{styled}");
+    // the columns line up as in the plain output
+    let width = |s: &str| s.lines().next().unwrap().chars().count();
+    assert_eq!(width(&plain), width(&styled));
+}
+
+#[test]
+fn workspaces_keep_tasks_apart() {
+    let dir = "./target/workspace-test";
+    std::fs::remove_dir_all(dir).ok();
+    let stdout = |args: &[&str]| {
+        let out = ivly(dir)
+            .args(args)
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+        String::from_utf8(out).unwrap()
+    };
+
+    stdout(&["add", "Groceries"]);
+    ivly(dir).args(["ws", "use", "work"]).assert().failure();
+    ivly(dir).args(["ws", "new", "../work"]).assert().failure();
+    stdout(&["ws", "new", "work"]);
+    ivly(dir).args(["ws", "new", "work"]).assert().failure();
+
+    stdout(&["add", "Report", "--workspace", "work"]);
+    stdout(&["ws", "use", "work"]);
+    let out = stdout(&[]);
+    assert!(out.contains("Report") && !out.contains("Groceries"));
+    let out = stdout(&["--workspace", "default"]);
+    assert!(out.contains("Groceries") && !out.contains("Report"));
+
+    let out = stdout(&["ws", "list"]);
+    assert!(out.contains("▶ work") && out.contains("default"));
+    ivly(dir).args(["ws", "remove", "work"]).assert().failure();
+
+    stdout(&["ws", "use", "default"]);
+    assert!(stdout(&[]).contains("Groceries"));
+    ivly(dir).args(["ws", "remove", "work"]).assert().failure();
+    stdout(&["ws", "remove", "work", "--force"]);
+    assert!(!Path::new(dir).join("workspaces/work").exists());
+    ivly(dir).args(["--workspace", "work"]).assert().failure();
+    // the removed tasks are kept aside
+    let removed = std::fs::read_dir(Path::new(dir).join("removed-workspaces"))
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .collect::<Vec<_>>();
+    assert_eq!(removed.len(), 1);
+    assert_eq!(io::read_open_tasks(&removed[0])[0].description, "Report");
+
+    // done tasks are history worth keeping too
+    stdout(&["ws", "new", "home"]);
+    stdout(&["add", "Mow", "--workspace", "home"]);
+    stdout(&["finish", "1", "--workspace", "home"]);
+    stdout(&["sweep", "--workspace", "home"]);
+    ivly(dir).args(["ws", "remove", "home"]).assert().failure();
+    stdout(&["ws", "new", "empty"]);
+    stdout(&["ws", "remove", "empty"]);
+}
+
+#[test]
+fn tag_lists_styled_tags() {
+    let dir = "./target/tag-list-test";
+    std::fs::remove_dir_all(dir).ok();
+    let stdout = |args: &[&str]| {
+        let out = ivly(dir)
+            .args(args)
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+        String::from_utf8(out).unwrap()
+    };
+
+    assert!(stdout(&["tag"]).starts_with("No tags are styled"));
+    stdout(&["tag", "work", "--fg", "red"]);
+    stdout(&["tag", "home", "--fg", "blue", "--bg", "white"]);
+    for out in [stdout(&["tag"]), stdout(&["tag", "--list"])] {
+        assert!(out.contains("work") && out.contains("red"));
+        assert!(out.contains("home") && out.contains("white"));
+    }
+    ivly(dir).args(["tag", "--fg", "red"]).assert().failure();
+    ivly(dir).args(["tag", "work", "--list"]).assert().failure();
+}
+
+#[test]
+fn list_footer_counts_tasks() {
+    let dir = Path::new("./target/list-footer-test");
+    std::fs::remove_dir_all(dir).ok();
+    std::fs::create_dir_all(dir).unwrap();
+    op::add(dir, task("Report", "", &["work"]), Default::default()).unwrap();
+    op::add(dir, task("Invoice", "", &["work"]), Default::default()).unwrap();
+    op::add(dir, task("Groceries", "", &[]), Default::default()).unwrap();
+    op::finish(dir, Some(1), false, false).unwrap();
+
+    let list = |filter: filter::Filter| {
+        let mut o = Vec::new();
+        op::list(
+            dir,
+            Default::default(),
+            filter,
+            print::Render::plain(),
+            &mut o,
+        );
+        String::from_utf8(o).unwrap()
+    };
+    assert!(list(Default::default()).ends_with("3 open, 1 marked, 0 done shown\n"));
+
+    let filter = filter::Filter {
+        tags: vec!["+work".parse().unwrap()],
+        pattern: Some(regex::Regex::new("^I").unwrap()),
+        ..Default::default()
+    };
+    assert!(list(filter)
+        .ends_with("1 open, 0 marked, 0 done shown · filtered by +work, matching '^I'\n"));
+}
+
+#[test]
+fn defer_pushes_tasks_down() {
+    let dir = "./target/defer-test";
+    std::fs::remove_dir_all(dir).ok();
+    for desc in ["1", "2", "3", "4", "5", "6", "7", "8", "9"] {
+        ivly(dir).args(["add", desc]).assert().success();
+    }
+    let descs = || {
+        io::read_open_tasks(Path::new(dir))
+            .iter()
+            .map(|t| t.description.clone())
+            .collect::<Vec<_>>()
+    };
+
+    ivly(dir).args(["defer", "2", "3"]).assert().success();
+    assert_eq!(descs(), ["1", "3", "4", "5", "2", "6", "7", "8", "9"]);
+
+    // just below the six
+    ivly(dir).args(["defer", "1"]).assert().success();
+    assert_eq!(descs(), ["3", "4", "5", "2", "6", "7", "1", "8", "9"]);
+    ivly(dir).args(["defer", "8"]).assert().failure();
+
+    ivly(dir).args(["defer", "8", "10"]).assert().success();
+    assert_eq!(descs(), ["3", "4", "5", "2", "6", "7", "1", "9", "8"]);
+}
+
+#[test]
+fn sed_replaces_text() {
+    use crate::sed::Substitution;
+    let s: Substitution = "s/client ?x/ClientY/gi".parse().unwrap();
+    assert_eq!(
+        s.apply("Call clientX, then Client X"),
+        "Call ClientY, then ClientY"
+    );
+    let s: Substitution = "s|a|b|".parse().unwrap();
+    assert_eq!(s.apply("aaa"), "baa");
+    assert!("s/a/b".parse::<Substitution>().is_err());
+    assert!("s/a/b/x".parse::<Substitution>().is_err());
+
+    let dir = "./target/sed-test";
+    std::fs::remove_dir_all(dir).ok();
+    ivly(dir)
+        .args(["add", "Invoice ClientX", "-n", "ask ClientX"])
+        .assert()
+        .success();
+    ivly(dir).args(["add", "Other"]).assert().success();
+    ivly(dir)
+        .args(["sed", "s/ClientX/ClientY/", "--yes"])
+        .assert()
+        .success();
+    let tasks = io::read_open_tasks(Path::new(dir));
+    assert_eq!(tasks[0].description, "Invoice ClientY");
+    assert_eq!(tasks[0].note, "ask ClientY");
+    assert_eq!(tasks[1].description, "Other");
+}
+
+#[test]
+fn semantic_diff() {
+    use diff::Change;
+    let old = Tasks(["a", "b", "c", "d"].map(TodoTask::new).to_vec());
+    let mut new = Tasks(old.to_vec());
+    new.remove(1); // b
+    let d = new.remove(2);
+    new.insert(0, d);
+    new[1].note = "noted".into();
+    new.push(TodoTask::new("e"));
+
+    let desc = |x: &str| x.to_string();
+    assert_eq!(
+        diff::diff(&old, &new),
+        [
+            Change::Removed {
+                num: 2,
+                description: desc("b")
+            },
+            Change::Moved {
+                from: 4,
+                to: 1,
+                description: desc("d")
+            },
+            Change::Edited {
+                num: 2,
+                description: desc("a"),
+                fields: vec!["note"]
+            },
+            Change::Added {
+                num: 4,
+                description: desc("e")
+            },
+        ]
+    );
+}
+
+#[test]
+fn snapshots_restore_tasks() {
+    let dir = Path::new("./target/snapshot-test");
+    std::fs::remove_dir_all(dir).ok();
+    std::fs::create_dir_all(dir).unwrap();
+
+    op::add(dir, TodoTask::new("Kept"), Default::default()).unwrap();
+    io::save_snapshot(dir, "before").unwrap();
+    op::add(dir, TodoTask::new("Dropped"), Default::default()).unwrap();
+    assert_eq!(io::read_open_tasks(dir).len(), 2);
+
+    io::restore_snapshot(dir, "before").unwrap();
+    assert_eq!(io::read_open_tasks(dir).len(), 1);
+    let snaps = io::snapshots(dir);
+    assert_eq!(snaps.len(), 1);
+    assert_eq!(snaps[0].0, "before");
+    assert!(io::save_snapshot(dir, "../escape").is_err());
+}
+
+#[test]
+fn projects_rename_tasks() {
+    let dir = Path::new("./target/project-test");
+    std::fs::remove_dir_all(dir).ok();
+    std::fs::create_dir_all(dir).unwrap();
+
+    let mut t = TodoTask::new("Write report");
+    t.project = Some("work".into());
+    op::add(dir, t, Default::default()).unwrap();
+    op::add(dir, TodoTask::new("Unrelated"), Default::default()).unwrap();
+    assert!(io::read_projects(dir).contains("work"));
+
+    op::rename_project(dir, "work", "job").unwrap();
+    let tasks = io::read_open_tasks(dir);
+    assert_eq!(tasks[0].project.as_deref(), Some("job"));
+    assert_eq!(tasks[1].project, None);
+    let projects = io::read_projects(dir);
+    assert!(projects.contains("job") && !projects.contains("work"));
+    assert!(op::rename_project(dir, "missing", "x").is_err());
+}
+
+#[test]
+fn projects_take_their_style() {
+    let dir = "./target/project-style-test";
+    std::fs::remove_dir_all(dir).ok();
+    ivly(dir)
+        .args(["project", "add", "work", "--fg", "red"])
+        .assert()
+        .success();
+    ivly(dir)
+        .args(["add", "Report", "--project", "work"])
+        .assert()
+        .success();
+    let stdout = |args: &[&str]| {
+        let out = ivly(dir)
+            .args(args)
+            .env("CLICOLOR_FORCE", "1")
+            .env_remove("NO_COLOR")
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+        String::from_utf8(out).unwrap()
+    };
+    assert!(stdout(&[]).contains("\x1b[31m📁 work\x1b[0m"));
+    let list = stdout(&["list"]);
+    assert!(list.contains("Project"), "{list}");
+    assert!(list.contains("\x1b[31mwork\x1b[0m"), "{list}");
+}
+
+#[test]
+fn removed_tasks_go_to_trash() {
+    let dir = Path::new("./target/trash-test");
+    std::fs::remove_dir_all(dir).ok();
+    std::fs::create_dir_all(dir).unwrap();
+
+    op::add(dir, TodoTask::new("Oops"), Default::default()).unwrap();
+    let id = io::read_open_tasks(dir)[0].id().to_string();
+    op::remove(dir, &id).unwrap();
+    assert!(io::read_open_tasks(dir).is_empty());
+    assert_eq!(io::read_trash(dir).len(), 1);
+
+    op::restore_trash(dir, &id).unwrap();
+    assert_eq!(io::read_open_tasks(dir)[0].id(), id);
+    assert!(io::read_trash(dir).is_empty());
+
+    op::remove(dir, &id).unwrap();
+    let day = std::time::Duration::from_secs(stats::DAY);
+    op::empty_trash(dir, Some(day)).unwrap();
+    assert_eq!(io::read_trash(dir).len(), 1);
+    op::empty_trash(dir, None).unwrap();
+    assert!(io::read_trash(dir).is_empty());
+}
+
+#[test]
+fn undo_reverts_last_command() {
+    let dir = Path::new("./target/undo-test");
+    std::fs::remove_dir_all(dir).ok();
+    std::fs::create_dir_all(dir).unwrap();
+
+    let session = journal::Session::begin(dir, "ivly add First");
+    op::add(dir, TodoTask::new("First"), Default::default()).unwrap();
+    session.end().unwrap();
+    let session = journal::Session::begin(dir, "ivly finish");
+    op::finish(dir, None, false, false).unwrap();
+    op::sweep(dir, false, false).unwrap();
+    session.end().unwrap();
+    assert!(io::read_open_tasks(dir).is_empty());
+
+    let entry = journal::undo(dir, false).unwrap();
+    assert_eq!(entry.command, "ivly finish");
+    let undone = crate::history::read(dir)
+        .into_iter()
+        .filter(|e| e.command.as_deref() == Some("ivly undo"))
+        .map(|e| e.change)
+        .collect::<Vec<_>>();
+    assert_eq!(undone, ["added", "removed from the done list"]);
+    let open = io::read_open_tasks(dir);
+    assert!(!open[0].is_finished());
+    assert!(!dir.join("done.ron").exists());
+
+    // changed outside the journal
+    std::fs::write(dir.join("open.ron"), "[]").unwrap();
+    assert!(journal::undo(dir, false).is_err());
+    journal::undo(dir, true).unwrap();
+    assert!(!dir.join("open.ron").exists());
+    assert!(journal::undo(dir, false).is_err());
+}
+
+#[test]
+fn maintenance_is_undone_by_itself() {
+    let path = "./target/undo-maintain-test";
+    std::fs::remove_dir_all(path).ok();
+    let dir = Path::new(path);
+    ivly(path).args(["add", "Yesterday"]).assert().success();
+    ivly(path).args(["finish", "1"]).assert().success();
+    let yesterday = crate::now() / stats::DAY - 1;
+    io::write_last_run(dir, yesterday).unwrap();
+
+    ivly(path).assert().success();
+    assert!(io::read_open_tasks(dir).is_empty());
+    let entry = journal::undo(dir, false).unwrap();
+    assert_eq!(entry.command, "ivly maintain");
+    assert_eq!(io::read_open_tasks(dir).len(), 1);
+    assert_eq!(io::read_last_run(dir), Some(yesterday));
+}
+
+#[test]
+fn history_logs_task_changes() {
+    let path = "./target/history-test";
+    std::fs::remove_dir_all(path).ok();
+    let dir = Path::new(path);
+
+    // each command's changes are logged as its session ends
+    for args in [
+        &["add", "Logged"][..],
+        &["add", "Other"],
+        &["move", "2", "1"],
+        &["edit", "1", "-n", "call first"],
+        &["finish", "2"],
+        &["sweep"],
+    ] {
+        ivly(path).args(args).assert().success();
+    }
+    let edited = crate::history::read(dir)
+        .into_iter()
+        .find(|e| e.change.starts_with("edited"));
+    assert_eq!(edited.unwrap().change, r#"edited note: "" → "call first""#);
+
+    let moved = crate::history::read(dir)
+        .into_iter()
+        .find(|e| e.change.starts_with("moved"));
+    assert_eq!(moved.unwrap().description, "Other");
+    let id = io::read_done_tasks(dir)[0].id().to_string();
+    let changes = crate::history::read(dir)
+        .into_iter()
+        .filter(|e| e.task == id)
+        .map(|e| e.change)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        changes,
+        [
+            "added",
+            "finished",
+            "removed from the open list",
+            "completed"
+        ]
+    );
+}
+
+#[test]
+fn stale_tasks() {
+    let week = std::time::Duration::from_secs(7 * stats::DAY);
+    let task = TodoTask::new("fresh");
+    assert!(!task.is_stale(week));
+
+    let s = ron::to_string(&task).unwrap();
+    let created = format!("created:{}", task.created());
+    let old = format!("created:{}", task.created() - 8 * stats::DAY);
+    let mut task: TodoTask = ron::from_str(&s.replace(&created, &old)).unwrap();
+    assert!(task.is_stale(week));
+
+    // flagged by the age the caller renders with, 14 days by default
+    let render = |r| print::render_todo_task(0, &task, &Tags::default(), &Tags::default(), r);
+    assert!(!render(print::Render::plain()).contains('⚠'));
+    assert!(render(print::Render::plain().stale_after(week)).contains('⚠'));
+    task.finish();
+    assert!(!task.is_stale(week));
+}
+
+#[test]
+fn aging_buckets() {
+    let tasks = Tasks(vec![TodoTask::new("fresh")]);
+    let aging = report::aging(&tasks, 5);
+    let counts = aging.buckets.iter().map(|b| b.count).collect::<Vec<_>>();
+    assert_eq!(counts, [1, 0, 0, 0]);
+    assert_eq!(aging.oldest[0].age_days, 0);
+
+    let dir = "./target/aging-test";
+    std::fs::remove_dir_all(dir).ok();
+    ivly(dir).args(["add", "fresh, today"]).assert().success();
+    let out = ivly(dir)
+        .args(["report", "aging", "--format", "csv"])
+        .output()
+        .unwrap();
+    let out = String::from_utf8(out.stdout).unwrap();
+    let id = &io::read_open_tasks(Path::new(dir))[0].id().to_string();
+    assert_eq!(
+        out,
+        format!("Age,Tasks\n0-2d,1\n3-7d,0\n1-4w,0\n>1mo,0\n\nID,Description,Age (days)\n{id},\"fresh, today\",0\n")
+    );
+}
+
+#[test]
+fn tracking_one_task_at_a_time() {
+    let dir = Path::new("./target/tracking-test");
+    std::fs::remove_dir_all(dir).ok();
+    std::fs::create_dir_all(dir).unwrap();
+
+    op::add(dir, TodoTask::new("A"), Default::default()).unwrap();
+    op::add(dir, TodoTask::new("B"), Default::default()).unwrap();
+    op::start(dir, 1).unwrap();
+    op::start(dir, 2).unwrap();
+    let tasks = io::read_open_tasks(dir);
+    assert!(!tasks[0].is_tracking());
+    assert!(tasks[1].is_tracking());
+
+    op::stop(dir).unwrap();
+    let tasks = io::read_open_tasks(dir);
+    assert!(!tasks[1].is_tracking());
+    assert_eq!(tasks[0].intervals.len(), 1);
+    assert_eq!(tasks[1].intervals.len(), 1);
+}
+
+#[test]
+fn latency_by_tag() {
+    let dir = Path::new("./target/latency-test");
+    std::fs::remove_dir_all(dir).ok();
+    std::fs::create_dir_all(dir).unwrap();
+
+    op::add(dir, task("A", "", &["x"]), Default::default()).unwrap();
+    op::add(dir, TodoTask::new("B"), Default::default()).unwrap();
+    op::finish(dir, Some(1), false, false).unwrap();
+    op::finish(dir, Some(2), false, false).unwrap();
+    op::sweep(dir, false, false).unwrap();
+
+    let latency = report::latency(&io::read_open_tasks(dir), &io::read_done_tasks(dir));
+    let tags = latency.iter().map(|l| l.tag.as_str()).collect::<Vec<_>>();
+    assert_eq!(latency.len(), 2);
+    assert!(tags.contains(&"x") && tags.contains(&"(untagged)"));
+    assert!(latency.iter().all(|l| l.count == 1 && l.median <= l.p90));
+}
+
+#[test]
+fn report_formats() {
+    let rows = [report::Latency {
+        tag: "a,b".into(),
+        count: 2,
+        median: 3600,
+        p90: 7200,
+    }];
+    let csv = report::render(&rows, report::Format::Csv).unwrap();
+    assert_eq!(
+        csv,
+        "Tag,Completed,Median,90th percentile\n\"a,b\",2,3600,7200"
+    );
+    let json = report::render(&rows, report::Format::Json).unwrap();
+    assert!(json.contains("\"median\": 3600"));
+    let table = report::render(&rows, report::Format::Table).unwrap();
+    assert!(table.contains("1h"));
+}
+
+#[test]
+fn markdown_notes() {
+    use markdown::{Span, Style};
+    let lines = markdown::parse("- **do** the `cargo_fmt` *now*");
+    let plain = Style::default();
+    assert_eq!(
+        lines[0],
+        [
+            Span {
+                text: "• ".into(),
+                style: plain
+            },
+            Span {
+                text: "do".into(),
+                style: Style {
+                    bold: true,
+                    ..plain
+                }
+            },
+            Span {
+                text: " the ".into(),
+                style: plain
+            },
+            Span {
+                text: "cargo_fmt".into(),
+                style: Style {
+                    code: true,
+                    ..plain
+                }
+            },
+            Span {
+                text: " ".into(),
+                style: plain
+            },
+            Span {
+                text: "now".into(),
+                style: Style {
+                    italic: true,
+                    ..plain
+                }
+            },
+        ]
+    );
+}
+
+#[test]
+fn sweep_retags_swept_tasks() {
+    let dir = Path::new("./target/sweep-tags-test");
+    std::fs::remove_dir_all(dir).ok();
+    std::fs::create_dir_all(dir).unwrap();
+    std::fs::write(
+        dir.join("config.ron"),
+        r#"(sweep_tags: (remove: ["today"], add: ["swept"]))"#,
+    )
+    .unwrap();
+
+    op::add(dir, task("A", "", &["today", "x"]), Default::default()).unwrap();
+    op::add(dir, task("B", "", &["today"]), Default::default()).unwrap();
+    op::finish(dir, Some(1), false, false).unwrap();
+    op::sweep(dir, false, false).unwrap();
+
+    let done = io::read_done_tasks(dir);
+    assert_eq!(done[0].tags().collect::<Vec<_>>(), ["x", "swept"]);
+    let open = io::read_open_tasks(dir);
+    assert_eq!(open[0].tags().collect::<Vec<_>>(), ["today"]);
+}
+
+#[test]
+fn temporal_tags_expire() {
+    let dir = Path::new("./target/temporal-test");
+    std::fs::remove_dir_all(dir).ok();
+    std::fs::create_dir_all(dir).unwrap();
+
+    op::add(
+        dir,
+        task("A", "", &["today", "this-week", "x"]),
+        Default::default(),
+    )
+    .unwrap();
+    op::maintain(dir).unwrap();
+    assert_eq!(io::read_open_tasks(dir)[0].tags.len(), 3);
+
+    // pretend the last run was over a week ago
+    let today = crate::now() / stats::DAY;
+    io::write_last_run(dir, today - 8).unwrap();
+    op::maintain(dir).unwrap();
+    assert_eq!(io::read_open_tasks(dir)[0].tags, ["x"]);
+    assert_eq!(io::read_last_run(dir), Some(today));
+}
+
+#[test]
+fn triage_keys() {
+    let mut tasks = Tasks(["a", "b", "c", "d"].map(|d| task(d, "", &[])).to_vec());
+    let desc = |t: &tui::Triage| t.task().map(|t| t.description.clone());
+
+    let mut t = tui::Triage::new(&mut tasks, 1);
+    assert_eq!(desc(&t).as_deref(), Some("b"));
+    t.handle(KeyCode::Char(' '));
+    assert_eq!(desc(&t).as_deref(), Some("c"));
+    // a deleted task leaves the queue, so going back lands on the task before it
+    t.handle(KeyCode::Char('d'));
+    assert_eq!(desc(&t).as_deref(), Some("d"));
+    t.handle(KeyCode::Left);
+    assert_eq!(desc(&t).as_deref(), Some("b"));
+    // a snoozed task leaves the queue too
+    t.handle(KeyCode::Char('s'));
+    for c in "1d".chars() {
+        t.handle(KeyCode::Char(c));
+    }
+    t.handle(KeyCode::Enter);
+    assert_eq!(desc(&t).as_deref(), Some("d"));
+    // promoted into the last priority slot, leaving nothing to triage
+    t.handle(KeyCode::Char('p'));
+    assert!(t.task().is_none());
+    assert_eq!(t.trashed.len(), 1);
+    drop(t);
+
+    let order = tasks
+        .iter()
+        .map(|t| t.description.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(order, ["d", "a", "b"]);
+    assert!(tasks[2].is_hidden());
+}
+
+#[test]
+fn triage_inbox_keys() {
+    let dir = Path::new("./target/triage-inbox-test");
+    std::fs::remove_dir_all(dir).ok();
+    std::fs::create_dir_all(dir).unwrap();
+    op::add(dir, task("open", "", &[]), Default::default()).unwrap();
+    let mut inbox = Tasks(["a", "b", "c"].map(|d| task(d, "", &[])).to_vec());
+    let id = inbox[0].id().to_string();
+
+    let mut t = tui::Triage::inbox(&mut inbox);
+    // inbox items are not snoozed, so the key does nothing
+    t.handle(KeyCode::Char('s'));
+    t.handle(KeyCode::Char('p'));
+    assert_eq!(t.task().unwrap().description, "b");
+    t.handle(KeyCode::Char(' '));
+    t.handle(KeyCode::Char('d'));
+    assert!(t.task().is_none());
+    op::save_inbox_triage(dir, t).unwrap();
+
+    let open = io::read_open_tasks(dir);
+    let open = open
+        .iter()
+        .map(|t| (t.description.as_str(), t.id()))
+        .collect::<Vec<_>>();
+    assert_eq!(open[1], ("a", id.as_str()));
+    assert_eq!(open.len(), 2);
+    let inbox = io::read_inbox(dir);
+    assert_eq!(inbox.len(), 1);
+    assert_eq!(inbox[0].description, "b");
+    let trash = io::read_trash(dir);
+    assert_eq!(trash.len(), 1);
+    assert_eq!(trash[0].description, "c");
+}
+
+#[test]
+fn dedupe_keys() {
+    let mut tasks = Tasks(vec![
+        task("Renew car registration", "before May", &["admin"]),
+        task("Plan the offsite", "", &[]),
+        task("renew car registration", "", &["car"]),
+        task("Plan the offsite!", "", &[]),
+        task("Plan the offsite", "", &[]),
+    ]);
+
+    let mut d = tui::Dedupe::new(&mut tasks, 0.75);
+    assert_eq!(d.pair(), Some((0, 2)));
+    d.handle(KeyCode::Char('m'));
+    assert_eq!(d.pair(), Some((1, 2)));
+    d.handle(KeyCode::Char('k'));
+    assert_eq!(d.pair(), Some((1, 3)));
+    d.handle(KeyCode::Char('2'));
+    assert_eq!(d.pair(), None);
+    assert_eq!(d.trashed.len(), 1);
+    drop(d);
+
+    assert_eq!(tasks.len(), 3);
+    assert_eq!(tasks[0].note, "before May");
+    assert_eq!(tasks[0].tags().collect::<Vec<_>>(), ["admin", "car"]);
+}
+
+#[test]
+fn picker_keys() {
+    let tasks = Tasks(
+        ["write report", "review pr", "water plants"]
+            .map(|d| task(d, "", &[]))
+            .to_vec(),
+    );
+
+    let mut p = tui::Picker::new(&tasks);
+    assert_eq!(p.picked(), None);
+    for c in "wri".chars() {
+        p.handle(KeyCode::Char(c));
+    }
+    p.handle(KeyCode::Enter);
+    assert_eq!(p.picked(), Some(0));
+
+    // the matches are narrowed as the query grows, and widened as it shrinks
+    let mut p = tui::Picker::new(&tasks);
+    for c in "wx".chars() {
+        p.handle(KeyCode::Char(c));
+    }
+    p.handle(KeyCode::Enter);
+    assert_eq!(p.picked(), None);
+    p.handle(KeyCode::Backspace);
+    // best first: the matches at the start of a word, then the rest
+    p.handle(KeyCode::Down);
+    p.handle(KeyCode::Down);
+    p.handle(KeyCode::Down);
+    p.handle(KeyCode::Up);
+    p.handle(KeyCode::Enter);
+    assert_eq!(p.picked(), Some(2));
+
+    let mut p = tui::Picker::new(&tasks);
+    p.handle(KeyCode::Down);
+    p.handle(KeyCode::Esc);
+    assert_eq!(p.picked(), None);
+}
+
+#[test]
+fn finishing_again_is_quiet() {
+    let dir = "./target/finish-again-test";
+    std::fs::remove_dir_all(dir).ok();
+    std::fs::create_dir_all(dir).unwrap();
+    std::fs::write(
+        Path::new(dir).join("config.ron"),
+        "(finish_bell: true, celebrate: true, top: Some(1))",
+    )
+    .unwrap();
+    ivly(dir).args(["add", "only"]).assert().success();
+
+    let out = ivly(dir).args(["finish", "1"]).output().unwrap();
+    let out = String::from_utf8(out.stdout).unwrap();
+    assert!(out.contains('\x07'));
+    assert!(out.contains("All of today's tasks are done"));
+
+    let out = ivly(dir).args(["finish", "1"]).output().unwrap();
+    let out = String::from_utf8(out.stdout).unwrap();
+    assert!(!out.contains('\x07'));
+    assert!(!out.contains("All of today's tasks are done"));
+}
+
+#[test]
+fn edit_clears_energy() {
+    let dir = "./target/edit-energy-test";
+    std::fs::remove_dir_all(dir).ok();
+    ivly(dir)
+        .args(["add", "tired", "--energy", "low"])
+        .assert()
+        .success();
+    let energy = || io::read_open_tasks(Path::new(dir))[0].energy;
+
+    ivly(dir)
+        .args(["edit", "1", "--energy", "high"])
+        .assert()
+        .success();
+    assert!(energy() == Some(task::Energy::High));
+    ivly(dir)
+        .args(["edit", "1", "-d", "still"])
+        .assert()
+        .success();
+    assert!(energy() == Some(task::Energy::High));
+    ivly(dir)
+        .args(["edit", "1", "--energy", "none"])
+        .assert()
+        .success();
+    assert!(energy().is_none());
+}
+
+#[test]
+fn markdown_unclosed_markers() {
+    let text = |line: &str| {
+        markdown::parse(line)
+            .remove(0)
+            .into_iter()
+            .map(|s| (s.text, s.style.bold || s.style.italic || s.style.code))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(text("5 * 3 = 15"), [("5 * 3 = 15".into(), false)]);
+    assert_eq!(text("a ** b * c"), [("a ** b * c".into(), false)]);
+    assert_eq!(text("use `x"), [("use `x".into(), false)]);
+    assert_eq!(
+        text("*so* 2 * 3"),
+        [("so".into(), true), (" 2 * 3".into(), false)]
+    );
+
+    // only the marked spans are styled, the rest of the note is left plain
+    let dir = "./target/markdown-style-test";
+    std::fs::remove_dir_all(dir).ok();
+    ivly(dir)
+        .args(["add", "Note", "-n", "plain *italic*\nmore"])
+        .assert()
+        .success();
+    let out = ivly(dir)
+        .env("CLICOLOR_FORCE", "1")
+        .env_remove("NO_COLOR")
+        .output()
+        .unwrap();
+    let out = String::from_utf8(out.stdout).unwrap();
+    assert!(
+        out.contains("       plain \x1b[3mitalic\x1b[0m\n"),
+        "{out:?}"
+    );
+    assert!(out.contains("       more\n"), "{out:?}");
+}
+
+#[cfg(unix)]
+#[test]
+fn notes_edit_in_the_editor() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = "./target/editor-test";
+    std::fs::remove_dir_all(dir).ok();
+    std::fs::create_dir_all(dir).unwrap();
+    // an editor that checks it was given a fresh file holding the note, then rewrites it
+    let editor = std::fs::canonicalize(dir).unwrap().join("editor.sh");
+    std::fs::write(
+        &editor,
+        "#!/bin/sh\ncase \"$1\" in *ivly-note-*.md) ;; *) exit 1 ;; esac\n\
+         grep -q old \"$1\" || exit 1\necho edited > \"$1\"\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(&editor, std::fs::Permissions::from_mode(0o755)).unwrap();
+    ivly(dir)
+        .args(["add", "Task", "-n", "old"])
+        .assert()
+        .success();
+
+    ivly(dir)
+        .args(["edit", "1", "--note-editor"])
+        .env("VISUAL", &editor)
+        .assert()
+        .success();
+    assert_eq!(io::read_open_tasks(Path::new(dir))[0].note, "edited");
+
+    ivly(dir)
+        .args(["edit", "1", "--note-editor"])
+        .env("VISUAL", "false")
+        .assert()
+        .failure();
+    assert_eq!(io::read_open_tasks(Path::new(dir))[0].note, "edited");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn links_open_in_the_browser() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = "./target/link-test";
+    std::fs::remove_dir_all(dir).ok();
+    std::fs::create_dir_all(dir).unwrap();
+    // stand in for xdg-open, recording the link it is given
+    let bin = std::fs::canonicalize(dir).unwrap();
+    let opened = bin.join("opened");
+    std::fs::write(
+        bin.join("xdg-open"),
+        format!("#!/bin/sh\necho \"$1\" > {}\n", opened.display()),
+    )
+    .unwrap();
+    std::fs::set_permissions(bin.join("xdg-open"), std::fs::Permissions::from_mode(0o755)).unwrap();
+    let path = format!("{}:{}", bin.display(), std::env::var("PATH").unwrap());
+
+    ivly(dir).args(["add", "Read"]).assert().success();
+    ivly(dir).args(["open", "1"]).assert().failure();
+    let url = "https://example.com/?a=1&b=2";
+    ivly(dir).args(["link", "1", url]).assert().success();
+    ivly(dir).args(["link", "1", url]).assert().success();
+    ivly(dir).args(["link", "1", "notes.md"]).assert().success();
+    assert_eq!(
+        io::read_open_tasks(Path::new(dir))[0].links,
+        [url, "notes.md"]
+    );
+
+    ivly(dir)
+        .args(["open", "1"])
+        .env("PATH", path)
+        .assert()
+        .success();
+    // the opener runs in the background
+    let mut got = String::new();
+    for _ in 0..100 {
+        got = std::fs::read_to_string(&opened).unwrap_or_default();
+        if got.ends_with('\n') {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+    assert_eq!(got.trim(), url);
+}
+
+#[test]
+fn cancelled_tasks_are_not_completed() {
+    let dir = "./target/cancel-test";
+    std::fs::remove_dir_all(dir).ok();
+    let stdout = |args: &[&str]| {
+        let out = ivly(dir).args(args).assert().success();
+        String::from_utf8(out.get_output().stdout.clone()).unwrap()
+    };
+    stdout(&["add", "Keep"]);
+    stdout(&["add", "Drop"]);
+    stdout(&["finish", "1"]);
+    stdout(&["sweep"]);
+    assert!(stdout(&["cancel", "1", "--reason", "not needed"]).contains("Cancelled 'Drop'"));
+
+    assert!(io::read_open_tasks(Path::new(dir)).is_empty());
+    let done = io::read_done_tasks(Path::new(dir));
+    let cancelled = done.iter().find(|t| t.is_cancelled()).unwrap();
+    assert_eq!(cancelled.description, "Drop");
+    assert_eq!(cancelled.cancel_reason(), Some("not needed"));
+
+    let list = stdout(&["list", "--done"]);
+    assert!(
+        list.contains("cancelled") && list.contains("not needed"),
+        "{list}"
+    );
+    let stats = stdout(&["stats", "--format", "json"]);
+    assert!(stats.contains("\"completed\": 1"), "{stats}");
+}
+
+#[test]
+fn stats_take_the_list_filters() {
+    let dir = "./target/stats-filter-test";
+    std::fs::remove_dir_all(dir).ok();
+    let stdout = |args: &[&str]| {
+        let out = ivly(dir).args(args).assert().success();
+        String::from_utf8(out.get_output().stdout.clone()).unwrap()
+    };
+    stdout(&["add", "Report", "+work"]);
+    stdout(&["add", "Garden"]);
+    stdout(&["add", "Invoice", "+work"]);
+    stdout(&["finish", "1"]);
+    stdout(&["finish", "2"]);
+    stdout(&["finish", "3"]);
+    stdout(&["sweep"]);
+
+    assert!(stdout(&["stats"]).contains("Completed tasks: 3"));
+    let work = stdout(&["stats", "+work"]);
+    assert!(work.contains("Filtered: +work"), "{work}");
+    assert!(work.contains("Completed tasks: 2"), "{work}");
+    let json = stdout(&["stats", "--match", "Garden", "--format", "json"]);
+    assert!(json.contains("\"completed\": 1"), "{json}");
+}
+
+#[test]
+fn waiting_tasks_hide_until_resurfacing() {
+    let dir = "./target/wait-test";
+    std::fs::remove_dir_all(dir).ok();
+    let stdout = |args: &[&str]| {
+        let out = ivly(dir).args(args).assert().success();
+        String::from_utf8(out.get_output().stdout.clone()).unwrap()
+    };
+    stdout(&["add", "Report"]);
+    stdout(&["add", "Invoice"]);
+    ivly(dir).args(["wait", "1"]).assert().failure();
+
+    stdout(&["wait", "1", "--for", "reply from Bob", "--until", "3d"]);
+    let view = stdout(&[]);
+    assert!(
+        !view.contains("Report") && view.contains("Invoice"),
+        "{view}"
+    );
+    let waiting = stdout(&["waiting"]);
+    assert!(waiting.contains("Report") && !waiting.contains("Invoice"));
+    assert!(waiting.contains("waiting on reply from Bob (resurfaces in "));
+
+    stdout(&["wait", "2", "Sam", "--until", "0s"]);
+    let view = stdout(&[]);
+    assert!(view.contains("Invoice") && view.contains("waiting on Sam (resurfaced)"));
+
+    let id = io::read_open_tasks(Path::new(dir))[0].id().to_string();
+    stdout(&["wait", &id, "--clear"]);
+    let view = stdout(&[]);
+    assert!(
+        view.contains("Report") && !view.contains("reply from Bob"),
+        "{view}"
+    );
+}
+
+#[test]
+fn sweep_refills_from_the_backlog() {
+    let dir = "./target/refill-test";
+    std::fs::remove_dir_all(dir).ok();
+    std::fs::create_dir_all(dir).unwrap();
+    std::fs::write(Path::new(dir).join("config.ron"), "(top: Some(2))").unwrap();
+    for x in ["A", "B", "C", "D"] {
+        ivly(dir).args(["add", x]).assert().success();
+    }
+    let order = || {
+        io::read_open_tasks(Path::new(dir))
+            .iter()
+            .map(|t| t.description.clone())
+            .collect::<Vec<_>>()
+    };
+
+    ivly(dir).args(["finish", "1"]).assert().success();
+    // only backlog tasks can be picked, and no more than the free slots
+    ivly(dir)
+        .args(["sweep", "--refill"])
+        .write_stdin("1\n")
+        .assert()
+        .failure();
+    ivly(dir)
+        .args(["sweep", "--refill"])
+        .write_stdin("2 3\n")
+        .assert()
+        .failure();
+    assert_eq!(order(), ["A", "B", "C", "D"]);
+
+    // numbered as listed, once the finished task is swept
+    let out = ivly(dir)
+        .args(["sweep", "--refill"])
+        .write_stdin("3\n")
+        .assert()
+        .success();
+    let out = String::from_utf8(out.get_output().stdout.clone()).unwrap();
+    assert!(out.contains("1 free slots"), "{out}");
+    assert_eq!(order(), ["B", "D", "C"]);
+
+    // an empty answer keeps the order
+    ivly(dir).args(["finish", "1"]).assert().success();
+    ivly(dir)
+        .args(["sweep", "--refill"])
+        .write_stdin("\n")
+        .assert()
+        .success();
+    assert_eq!(order(), ["D", "C"]);
+}
+
+#[test]
+fn peek_previews_the_backlog() {
+    let dir = "./target/peek-test";
+    std::fs::remove_dir_all(dir).ok();
+    let stdout = |args: &[&str]| {
+        let out = ivly(dir).args(args).assert().success();
+        String::from_utf8(out.get_output().stdout.clone()).unwrap()
+    };
+    for i in 1..=9 {
+        stdout(&["add", &format!("Task {i}")]);
+    }
+
+    let view = stdout(&[]);
+    assert!(view.contains("Task 6") && !view.contains("Task 7"));
+    assert!(view.contains("3 tasks in backlog"));
+
+    let view = stdout(&["--peek", "2"]);
+    assert!(view.contains("   7. Task 7\n   8. Task 8\n"), "{view}");
+    assert!(!view.contains("Task 9") && view.contains("1 tasks in backlog"));
+
+    // the config sets the default, which the flag overrides
+    std::fs::write(Path::new(dir).join("config.ron"), "(peek: 1)").unwrap();
+    let view = stdout(&[]);
+    assert!(view.contains("Task 7") && !view.contains("Task 8"));
+    let view = stdout(&["--peek", "0"]);
+    assert!(!view.contains("Task 7"));
+}
+
+#[test]
+fn active_context_filters_the_view() {
+    let dir = "./target/context-test";
+    std::fs::remove_dir_all(dir).ok();
+    let stdout = |args: &[&str]| {
+        let out = ivly(dir).args(args).assert().success();
+        String::from_utf8(out.get_output().stdout.clone()).unwrap()
+    };
+    stdout(&["add", "Call the bank @phone"]);
+    stdout(&["add", "Mow", "@home"]);
+    stdout(&["add", "Water plants @home @garden"]);
+    let tasks = io::read_open_tasks(Path::new(dir));
+    assert_eq!(tasks[0].description, "Call the bank");
+    assert_eq!(tasks[0].contexts().collect::<Vec<_>>(), ["phone"]);
+    assert_eq!(tasks[2].contexts().collect::<Vec<_>>(), ["home", "garden"]);
+
+    assert_eq!(stdout(&["context"]), "No active context\n");
+    ivly(dir).args(["context", "home"]).assert().failure();
+    stdout(&["context", "@home"]);
+    assert_eq!(stdout(&["context"]), "📍 @home\n");
+
+    // the active context applies until another is given
+    let view = stdout(&[]);
+    assert!(view.contains("📍 @home") && view.contains("Mow") && view.contains("Water plants"));
+    assert!(!view.contains("Call the bank"));
+    let view = stdout(&["@phone"]);
+    assert!(view.contains("Call the bank") && !view.contains("Mow"));
+    let view = stdout(&["/@garden"]);
+    assert!(view.contains("Call the bank") && view.contains("Mow") && !view.contains("Water"));
+
+    stdout(&["context", "--clear"]);
+    let view = stdout(&[]);
+    assert!(view.contains("Call the bank") && view.contains("Mow") && !view.contains("📍"));
+}
+
+#[test]
+fn standup_lists_delegated_tasks() {
+    let dir = "./target/standup-test";
+    std::fs::remove_dir_all(dir).ok();
+    let stdout = |args: &[&str]| {
+        let out = ivly(dir).args(args).assert().success();
+        String::from_utf8(out.get_output().stdout.clone()).unwrap()
+    };
+    stdout(&["add", "Ship it"]);
+    stdout(&["add", "Review"]);
+    stdout(&["add", "Draft the report", "--assignee", "Bob"]);
+    stdout(&["add", "Book travel", "--delegated-to", "Ann"]);
+    stdout(&["add", "Invoice"]);
+    stdout(&["wait", "5", "--for", "the PO number"]);
+    stdout(&["finish", "1"]);
+
+    let delegated = stdout(&["delegated"]);
+    let (ann, bob) = (
+        delegated.find("Ann").unwrap(),
+        delegated.find("Bob").unwrap(),
+    );
+    assert!(ann < delegated.find("Book travel").unwrap());
+    assert!(bob < delegated.find("Draft the report").unwrap() && ann < bob);
+    assert!(!delegated.contains("Review"));
+
+    assert_eq!(
+        stdout(&["standup"]),
+        "Done:
+- Ship it
+Today:
+- Review
+- Draft the report
+- Book travel
+Waiting on:
+- Bob: Draft the report
+- Ann: Book travel
+- the PO number: Invoice
+"
+    );
 }
@@ -1,6 +1,8 @@
 use crate::{
-    days_ago, tag_csv,
-    task::{TodoTask, TodoTasks},
+    config::Theme,
+    date, days_ago, io as ivly_io, label_csv, markdown,
+    tags::Projects,
+    task::{DoneTasks, Ids, Priority, Task, Tasks, TodoTask, TodoTasks, TrashedTasks},
 };
 use crossterm::{
     event::{self, Event, KeyCode, KeyEventKind},
@@ -12,7 +14,11 @@ use ratatui::{
     prelude::*,
     widgets::{Block, Cell, Row, Table, TableState},
 };
-use std::io::{self, stdout, Stdout};
+use std::{
+    io::{self, stdout, Stdout},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
 
 pub type Tui = Terminal<CrosstermBackend<Stdout>>;
 
@@ -28,6 +34,20 @@ fn term_restore() -> io::Result<()> {
     Ok(())
 }
 
+#[derive(Copy, Clone, PartialEq)]
+enum Tab {
+    Open,
+    Done,
+}
+
+/// A temporary display order for the open list.
+#[derive(Copy, Clone, PartialEq)]
+enum Sort {
+    /// Oldest first.
+    Created,
+    Description,
+}
+
 #[derive(Copy, Clone, PartialEq)]
 enum Exit {
     Continue,
@@ -35,6 +55,112 @@ enum Exit {
     Forget,
 }
 
+/// How often the event loop wakes without any input.
+const TICK_RATE: Duration = Duration::from_millis(250);
+
+/// Messages which update the TUI state.
+#[derive(Copy, Clone, PartialEq)]
+enum Action {
+    /// Time has passed.
+    Tick,
+    /// A raw key press, used for text input.
+    Input(KeyCode),
+    Exit(Exit),
+    ToggleHelp,
+    SwitchTab,
+    Up,
+    Down,
+    First,
+    Last,
+    /// Move the selected task up one place.
+    Raise,
+    /// Move the selected task down one place.
+    Lower,
+    /// Move the selected task to this index.
+    Prioritise(usize),
+    Remove,
+    Add,
+    EditDesc,
+    EditNote,
+    /// Edit the selected task's note in `$EDITOR`.
+    EditNoteExternal,
+    EditTags,
+    /// Cycle the selected task's priority.
+    CyclePriority,
+    Search,
+    Reopen,
+    /// Replace the open tasks with those on disk.
+    Reload,
+    /// Ignore the changes on disk, keeping the tasks being edited.
+    KeepMine,
+    /// Switch between relative and absolute dates.
+    ToggleDates,
+    /// Cycle the display order of the open list.
+    Sort,
+    /// Save the display order as the task order.
+    KeepSort,
+    /// Repeat the last action.
+    Repeat,
+    /// Start or stop recording a macro.
+    Record,
+    /// Replay the recorded macro.
+    Replay,
+}
+
+impl Action {
+    /// Actions which change the tasks or selection, and so are worth repeating.
+    fn is_repeatable(&self) -> bool {
+        !matches!(
+            self,
+            Action::Tick
+                | Action::Input(_)
+                | Action::Exit(_)
+                | Action::ToggleHelp
+                | Action::Reload
+                | Action::KeepMine
+                | Action::Repeat
+                | Action::Record
+                | Action::Replay
+        )
+    }
+}
+
+/// Watches the open tasks file for changes made outside the TUI.
+struct Watch {
+    dir: PathBuf,
+    modified: Option<SystemTime>,
+    changed: bool,
+}
+
+impl Watch {
+    fn new(dir: &Path) -> Self {
+        let dir = dir.to_path_buf();
+        let modified = modified(&dir);
+        Watch {
+            dir,
+            modified,
+            changed: false,
+        }
+    }
+
+    fn check(&mut self) {
+        if !self.changed && modified(&self.dir) != self.modified {
+            self.changed = true;
+        }
+    }
+
+    fn acknowledge(&mut self) {
+        self.modified = modified(&self.dir);
+        self.changed = false;
+    }
+}
+
+fn modified(dir: &Path) -> Option<SystemTime> {
+    std::fs::metadata(ivly_io::open_tasks_file(dir))
+        .and_then(|m| m.modified())
+        .ok()
+}
+
 enum Editing {
     Desc { idx: usize, val: String },
     Note { idx: usize, val: String },
@@ -71,9 +197,9 @@ impl Editing {
     }
 
     /// If editing this description, creating the 'editing' text.
-    fn desc(&self, idx_: usize, task: &TodoTask) -> Text {
+    fn desc(&self, idx_: usize, task: &TodoTask, colour: Color) -> Text {
         let txt = match self {
-            Self::Desc { idx, val } if *idx == idx_ => Text::from(val.clone()).yellow(),
+            Self::Desc { idx, val } if *idx == idx_ => Text::from(val.clone()).fg(colour),
             _ => Text::from(task.description.clone()),
         }
         .bold();
@@ -85,42 +211,129 @@ impl Editing {
     }
 
     /// If editing this note, creating the 'editing' text.
-    fn note(&self, idx_: usize, task: &TodoTask) -> Text {
+    fn note(&self, idx_: usize, task: &TodoTask, colour: Color) -> Text {
         match self {
-            Self::Note { idx, val } if *idx == idx_ => Text::from(val.clone()).italic().yellow(),
-            _ => Text::from(task.note.clone()).italic(),
+            Self::Note { idx, val } if *idx == idx_ => Text::from(val.clone()).italic().fg(colour),
+            _ => note_text(task),
         }
     }
 
     /// If editing this tags, creating the 'editing' text.
-    fn tags(&self, idx_: usize, task: &TodoTask) -> Text {
+    fn tags(&self, idx_: usize, task: &TodoTask, colour: Color) -> Text {
         match self {
-            Self::Tags { idx, val } if *idx == idx_ => Text::from(val.clone()).yellow(),
-            _ => Text::from(tag_csv(task.tags())),
+            Self::Tags { idx, val } if *idx == idx_ => Text::from(val.clone()).fg(colour),
+            _ => Text::from(label_csv(task)),
         }
     }
 }
 
 pub struct Move<'a> {
     pub tasks: &'a mut TodoTasks,
+    pub done: &'a mut DoneTasks,
+    /// Tasks deleted, to be moved into the trash when saved.
+    pub trashed: TrashedTasks,
+    tab: Tab,
     table_state: TableState,
+    done_state: TableState,
     exit: Exit,
     show_help: bool,
     editing: Editing,
+    /// Filter for the done list.
+    search: String,
+    searching: bool,
+    watch: Option<Watch>,
+    last_action: Option<Action>,
+    /// The macro being recorded.
+    recording: Option<Vec<Action>>,
+    macro_: Vec<Action>,
+    styles: Styles,
+    sort: Option<Sort>,
+    absolute_dates: bool,
+    /// Open the selected note in `$EDITOR` once the terminal is released.
+    editor_pending: bool,
+    /// Feedback, such as an editor failing, shown until the next key press.
+    status: Option<String>,
+    /// IDs of the locked plan, which reordering cannot change.
+    locked: Vec<String>,
+    /// How many priority tasks are shown.
+    top: usize,
+    /// The styles of the project column.
+    projects: Projects,
+    /// The IDs in use, for the tasks added.
+    ids: Ids,
 }
 
 impl<'a> Move<'a> {
-    pub fn new(tasks: &'a mut TodoTasks) -> Self {
+    pub fn new(tasks: &'a mut TodoTasks, done: &'a mut DoneTasks) -> Self {
         Move {
             tasks,
+            done,
+            trashed: Tasks::new(),
+            tab: Tab::Open,
             table_state: TableState::default().with_selected(0),
+            done_state: TableState::default().with_selected(0),
             exit: Exit::Continue,
             show_help: false,
             editing: Editing::None,
+            search: String::new(),
+            searching: false,
+            watch: None,
+            last_action: None,
+            recording: None,
+            macro_: Vec::new(),
+            styles: Styles::new(&Theme::default()),
+            sort: None,
+            absolute_dates: false,
+            editor_pending: false,
+            status: None,
+            locked: Vec::new(),
+            top: 6,
+            projects: Projects::default(),
+            ids: Ids::default(),
         }
     }
 
-    pub fn run(mut self) -> Result<bool> {
+    /// Use the colours from this theme.
+    pub fn theme(mut self, theme: &Theme) -> Self {
+        self.styles = Styles::new(theme);
+        self
+    }
+
+    /// Keep pinned tasks within this many priority tasks, defaulting to six.
+    pub fn top(mut self, top: usize) -> Self {
+        self.top = top;
+        self
+    }
+
+    /// Give added tasks IDs apart from these, as well as the open and done tasks.
+    pub fn ids(mut self, ids: Ids) -> Self {
+        self.ids = ids;
+        self
+    }
+
+    /// Colour the project column with these styles.
+    pub fn projects(mut self, projects: Projects) -> Self {
+        self.projects = projects;
+        self
+    }
+
+    /// Keep the tasks of a locked plan in the priority tasks, and others out of them.
+    pub fn lock(mut self, locked: Vec<String>) -> Self {
+        self.locked = locked;
+        self
+    }
+
+    /// Watch the open tasks saved in `dir`, offering to reload them if they change.
+    pub fn watch(mut self, dir: &Path) -> Self {
+        self.watch = Some(Watch::new(dir));
+        self
+    }
+
+    fn reload_pending(&self) -> bool {
+        self.watch.as_ref().is_some_and(|w| w.changed)
+    }
+
+    pub fn run(&mut self) -> Result<bool> {
         let mut term = term_init().into_diagnostic()?;
         let res = self.run_loop(&mut term);
         term_restore().into_diagnostic()?;
@@ -132,9 +345,48 @@ impl<'a> Move<'a> {
     }
 
     fn run_loop(&mut self, terminal: &mut Tui) -> io::Result<()> {
+        let mut last_tick = Instant::now();
         while self.exit == Exit::Continue {
             terminal.draw(|frame| self.render_frame(frame))?;
-            self.handle_events()?;
+
+            let timeout = TICK_RATE.saturating_sub(last_tick.elapsed());
+            if event::poll(timeout)? {
+                match event::read()? {
+                    // it's important to check that the event is a key press event as
+                    // crossterm also emits key release and repeat events on Windows.
+                    Event::Key(key_ev) if key_ev.kind == KeyEventKind::Press => {
+                        self.status = None;
+                        if let Some(action) = self.key_action(key_ev.code) {
+                            self.update(action);
+                        }
+                    }
+                    _ => (),
+                }
+            }
+            if std::mem::take(&mut self.editor_pending) {
+                self.edit_note_externally(terminal)?;
+            }
+            if last_tick.elapsed() >= TICK_RATE {
+                self.update(Action::Tick);
+                last_tick = Instant::now();
+            }
+        }
+        Ok(())
+    }
+
+    /// Hand the terminal to `$EDITOR` to edit the selected task's note.
+    fn edit_note_externally(&mut self, terminal: &mut Tui) -> io::Result<()> {
+        let Some(i) = self.selected_task() else {
+            return Ok(());
+        };
+        term_restore()?;
+        let note = ivly_io::edit_in_editor(&self.tasks[i].note);
+        execute!(stdout(), EnterAlternateScreen)?;
+        enable_raw_mode()?;
+        terminal.clear()?;
+        match note {
+            Ok(note) => self.tasks[i].note = note,
+            Err(e) => self.status = Some(format!("⚠️ Note not changed: {e}")),
         }
         Ok(())
     }
@@ -143,10 +395,28 @@ impl<'a> Move<'a> {
         self.render_table(frame);
 
         let size = frame.size();
-        let instructions = if self.editing.is_editing() {
-            "Enter to accept changes"
+        let instructions = if self.reload_pending() {
+            "Tasks changed on disk!  r Reload (lose changes)  k Keep mine  X Exit".to_string()
+        } else if let Some(status) = &self.status {
+            status.clone()
+        } else if self.editing.is_editing() {
+            "Enter to accept changes".to_string()
+        } else if self.searching {
+            format!("Search: {}  Enter to accept  Esc to clear", self.search)
         } else {
-            "? Toggle Help  X Exit  q Save and exit"
+            let list = match (self.tab, self.sort) {
+                (Tab::Open, None) => "Open list",
+                (Tab::Open, Some(Sort::Created)) => "Open list by created (S to keep)",
+                (Tab::Open, Some(Sort::Description)) => "Open list by description (S to keep)",
+                (Tab::Done, _) if self.search.is_empty() => "Done list",
+                (Tab::Done, _) => "Done list (filtered)",
+            };
+            let rec = if self.recording.is_some() {
+                "● Recording (m to stop)  "
+            } else {
+                ""
+            };
+            format!("{rec}{list}  ? Toggle Help  Tab Switch list  X Exit  q Save and exit")
         };
         let instructions = Text::from(instructions).centered();
         let size = Rect {
@@ -157,88 +427,267 @@ impl<'a> Move<'a> {
         frame.render_widget(instructions, size);
 
         if self.show_help {
-            render_help(frame)
+            render_help(frame, self.tab)
         }
     }
 
-    fn handle_events(&mut self) -> io::Result<()> {
+    /// Map a key press to an action, depending on the current mode.
+    fn key_action(&self, key_code: KeyCode) -> Option<Action> {
+        if self.reload_pending() {
+            return match key_code {
+                KeyCode::Char('r') => Some(Action::Reload),
+                KeyCode::Char('k') => Some(Action::KeepMine),
+                KeyCode::Char('X') => Some(Action::Exit(Exit::Forget)),
+                _ => None,
+            };
+        }
+        if self.editing.is_editing() || self.searching {
+            return Some(Action::Input(key_code));
+        }
+
+        let action = match (self.tab, key_code) {
+            (_, KeyCode::Char('q')) => Action::Exit(Exit::Save),
+            (_, KeyCode::Char('X')) => Action::Exit(Exit::Forget),
+            (_, KeyCode::Char('?')) => Action::ToggleHelp,
+            (_, KeyCode::Tab) => Action::SwitchTab,
+            (_, KeyCode::Up) => Action::Up,
+            (_, KeyCode::Down) => Action::Down,
+            (_, KeyCode::Home) => Action::First,
+            (_, KeyCode::End) => Action::Last,
+            (_, KeyCode::Char('D')) => Action::Remove,
+            (_, KeyCode::Char('c')) => Action::ToggleDates,
+            (_, KeyCode::Char('.')) => Action::Repeat,
+            (_, KeyCode::Char('m')) => Action::Record,
+            (_, KeyCode::Char('@')) => Action::Replay,
+            (Tab::Open, KeyCode::Char('=')) => Action::Raise,
+            (Tab::Open, KeyCode::Char('-')) => Action::Lower,
+            (Tab::Open, KeyCode::Char(c @ '1'..='6')) => {
+                Action::Prioritise(c as usize - '1' as usize)
+            }
+            (Tab::Open, KeyCode::Char('a')) => Action::Add,
+            (Tab::Open, KeyCode::Char('e')) => Action::EditDesc,
+            (Tab::Open, KeyCode::Char('n')) => Action::EditNote,
+            (Tab::Open, KeyCode::Char('E')) => Action::EditNoteExternal,
+            (Tab::Open, KeyCode::Char('t')) => Action::EditTags,
+            (Tab::Open, KeyCode::Char('p')) => Action::CyclePriority,
+            (Tab::Open, KeyCode::Char('s')) => Action::Sort,
+            (Tab::Open, KeyCode::Char('S')) => Action::KeepSort,
+            (Tab::Done, KeyCode::Char('/')) => Action::Search,
+            (Tab::Done, KeyCode::Char('r')) => Action::Reopen,
+            _ => return None,
+        };
+        Some(action)
+    }
+
+    fn update(&mut self, action: Action) {
+        match action {
+            Action::Repeat => {
+                if let Some(action) = self.last_action {
+                    self.update(action);
+                }
+                return;
+            }
+            Action::Record => {
+                match self.recording.take() {
+                    Some(actions) => self.macro_ = actions,
+                    None => self.recording = Some(Vec::new()),
+                }
+                return;
+            }
+            Action::Replay => {
+                for action in self.macro_.clone() {
+                    self.update(action);
+                }
+                return;
+            }
+            _ => (),
+        }
+
+        if action.is_repeatable() {
+            self.last_action = Some(action);
+        }
+        if let Some(rec) = &mut self.recording {
+            if !matches!(action, Action::Tick | Action::Exit(_)) {
+                rec.push(action);
+            }
+        }
+
         let tlen = self.tasks.len();
-        let key_ev = match event::read()? {
-            // it's important to check that the event is a key press event as
-            // crossterm also emits key release and repeat events on Windows.
-            Event::Key(key_event) if key_event.kind == KeyEventKind::Press => Some(key_event),
-            _ => None,
+        let dlen = self.visible_done().len();
+        let state = match self.tab {
+            Tab::Open => &mut self.table_state,
+            Tab::Done => &mut self.done_state,
         };
-        if let Some(key_ev) = key_ev {
-            if self.editing.is_editing() {
-                self.handle_editing(key_ev.code);
-            } else {
-                match key_ev.code {
-                    KeyCode::Char('q') => self.exit = Exit::Save,
-                    KeyCode::Char('X') => self.exit = Exit::Forget,
-                    KeyCode::Up => {
-                        *self.table_state.selected_mut() = self
-                            .table_state
-                            .selected()
-                            .unwrap_or_default()
-                            .saturating_sub(1)
-                            .into()
-                    }
-                    KeyCode::Down => {
-                        *self.table_state.selected_mut() =
-                            (self.table_state.selected().unwrap_or_default() + 1)
-                                .min(tlen)
-                                .into()
-                    }
-                    KeyCode::Home => *self.table_state.selected_mut() = 0.into(),
-                    KeyCode::End => {
-                        *self.table_state.selected_mut() = tlen.saturating_sub(1).into()
-                    }
-                    KeyCode::Char('=') => self.move_(|i| i.saturating_sub(1)),
-                    KeyCode::Char('-') => self.move_(|i| (i + 2).min(tlen)),
-                    KeyCode::Char('1') => self.move_(|_| 0),
-                    KeyCode::Char('2') => self.move_(|_| 1.min(tlen)),
-                    KeyCode::Char('3') => self.move_(|_| 2.min(tlen)),
-                    KeyCode::Char('4') => self.move_(|_| 3.min(tlen)),
-                    KeyCode::Char('5') => self.move_(|_| 4.min(tlen)),
-                    KeyCode::Char('6') => self.move_(|_| 5.min(tlen)),
-                    KeyCode::Char('D') => {
-                        if let Some(i) = self.table_state.selected() {
-                            self.tasks.remove(i);
-                            *self.table_state.selected_mut() = Some(i.saturating_sub(0));
-                        }
+        let selected = state.selected().unwrap_or_default();
+        let len = match self.tab {
+            Tab::Open => tlen,
+            Tab::Done => dlen,
+        };
+
+        match action {
+            Action::Tick => {
+                if let Some(w) = &mut self.watch {
+                    w.check();
+                }
+            }
+            Action::Reload => {
+                if let Some(w) = &mut self.watch {
+                    *self.tasks = ivly_io::read_open_tasks(&w.dir);
+                    self.editing = Editing::None;
+                    *self.table_state.selected_mut() = Some(0);
+                    w.acknowledge();
+                }
+            }
+            Action::KeepMine => {
+                if let Some(w) = &mut self.watch {
+                    w.acknowledge();
+                }
+            }
+            Action::Input(key_code) if self.searching => self.handle_searching(key_code),
+            Action::Input(key_code) => self.handle_editing(key_code),
+            Action::Exit(exit) => self.exit = exit,
+            Action::ToggleHelp => self.show_help = !self.show_help,
+            Action::ToggleDates => self.absolute_dates = !self.absolute_dates,
+            Action::SwitchTab => {
+                self.tab = match self.tab {
+                    Tab::Open => Tab::Done,
+                    Tab::Done => Tab::Open,
+                }
+            }
+            Action::Up => *state.selected_mut() = selected.saturating_sub(1).into(),
+            Action::Down => {
+                *state.selected_mut() = (selected + 1).min(len.saturating_sub(1)).into()
+            }
+            Action::First => *state.selected_mut() = 0.into(),
+            Action::Last => *state.selected_mut() = len.saturating_sub(1).into(),
+            Action::Raise => self.move_(|i| i.saturating_sub(1)),
+            Action::Lower => self.move_(|i| (i + 2).min(tlen)),
+            Action::Prioritise(i) => self.move_(|_| i.min(tlen)),
+            Action::Remove => match self.tab {
+                Tab::Open => {
+                    if let Some(i) = self.selected_task() {
+                        self.trashed.push(self.tasks.remove(i).trash());
                     }
-                    KeyCode::Char('a') => {
-                        let i = self.tasks.len();
-                        self.tasks.push(TodoTask::new(""));
-                        *self.table_state.selected_mut() = Some(i);
-                        self.start_editing_desc()
+                }
+                Tab::Done => {
+                    if let Some(&i) = self.visible_done().get(selected) {
+                        self.trashed.push(self.done.remove(i).trash());
                     }
-                    KeyCode::Char('?') => self.show_help = !self.show_help,
-                    KeyCode::Char('e') => self.start_editing_desc(),
-                    KeyCode::Char('n') => self.start_editing_note(),
-                    KeyCode::Char('t') => self.start_editing_tags(),
-                    _ => {}
+                }
+            },
+            Action::Add => {
+                self.sort = None;
+                let i = self.tasks.len();
+                let mut task = TodoTask::new("");
+                self.ids.extend(self.tasks);
+                self.ids.extend(self.done);
+                self.ids.assign(&mut task);
+                self.tasks.push(task);
+                *self.table_state.selected_mut() = Some(i);
+                self.start_editing_desc()
+            }
+            Action::EditDesc => self.start_editing_desc(),
+            Action::EditNote => self.start_editing_note(),
+            Action::EditNoteExternal => self.editor_pending = true,
+            Action::EditTags => self.start_editing_tags(),
+            Action::CyclePriority => {
+                if let Some(i) = self.selected_task() {
+                    let task = &mut self.tasks[i];
+                    task.priority = task.priority.cycle();
+                }
+            }
+            Action::Sort => {
+                self.sort = match self.sort {
+                    None => Some(Sort::Created),
+                    Some(Sort::Created) => Some(Sort::Description),
+                    Some(Sort::Description) => None,
                 }
             }
+            Action::KeepSort => {
+                let order = self.open_order();
+                let mut tasks = std::mem::take(&mut self.tasks.0)
+                    .into_iter()
+                    .map(Some)
+                    .collect::<Vec<_>>();
+                self.tasks
+                    .extend(order.into_iter().filter_map(|i| tasks[i].take()));
+                self.sort = None;
+            }
+            Action::Search => self.searching = true,
+            Action::Reopen => {
+                if let Some(&i) = self.visible_done().get(selected) {
+                    let task = self.done.remove(i).reopen();
+                    self.tasks.push(task);
+                }
+            }
+            Action::Repeat | Action::Record | Action::Replay => (),
         }
-        Ok(())
+    }
+
+    /// The indices of the open tasks, in display order.
+    fn open_order(&self) -> Vec<usize> {
+        let mut order = (0..self.tasks.len()).collect::<Vec<_>>();
+        match self.sort {
+            None => (),
+            Some(Sort::Created) => order.sort_by_key(|&i| self.tasks[i].created()),
+            Some(Sort::Description) => {
+                order.sort_by_key(|&i| self.tasks[i].description.to_lowercase())
+            }
+        }
+        order
+    }
+
+    /// The index of the selected open task.
+    fn selected_task(&self) -> Option<usize> {
+        let i = self.table_state.selected()?;
+        self.open_order().get(i).copied()
+    }
+
+    /// The indices of the done tasks matching the search.
+    fn visible_done(&self) -> Vec<usize> {
+        let search = self.search.to_lowercase();
+        self.done
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| t.description.to_lowercase().contains(&search))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    fn handle_searching(&mut self, key_code: KeyCode) {
+        match key_code {
+            KeyCode::Enter => self.searching = false,
+            KeyCode::Esc => {
+                self.search.clear();
+                self.searching = false;
+            }
+            KeyCode::Backspace => {
+                self.search.pop();
+            }
+            KeyCode::Char(c) => self.search.push(c),
+            _ => {}
+        }
+        *self.done_state.selected_mut() = Some(0);
     }
 
     fn move_(&mut self, before: impl FnOnce(usize) -> usize) {
+        // reordering a sorted view would be confusing
+        if self.sort.is_some() {
+            return;
+        }
         if let Some(i) = self.table_state.selected() {
             let mut before = before(i);
             if i < before {
                 before = before.saturating_sub(1);
             }
-            let t = self.tasks.remove(i);
-            self.tasks.insert(before, t);
-            *self.table_state.selected_mut() = Some(before);
+            if self.tasks.reorder(i, before, self.top, &self.locked) {
+                *self.table_state.selected_mut() = Some(before);
+            }
         }
     }
 
     fn start_editing_desc(&mut self) {
-        let idx = self.table_state.selected().unwrap_or_default();
+        let idx = self.selected_task().unwrap_or_default();
         let val = self
             .tasks
             .get(idx)
@@ -248,7 +697,7 @@ impl<'a> Move<'a> {
     }
 
     fn start_editing_note(&mut self) {
-        let idx = self.table_state.selected().unwrap_or_default();
+        let idx = self.selected_task().unwrap_or_default();
         let val = self
             .tasks
             .get(idx)
@@ -258,12 +707,8 @@ impl<'a> Move<'a> {
     }
 
     fn start_editing_tags(&mut self) {
-        let idx = self.table_state.selected().unwrap_or_default();
-        let val = self
-            .tasks
-            .get(idx)
-            .map(|t| tag_csv(t.tags()))
-            .unwrap_or_default();
+        let idx = self.selected_task().unwrap_or_default();
+        let val = self.tasks.get(idx).map(label_csv).unwrap_or_default();
         self.editing = Editing::Tags { idx, val };
     }
 
@@ -283,7 +728,11 @@ impl<'a> Move<'a> {
                 }
                 Editing::Tags { idx, val } => {
                     if let Some(task) = self.tasks.get_mut(idx) {
-                        task.tags = val.split(',').map(String::from).collect();
+                        let (contexts, tags) = val
+                            .split(',')
+                            .partition::<Vec<_>, _>(|x| x.starts_with('@'));
+                        task.tags = tags.into_iter().map(String::from).collect();
+                        task.contexts = contexts.into_iter().map(|x| x[1..].to_string()).collect();
                     }
                 }
             },
@@ -298,54 +747,265 @@ impl<'a> Move<'a> {
     }
 
     fn render_table(&mut self, frame: &mut Frame) {
-        let table = Table::default()
+        let mut size = frame.size();
+        size.height = size.height.saturating_sub(1);
+        let absolute = self.absolute_dates;
+
+        match self.tab {
+            Tab::Open => {
+                let rows = self
+                    .open_order()
+                    .into_iter()
+                    .map(|i| {
+                        let t = &self.tasks[i];
+                        let pin = if t.is_pinned() { "📌" } else { "" };
+                        let num = format!("{pin}{} {}", t.priority.marker(), i + 1);
+                        Row::from_iter([
+                            match t.priority {
+                                Priority::High => Text::from(num).red(),
+                                Priority::Low => Text::from(num).blue(),
+                                Priority::Normal => Text::from(num),
+                            }
+                            .right_aligned(),
+                            self.editing.desc(i, t, self.styles.editing),
+                            self.editing.note(i, t, self.styles.editing),
+                            project_text(&self.projects, t.project.as_deref()),
+                            Text::from(
+                                t.progress()
+                                    .map(|(d, n)| format!("{d}/{n}"))
+                                    .unwrap_or_default(),
+                            )
+                            .centered(),
+                            Text::from(
+                                t.estimate()
+                                    .map(|e| humantime::format_duration(e).to_string())
+                                    .unwrap_or_default(),
+                            )
+                            .centered(),
+                            Text::from(when(absolute, t.created())).centered(),
+                            self.editing.tags(i, t, self.styles.editing),
+                        ])
+                        .height(note_height(t))
+                    })
+                    .collect();
+                let table = self.styles.table(
+                    [
+                        "Task#",
+                        "Description",
+                        "Note",
+                        "Project",
+                        "Check",
+                        "Est",
+                        "Created",
+                        "Tags",
+                    ],
+                    [
+                        Constraint::Length(5),
+                        Constraint::Percentage(30),
+                        Constraint::Percentage(25),
+                        Constraint::Length(12),
+                        Constraint::Length(5),
+                        Constraint::Length(7),
+                        Constraint::Length(10),
+                        Constraint::Fill(1),
+                    ],
+                    rows,
+                );
+                frame.render_stateful_widget(table, size, &mut self.table_state);
+            }
+            Tab::Done => {
+                let rows = self
+                    .visible_done()
+                    .into_iter()
+                    .map(|i| {
+                        let t = &self.done[i];
+                        Row::from_iter([
+                            Text::from(t.description.clone()).bold(),
+                            note_text(t),
+                            Text::from(when(absolute, t.created())).centered(),
+                            Text::from(
+                                t.finished_at()
+                                    .map(|x| when(absolute, x))
+                                    .unwrap_or_default(),
+                            )
+                            .centered(),
+                            Text::from(label_csv(t)),
+                        ])
+                        .height(note_height(t))
+                    })
+                    .collect();
+                let table = self.styles.table(
+                    ["Description", "Note", "Created", "Finished", "Tags"],
+                    [
+                        Constraint::Percentage(35),
+                        Constraint::Percentage(35),
+                        Constraint::Length(10),
+                        Constraint::Length(10),
+                        Constraint::Fill(1),
+                    ],
+                    rows,
+                );
+                frame.render_stateful_widget(table, size, &mut self.done_state);
+            }
+        }
+    }
+}
+
+/// A project, in its style.
+fn project_text<'a>(projects: &Projects, project: Option<&'a str>) -> Text<'a> {
+    let project = project.unwrap_or_default();
+    let text = Text::from(project);
+    match projects.colours(project) {
+        Some((fg, bg)) => text.style(Style {
+            fg: Some(tui_colour(fg)),
+            bg: bg.map(tui_colour),
+            ..Style::new()
+        }),
+        None => text,
+    }
+}
+
+/// The terminal colour as a `ratatui` colour.
+fn tui_colour(c: colored::Color) -> Color {
+    use colored::Color as C;
+    match c {
+        C::Black => Color::Black,
+        C::Red => Color::Red,
+        C::Green => Color::Green,
+        C::Yellow => Color::Yellow,
+        C::Blue => Color::Blue,
+        C::Magenta => Color::Magenta,
+        C::Cyan => Color::Cyan,
+        C::White => Color::Gray,
+        C::BrightBlack => Color::DarkGray,
+        C::BrightRed => Color::LightRed,
+        C::BrightGreen => Color::LightGreen,
+        C::BrightYellow => Color::LightYellow,
+        C::BrightBlue => Color::LightBlue,
+        C::BrightMagenta => Color::LightMagenta,
+        C::BrightCyan => Color::LightCyan,
+        C::BrightWhite => Color::White,
+        C::TrueColor { r, g, b } => Color::Rgb(r, g, b),
+    }
+}
+
+/// Styles resolved from the [`Theme`].
+struct Styles {
+    stripe: Option<Color>,
+    highlight: Style,
+    editing: Color,
+}
+
+impl Styles {
+    fn new(theme: &Theme) -> Self {
+        let colour = |x: &str| x.parse::<Color>().ok();
+        let stripe = theme.stripe.as_deref().and_then(|x| match x {
+            "auto" if light_background() => Some(Color::Indexed(254)),
+            "auto" => Some(Color::Indexed(236)),
+            x => colour(x),
+        });
+        let fg = theme.highlight_fg.as_deref().and_then(colour);
+        let bg = theme.highlight_bg.as_deref().and_then(colour);
+        let highlight = match (fg, bg) {
+            (None, None) => Style::new().reversed(),
+            (fg, bg) => Style {
+                fg,
+                bg,
+                ..Style::new()
+            },
+        };
+        let editing = colour(&theme.editing).unwrap_or(Color::Yellow);
+        Styles {
+            stripe,
+            highlight,
+            editing,
+        }
+    }
+
+    /// A table of tasks, shared by the open and done lists.
+    fn table<'a, const N: usize>(
+        &self,
+        header: [&'a str; N],
+        widths: [Constraint; N],
+        rows: Vec<Row<'a>>,
+    ) -> Table<'a> {
+        let rows = rows
+            .into_iter()
+            .enumerate()
+            .map(|(i, row)| match self.stripe {
+                Some(bg) if i % 2 == 1 => row.bg(bg),
+                _ => row,
+            });
+        Table::default()
             .header(
                 Row::new(
-                    ["Task#", "Description", "Note", "Created", "Tags"]
+                    header
                         .map(|t| Text::from(t).centered())
                         .map(Cell::from)
                         .to_vec(),
                 )
                 .style(Style::new().bold()),
             )
-            .widths(vec![
-                Constraint::Length(5),
-                Constraint::Percentage(35),
-                Constraint::Percentage(35),
-                Constraint::Length(10),
-                Constraint::Fill(1),
-            ])
-            .highlight_style(Style::new().reversed())
+            .widths(widths)
+            .highlight_style(self.highlight)
             .highlight_symbol(">>")
-            .rows(
-                self.tasks
-                    .iter()
-                    .enumerate()
-                    .map(|(i, t)| {
-                        let desc = self.editing.desc(i, t);
-                        let note = self.editing.note(i, t);
-                        let tags = self.editing.tags(i, t);
-                        let row = Row::from_iter([
-                            Text::from(format!("{}", i + 1)).right_aligned(),
-                            desc,
-                            note,
-                            Text::from(days_ago(t.duration_since_creation())).centered(),
-                            tags,
-                        ]);
-                        row
-                    })
-                    .collect::<Vec<_>>(),
-            );
+            .rows(rows)
+    }
+}
 
-        let mut size = frame.size();
-        size.height = size.height.saturating_sub(1);
-        frame.render_stateful_widget(table, size, &mut self.table_state);
+/// The task's note, followed by its links.
+fn note_text<S>(task: &Task<S>) -> Text<'static> {
+    let mut text = markdown_text(&task.note);
+    for link in &task.links {
+        text.push_line(Line::from(format!("🔗 {link}")).fg(Color::Blue));
     }
+    text
+}
+
+/// Rows are tall enough to show the whole note and links.
+fn note_height<S>(task: &Task<S>) -> u16 {
+    (task.note.lines().count() + task.links.len()).max(1) as u16
 }
 
-fn render_help(frame: &mut Frame) {
-    let rows = [
-        Row::from_iter([Text::from("⬆/⬇").right_aligned(), Text::from("Select row")]),
+/// Style a markdown note.
+fn markdown_text(note: &str) -> Text<'static> {
+    let lines = markdown::parse(note).into_iter().map(|line| {
+        Line::from_iter(line.into_iter().map(|span| {
+            let mut style = Style::new();
+            if span.style.bold {
+                style = style.bold();
+            }
+            if span.style.italic {
+                style = style.italic();
+            }
+            if span.style.code {
+                style = style.fg(Color::Cyan);
+            }
+            ratatui::text::Span::styled(span.text, style)
+        }))
+    });
+    Text::from_iter(lines)
+}
+
+/// Format a time as either a date, or how long ago it was.
+fn when(absolute: bool, secs: u64) -> String {
+    if absolute {
+        date(secs)
+    } else {
+        days_ago(Duration::from_secs(crate::now().saturating_sub(secs)))
+    }
+}
+
+/// Guess if the terminal has a light background, using the `COLORFGBG` convention.
+fn light_background() -> bool {
+    std::env::var("COLORFGBG")
+        .ok()
+        .and_then(|x| x.rsplit(';').next()?.parse::<u8>().ok())
+        .is_some_and(|bg| bg == 7 || bg >= 9)
+}
+
+fn render_help(frame: &mut Frame, tab: Tab) {
+    let open = [
         Row::from_iter([
             Text::from("+/-").right_aligned(),
             Text::from("Change priority"),
@@ -359,12 +1019,48 @@ fn render_help(frame: &mut Frame) {
             Text::from("Edit description"),
         ]),
         Row::from_iter([Text::from("n").right_aligned(), Text::from("Edit note")]),
+        Row::from_iter([
+            Text::from("E").right_aligned(),
+            Text::from("Note in $EDITOR"),
+        ]),
         Row::from_iter([Text::from("t").right_aligned(), Text::from("Edit tags")]),
+        Row::from_iter([
+            Text::from("p").right_aligned(),
+            Text::from("Cycle importance"),
+        ]),
         Row::from_iter([Text::from("a").right_aligned(), Text::from("Add new task")]),
         Row::from_iter([Text::from("D").right_aligned(), Text::from("Remove task")]),
+        Row::from_iter([Text::from("s").right_aligned(), Text::from("Cycle sort")]),
+        Row::from_iter([
+            Text::from("S").right_aligned(),
+            Text::from("Keep sorted order"),
+        ]),
+    ];
+    let done = [
+        Row::from_iter([Text::from("/").right_aligned(), Text::from("Search")]),
+        Row::from_iter([Text::from("r").right_aligned(), Text::from("Reopen task")]),
+        Row::from_iter([Text::from("D").right_aligned(), Text::from("Delete task")]),
+    ];
+    let rows = std::iter::once(Row::from_iter([
+        Text::from("⬆/⬇").right_aligned(),
+        Text::from("Select row"),
+    ]))
+    .chain(match tab {
+        Tab::Open => open.to_vec(),
+        Tab::Done => done.to_vec(),
+    })
+    .chain([
+        Row::from_iter([
+            Text::from(".").right_aligned(),
+            Text::from("Repeat last action"),
+        ]),
+        Row::from_iter([Text::from("m").right_aligned(), Text::from("Record macro")]),
+        Row::from_iter([Text::from("@").right_aligned(), Text::from("Replay macro")]),
+        Row::from_iter([Text::from("Tab").right_aligned(), Text::from("Switch list")]),
         Row::from_iter([Text::from("q").right_aligned(), Text::from("Save and exit")]),
         Row::from_iter([Text::from("X").right_aligned(), Text::from("Exit")]),
-    ];
+    ])
+    .collect::<Vec<_>>();
     let ws = [5, 19];
     let width: u16 = ws.iter().sum();
     let height = rows.len() as u16;
@@ -381,3 +1077,613 @@ fn render_help(frame: &mut Frame) {
     frame.render_widget(ratatui::widgets::Clear, size);
     frame.render_widget(table, size)
 }
+
+/// A single-key action in triage.
+enum Prompt {
+    None,
+    Tag(String),
+    Snooze(String),
+}
+
+/// What is being triaged.
+enum Mode {
+    /// The open tasks after those shown, promoted into the priority tasks.
+    Backlog,
+    /// The inbox, its items promoted out onto the end of the open list.
+    Inbox { promoted: TodoTasks },
+}
+
+/// Work through the backlog one task at a time.
+pub struct Triage<'a> {
+    tasks: &'a mut TodoTasks,
+    /// Tasks deleted, to be moved into the trash when saved.
+    pub trashed: TrashedTasks,
+    /// IDs of the tasks left to triage, in order.
+    queue: Vec<String>,
+    at: usize,
+    prompt: Prompt,
+    exit: Exit,
+    /// Feedback on the last action.
+    status: String,
+    /// IDs of the locked plan, if any, which tasks cannot be promoted into.
+    locked: Vec<String>,
+    /// How many priority tasks are shown, which promoted tasks join.
+    top: usize,
+    mode: Mode,
+}
+
+impl<'a> Triage<'a> {
+    /// Triage the tasks after the `top` shown.
+    pub fn new(tasks: &'a mut TodoTasks, top: usize) -> Self {
+        let queue = tasks
+            .iter()
+            .filter(|t| !t.is_hidden())
+            .skip(top)
+            .map(|t| t.id().to_string())
+            .collect();
+        Triage {
+            tasks,
+            trashed: Tasks::new(),
+            queue,
+            at: 0,
+            prompt: Prompt::None,
+            exit: Exit::Continue,
+            status: String::new(),
+            locked: Vec::new(),
+            top,
+            mode: Mode::Backlog,
+        }
+    }
+
+    /// Triage every inbox item, promoting them out of the inbox rather than into the six.
+    pub fn inbox(inbox: &'a mut TodoTasks) -> Self {
+        let queue = inbox.iter().map(|t| t.id().to_string()).collect();
+        Triage {
+            queue,
+            mode: Mode::Inbox {
+                promoted: Tasks::new(),
+            },
+            ..Triage::new(inbox, 0)
+        }
+    }
+
+    /// Triage the tasks outside a locked plan, refusing promotions.
+    pub fn lock(mut self, locked: Vec<String>) -> Self {
+        if !locked.is_empty() {
+            self.queue = self
+                .tasks
+                .iter()
+                .filter(|t| !t.is_hidden() && !locked.contains(&t.id().to_string()))
+                .map(|t| t.id().to_string())
+                .collect();
+        }
+        self.locked = locked;
+        self
+    }
+
+    /// Take the inbox items promoted, in the order they were promoted.
+    pub fn take_promoted(&mut self) -> TodoTasks {
+        match &mut self.mode {
+            Mode::Inbox { promoted } => std::mem::replace(promoted, Tasks::new()),
+            Mode::Backlog => Tasks::new(),
+        }
+    }
+
+    /// The tasks left after triage.
+    pub fn into_tasks(self) -> &'a mut TodoTasks {
+        self.tasks
+    }
+
+    pub fn run(&mut self) -> Result<bool> {
+        if self.queue.is_empty() {
+            match self.mode {
+                Mode::Inbox { .. } => println!("The inbox is empty"),
+                Mode::Backlog => println!("The backlog is empty"),
+            }
+            return Ok(false);
+        }
+        let mut term = term_init().into_diagnostic()?;
+        let res = self.run_loop(&mut term);
+        term_restore().into_diagnostic()?;
+        res.map(|_| self.exit == Exit::Save).into_diagnostic()
+    }
+
+    fn run_loop(&mut self, terminal: &mut Tui) -> io::Result<()> {
+        while self.exit == Exit::Continue {
+            terminal.draw(|frame| self.render(frame))?;
+            if let Event::Key(key_ev) = event::read()? {
+                if key_ev.kind == KeyEventKind::Press {
+                    self.handle(key_ev.code);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn current(&self) -> Option<usize> {
+        let id = self.queue.get(self.at)?;
+        self.tasks.iter().position(|t| t.id() == id)
+    }
+
+    /// The task being triaged, if any are left.
+    pub fn task(&self) -> Option<&TodoTask> {
+        self.current().map(|i| &self.tasks[i])
+    }
+
+    fn next(&mut self) {
+        self.at += 1;
+        if self.at >= self.queue.len() {
+            self.exit = Exit::Save;
+        }
+    }
+
+    /// Drop the current task from the queue, as it has been triaged out of it.
+    fn done(&mut self) {
+        self.queue.remove(self.at);
+        if self.at >= self.queue.len() {
+            self.exit = Exit::Save;
+        }
+    }
+
+    /// Act on a key press.
+    pub fn handle(&mut self, key: KeyCode) {
+        let Some(i) = self.current() else {
+            self.exit = Exit::Save;
+            return;
+        };
+        let inbox = matches!(self.mode, Mode::Inbox { .. });
+        match (&mut self.prompt, key) {
+            (Prompt::Tag(x) | Prompt::Snooze(x), KeyCode::Char(c)) => x.push(c),
+            (Prompt::Tag(x) | Prompt::Snooze(x), KeyCode::Backspace) => {
+                x.pop();
+            }
+            (Prompt::Tag(_) | Prompt::Snooze(_), KeyCode::Esc) => self.prompt = Prompt::None,
+            (Prompt::Tag(x), KeyCode::Enter) => {
+                let tags = std::mem::take(x);
+                for word in tags.split_whitespace() {
+                    match word.parse::<crate::tags::Label>() {
+                        Ok(label) => label.apply(&mut self.tasks[i]),
+                        Err(_) => self.tasks[i].add_tag(word),
+                    }
+                }
+                self.status = format!("Tagged {tags}");
+                self.prompt = Prompt::None;
+            }
+            (Prompt::Snooze(x), KeyCode::Enter) => match humantime::parse_duration(x) {
+                Ok(d) => {
+                    self.tasks[i].snooze(d);
+                    self.status = format!("Snoozed for {x}");
+                    self.prompt = Prompt::None;
+                    self.done();
+                }
+                Err(e) => self.status = format!("Invalid duration: {e}"),
+            },
+            (Prompt::Tag(_) | Prompt::Snooze(_), _) => (),
+            (Prompt::None, KeyCode::Char('p')) if inbox => {
+                let task = self.tasks.remove(i);
+                self.status = format!("Promoted '{}'", task.description);
+                if let Mode::Inbox { promoted } = &mut self.mode {
+                    promoted.push(task);
+                }
+                self.done();
+            }
+            (Prompt::None, KeyCode::Char('p')) if !self.locked.is_empty() => {
+                self.status = "🔒 Today's plan is locked, run `ivly plan unlock` first".into();
+            }
+            (Prompt::None, KeyCode::Char('p')) => {
+                // promote into the last of the priority tasks
+                let task = self.tasks.remove(i);
+                let slot = self
+                    .tasks
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, t)| !t.is_hidden())
+                    .nth(self.top.saturating_sub(1))
+                    .map(|(i, _)| i)
+                    .unwrap_or(self.tasks.len());
+                self.status = format!("Promoted '{}'", task.description);
+                self.tasks.insert(slot, task);
+                self.done();
+            }
+            (Prompt::None, KeyCode::Char('t')) => self.prompt = Prompt::Tag(String::new()),
+            // an inbox item is promoted or deleted, not snoozed
+            (Prompt::None, KeyCode::Char('s')) if !inbox => {
+                self.prompt = Prompt::Snooze(String::new())
+            }
+            (Prompt::None, KeyCode::Char('d')) => {
+                let task = self.tasks.remove(i);
+                self.status = format!("Deleted '{}'", task.description);
+                self.trashed.push(task.trash());
+                self.done();
+            }
+            (Prompt::None, KeyCode::Char(' ') | KeyCode::Char('n') | KeyCode::Right) => {
+                self.status.clear();
+                self.next();
+            }
+            (Prompt::None, KeyCode::Left) => self.at = self.at.saturating_sub(1),
+            (Prompt::None, KeyCode::Char('q')) => self.exit = Exit::Save,
+            (Prompt::None, KeyCode::Char('X')) => self.exit = Exit::Forget,
+            (Prompt::None, _) => (),
+        }
+    }
+
+    fn render(&self, frame: &mut Frame) {
+        let size = frame.size();
+        let Some(task) = self.task() else {
+            return;
+        };
+
+        let mut text = Text::from(Line::from(format!(
+            "{} {} of {}",
+            match self.mode {
+                Mode::Inbox { .. } => "Inbox item",
+                Mode::Backlog => "Backlog task",
+            },
+            self.at + 1,
+            self.queue.len()
+        )))
+        .fg(Color::DarkGray);
+        text.push_line("");
+        text.push_line(Line::from(task.description.clone()).bold());
+        for line in markdown_text(&task.note) {
+            text.push_line(line);
+        }
+        text.push_line("");
+        text.push_line(format!(
+            "Created {}   {}",
+            when(false, task.created()),
+            label_csv(task)
+        ));
+        text.push_line("");
+        text.push_line(match &self.prompt {
+            Prompt::None => Line::from(self.status.clone()).fg(Color::Yellow),
+            Prompt::Tag(x) => Line::from(format!("Tags: {x}")).fg(Color::Yellow),
+            Prompt::Snooze(x) => Line::from(format!("Snooze for (eg 3d): {x}")).fg(Color::Yellow),
+        });
+        frame.render_widget(
+            ratatui::widgets::Paragraph::new(text).block(
+                Block::default()
+                    .borders(ratatui::widgets::Borders::ALL)
+                    .title(" Triage "),
+            ),
+            Rect {
+                height: size.height.saturating_sub(1),
+                ..size
+            },
+        );
+
+        let instructions = match (&self.prompt, &self.mode) {
+            (Prompt::None, Mode::Backlog) => {
+                "p Promote  t Tag  s Snooze  d Delete  Space Skip  ← Back  q Save and exit  X Exit"
+            }
+            (Prompt::None, Mode::Inbox { .. }) => {
+                "p Promote  t Tag  d Delete  Space Skip  ← Back  q Save and exit  X Exit"
+            }
+            _ => "Enter to accept  Esc to cancel",
+        };
+        frame.render_widget(
+            Text::from(instructions).centered(),
+            Rect {
+                y: size.height.saturating_sub(1),
+                height: 1,
+                ..size
+            },
+        );
+    }
+}
+
+/// Pick an open task by fuzzy searching the descriptions.
+pub struct Picker<'a> {
+    tasks: &'a TodoTasks,
+    query: String,
+    /// Indices of the matching tasks, best first.
+    matches: Vec<usize>,
+    state: TableState,
+    exit: Exit,
+}
+
+impl<'a> Picker<'a> {
+    pub fn new(tasks: &'a TodoTasks) -> Self {
+        let mut p = Picker {
+            tasks,
+            query: String::new(),
+            matches: Vec::new(),
+            state: TableState::default().with_selected(0),
+            exit: Exit::Continue,
+        };
+        p.filter();
+        p
+    }
+
+    /// The index of the picked task, if one was.
+    pub fn run(&mut self) -> Result<Option<usize>> {
+        if self.tasks.is_empty() {
+            return Ok(None);
+        }
+        let mut term = term_init().into_diagnostic()?;
+        let res = self.run_loop(&mut term);
+        term_restore().into_diagnostic()?;
+        res.into_diagnostic()?;
+        Ok(self.picked())
+    }
+
+    /// The index of the picked task, once Enter is pressed on a match.
+    pub fn picked(&self) -> Option<usize> {
+        match self.exit {
+            Exit::Save => self
+                .state
+                .selected()
+                .and_then(|i| self.matches.get(i).copied()),
+            _ => None,
+        }
+    }
+
+    fn run_loop(&mut self, terminal: &mut Tui) -> io::Result<()> {
+        while self.exit == Exit::Continue {
+            terminal.draw(|frame| self.render(frame))?;
+            if let Event::Key(key_ev) = event::read()? {
+                if key_ev.kind == KeyEventKind::Press {
+                    self.handle(key_ev.code);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn filter(&mut self) {
+        let mut scored = self
+            .tasks
+            .iter()
+            .enumerate()
+            .filter_map(|(i, t)| {
+                Some((crate::similar::fuzzy_score(&self.query, &t.description)?, i))
+            })
+            .collect::<Vec<_>>();
+        // best first, ties in list order
+        scored.sort_by_key(|(score, i)| (std::cmp::Reverse(*score), *i));
+        self.matches = scored.into_iter().map(|(_, i)| i).collect();
+        *self.state.selected_mut() = Some(0);
+    }
+
+    /// Act on a key press.
+    pub fn handle(&mut self, key: KeyCode) {
+        match key {
+            KeyCode::Esc => self.exit = Exit::Forget,
+            KeyCode::Enter if !self.matches.is_empty() => self.exit = Exit::Save,
+            KeyCode::Up => {
+                let i = self.state.selected().unwrap_or_default();
+                *self.state.selected_mut() = Some(i.saturating_sub(1));
+            }
+            KeyCode::Down => {
+                let i = self.state.selected().unwrap_or_default();
+                let last = self.matches.len().saturating_sub(1);
+                *self.state.selected_mut() = Some((i + 1).min(last));
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.filter();
+            }
+            KeyCode::Char(c) => {
+                self.query.push(c);
+                self.filter();
+            }
+            _ => (),
+        }
+    }
+
+    fn render(&mut self, frame: &mut Frame) {
+        let size = frame.size();
+        frame.render_widget(
+            Line::from(format!("🔎 {}", self.query)).fg(Color::Yellow),
+            Rect { height: 1, ..size },
+        );
+
+        let rows = self.matches.iter().map(|&i| {
+            let t = &self.tasks[i];
+            Row::new([
+                Cell::from(format!("{}.", i + 1)).fg(Color::DarkGray),
+                Cell::from(t.description.clone()),
+                Cell::from(t.id().to_string()).fg(Color::DarkGray),
+            ])
+        });
+        let widths = [
+            Constraint::Length(5),
+            Constraint::Fill(1),
+            Constraint::Length(16),
+        ];
+        frame.render_stateful_widget(
+            Table::new(rows, widths).highlight_style(Style::new().reversed()),
+            Rect {
+                y: 2,
+                height: size.height.saturating_sub(3),
+                ..size
+            },
+            &mut self.state,
+        );
+
+        frame.render_widget(
+            Text::from("Type to search  ↑↓ Select  Enter Pick  Esc Cancel").centered(),
+            Rect {
+                y: size.height.saturating_sub(1),
+                height: 1,
+                ..size
+            },
+        );
+    }
+}
+
+/// Walk through pairs of open tasks with similar descriptions, merging or deleting them.
+pub struct Dedupe<'a> {
+    tasks: &'a mut TodoTasks,
+    /// Tasks deleted, to be moved into the trash when saved.
+    pub trashed: TrashedTasks,
+    /// Descriptions at least this similar are paired.
+    threshold: f64,
+    /// The indices of the pair shown, if any are left.
+    pair: Option<(usize, usize)>,
+    exit: Exit,
+    /// Feedback on the last action.
+    status: String,
+    changed: bool,
+}
+
+impl<'a> Dedupe<'a> {
+    pub fn new(tasks: &'a mut TodoTasks, threshold: f64) -> Self {
+        let mut d = Dedupe {
+            tasks,
+            trashed: Tasks::new(),
+            threshold,
+            pair: None,
+            exit: Exit::Continue,
+            status: String::new(),
+            changed: false,
+        };
+        d.find(0, 1);
+        d
+    }
+
+    /// Whether there are changes to save.
+    pub fn run(&mut self) -> Result<bool> {
+        if self.pair.is_none() {
+            println!("No similar tasks found");
+            return Ok(false);
+        }
+        let mut term = term_init().into_diagnostic()?;
+        let res = self.run_loop(&mut term);
+        term_restore().into_diagnostic()?;
+        res.map(|_| self.exit == Exit::Save && self.changed)
+            .into_diagnostic()
+    }
+
+    fn run_loop(&mut self, terminal: &mut Tui) -> io::Result<()> {
+        while self.exit == Exit::Continue {
+            terminal.draw(|frame| self.render(frame))?;
+            if let Event::Key(key_ev) = event::read()? {
+                if key_ev.kind == KeyEventKind::Press {
+                    self.handle(key_ev.code);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The indices of the pair shown, if any are left.
+    pub fn pair(&self) -> Option<(usize, usize)> {
+        self.pair
+    }
+
+    /// Show the next similar pair, starting the search at `(i, j)`.
+    fn find(&mut self, mut i: usize, mut j: usize) {
+        let similarity =
+            |a: &TodoTask, b: &TodoTask| crate::similar::similarity(&a.description, &b.description);
+        while i < self.tasks.len() {
+            while j < self.tasks.len() {
+                if similarity(&self.tasks[i], &self.tasks[j]) >= self.threshold {
+                    self.pair = Some((i, j));
+                    return;
+                }
+                j += 1;
+            }
+            i += 1;
+            j = i + 1;
+        }
+        self.pair = None;
+        self.exit = Exit::Save;
+    }
+
+    /// Act on a key press.
+    pub fn handle(&mut self, key: KeyCode) {
+        let Some((i, j)) = self.pair() else {
+            self.exit = Exit::Save;
+            return;
+        };
+        match key {
+            KeyCode::Char('m') => {
+                let b = self.tasks.remove(j);
+                self.tasks[i].merge(b);
+                self.status = format!("Merged into '{}'", self.tasks[i].description);
+                self.changed = true;
+                self.find(i, j);
+            }
+            KeyCode::Char('1') | KeyCode::Char('2') => {
+                let at = if key == KeyCode::Char('1') { i } else { j };
+                let task = self.tasks.remove(at);
+                self.status = format!("Deleted '{}'", task.description);
+                self.trashed.push(task.trash());
+                self.changed = true;
+                if at == i {
+                    self.find(i, i + 1);
+                } else {
+                    self.find(i, j);
+                }
+            }
+            KeyCode::Char('k') | KeyCode::Char(' ') | KeyCode::Right => {
+                self.status = "Kept both".into();
+                self.find(i, j + 1);
+            }
+            KeyCode::Char('q') => self.exit = Exit::Save,
+            KeyCode::Char('X') => self.exit = Exit::Forget,
+            _ => (),
+        }
+    }
+
+    fn render(&self, frame: &mut Frame) {
+        let size = frame.size();
+        let Some((i, j)) = self.pair() else {
+            return;
+        };
+
+        let card = |n: usize, title: &str| {
+            let task = &self.tasks[n];
+            let mut text = Text::from(Line::from(task.description.clone()).bold());
+            for line in markdown_text(&task.note) {
+                text.push_line(line);
+            }
+            text.push_line("");
+            text.push_line(format!(
+                "Created {}   {}",
+                when(false, task.created()),
+                label_csv(task)
+            ));
+            text.push_line(Line::from(task.id().to_string()).fg(Color::DarkGray));
+            ratatui::widgets::Paragraph::new(text)
+                .wrap(ratatui::widgets::Wrap { trim: false })
+                .block(
+                    Block::default()
+                        .borders(ratatui::widgets::Borders::ALL)
+                        .title(format!(" {title}: task {} ", n + 1)),
+                )
+        };
+        let similarity =
+            crate::similar::similarity(&self.tasks[i].description, &self.tasks[j].description);
+        frame.render_widget(
+            Line::from(format!("{:.0}% similar", similarity * 100.0)).fg(Color::DarkGray),
+            Rect { height: 1, ..size },
+        );
+        let panes = Layout::horizontal([Constraint::Fill(1), Constraint::Fill(1)]).split(Rect {
+            y: 1,
+            height: size.height.saturating_sub(3),
+            ..size
+        });
+        frame.render_widget(card(i, "1st"), panes[0]);
+        frame.render_widget(card(j, "2nd"), panes[1]);
+
+        frame.render_widget(
+            Line::from(self.status.clone()).fg(Color::Yellow),
+            Rect {
+                y: size.height.saturating_sub(2),
+                height: 1,
+                ..size
+            },
+        );
+        frame.render_widget(
+            Text::from("m Merge  k Keep both  1 Delete 1st  2 Delete 2nd  q Save and exit  X Exit")
+                .centered(),
+            Rect {
+                y: size.height.saturating_sub(1),
+                height: 1,
+                ..size
+            },
+        );
+    }
+}